@@ -29,8 +29,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Set up function factory with tools
     let mut function_factory = FunctionFactory::new();
-    function_factory.register_tool(CalculatorTool::new());
-    function_factory.register_tool(WeatherTool::new());
+    function_factory.register_tool(CalculatorTool::new())?;
+    function_factory.register_tool(WeatherTool::new())?;
 
     println!("🛠️  Registered tools: Calculator, Weather");
 
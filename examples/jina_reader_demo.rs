@@ -10,8 +10,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let jina_key = std::env::var("JINA_API_KEY")?;
 
     let mut factory = FunctionFactory::new();
-    factory.register_tool(CalculatorTool::new());
-    factory.register_tool(JinaReaderTool::new(jina_key));
+    factory.register_tool(CalculatorTool::new())?;
+    factory.register_tool(JinaReaderTool::new(jina_key))?;
 
     let agent = Agent::new(api_key, factory).with_max_iterations(6);
 
@@ -70,6 +70,28 @@ tiny_agent_rs::tool!(
     }
 );
 
+// A tool that needs shared state (an HTTP client, a DB pool, an API key) declares it with
+// `state`; the generated struct is built with `new(state)`
+#[derive(Debug, Deserialize, JsonSchema)]
+struct GreetingParams {
+    /// Name of the person to greet
+    name: String,
+}
+
+struct GreetingConfig {
+    greeting: String,
+}
+
+tiny_agent_rs::tool!(
+    name = "greeting",
+    description = "Greet someone with the configured greeting",
+    params = GreetingParams,
+    state = GreetingConfig,
+    |config: &GreetingConfig, params: GreetingParams| async move {
+        Ok(json!({ "message": format!("{}, {}!", config.greeting, params.name) }))
+    }
+);
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load API key
@@ -77,15 +99,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Register the macro-generated tools
     let mut factory = FunctionFactory::new();
-    factory.register_tool(TextTransform);
-    factory.register_tool(MathCalculator);
+    factory.register_tool(TextTransform)?;
+    factory.register_tool(MathCalculator)?;
+    factory.register_tool(Greeting::new(GreetingConfig {
+        greeting: "Hello".to_string(),
+    }))?;
 
     let _agent = Agent::new(api_key, factory).with_max_iterations(3);
 
     println!("=== Macro Tool Example ===\n");
     println!("Available tools:");
     println!("1. text_transform - Transform text (uppercase/lowercase/reverse)");
-    println!("2. math_calculator - Basic math operations\n");
+    println!("2. math_calculator - Basic math operations");
+    println!("3. greeting - Greet someone (stateful tool)\n");
 
     // Demonstrate the tools work correctly
     println!("Testing text_transform tool:");
@@ -0,0 +1,50 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+use tiny_agent_rs::{
+    tools::{CalculatorTool, WeatherTool},
+    FunctionFactory, McpServer,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ShoutParams {
+    /// Text to shout
+    text: String,
+}
+
+tiny_agent_rs::tool!(
+    name = "shout",
+    description = "Uppercase the given text",
+    params = ShoutParams,
+    |params: ShoutParams| async move { Ok(json!({ "result": params.text.to_uppercase() })) }
+);
+
+/// Serve the built-in tools plus a `tool!` tool over MCP.
+///
+/// `cargo run --example mcp_server` speaks stdio; pass `--http 127.0.0.1:3000` to serve
+/// streamable HTTP at `/mcp` instead.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(CalculatorTool::new())?;
+    factory.register_tool(WeatherTool::new())?;
+    factory.register_tool(Shout)?;
+
+    let server = McpServer::new(factory).with_server_info("tiny-agent-example", "0.1.0");
+
+    let args: Vec<String> = std::env::args().collect();
+    match args.iter().position(|arg| arg == "--http") {
+        Some(index) => {
+            let addr = args
+                .get(index + 1)
+                .map(String::as_str)
+                .unwrap_or("127.0.0.1:3000")
+                .parse()?;
+            eprintln!("Serving MCP over HTTP at http://{}/mcp", addr);
+            server.serve_http(addr).await?;
+        }
+        None => server.serve_stdio().await?,
+    }
+
+    Ok(())
+}
@@ -20,8 +20,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Set up function factory with tools
     let mut function_factory = FunctionFactory::new();
-    function_factory.register_tool(CalculatorTool::new());
-    function_factory.register_tool(WeatherTool::new());
+    function_factory.register_tool(CalculatorTool::new())?;
+    function_factory.register_tool(WeatherTool::new())?;
 
     // Create agent with real LLM
     let agent = Agent::new(api_key, function_factory)
@@ -7,7 +7,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Set up tools
     let mut factory = FunctionFactory::new();
-    factory.register_tool(CalculatorTool::new());
+    factory.register_tool(CalculatorTool::new())?;
 
     // Create agent with step-based execution enabled
     let agent = Agent::new(api_key, factory).with_max_iterations(5);
@@ -92,8 +92,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let jina_key = std::env::var("JINA_API_KEY")?;
 
     let mut factory = FunctionFactory::new();
-    factory.register_tool(JinaReaderTool::new(jina_key));
-    factory.register_tool(BudgetCalculator);
+    factory.register_tool(JinaReaderTool::new(jina_key))?;
+    factory.register_tool(BudgetCalculator)?;
 
     let model = std::env::var("MODEL").unwrap_or_else(|_| "openai/gpt-4.1".to_string());
     let agent = Agent::new(api_key, factory)
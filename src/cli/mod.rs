@@ -1,29 +1,189 @@
 use crate::{
-    tools::{CalculatorTool, WeatherTool},
-    Agent, FunctionFactory,
+    server::AgentServer, Agent, AgentError, AgentStep, Redactor, RunResult, SchemaHandle,
+    ToolErrorKind,
 };
-use clap::{Arg, Command};
+use clap::{error::ErrorKind, parser::ValueSource, Arg, ArgAction, ArgMatches, Command};
 use dotenvy;
-use std::env;
+use serde_json::{json, Value};
+use std::{env, fs::OpenOptions, io::Write, sync::Mutex};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    task::JoinHandle,
+};
 use tracing::{error, info};
 
+pub mod profile;
+pub mod repl;
+
+use profile::{AgentProfile, ProfileFile};
+
 /// CLI entry point for the tiny-agent tool
 pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
     // Load environment variables from .env file
     dotenvy::dotenv().ok();
-    tracing_subscriber::fmt::init();
+    // Logs go to stderr so stdout only carries the response
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    let matches = command().get_matches();
+    // Flags may come before a subcommand, so a stray prompt is caught here instead of by clap
+    if let (Some(subcommand), Some(_)) = (
+        matches.subcommand_name(),
+        matches.get_one::<String>("prompt"),
+    ) {
+        command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!("a prompt cannot be combined with `{}`", subcommand),
+            )
+            .exit();
+    }
+
+    let agent = build_agent(&matches).await?;
+    let output = OutputOptions::from_matches(&matches);
+
+    if let Some(serve) = matches.subcommand_matches("serve") {
+        let addr: std::net::SocketAddr = serve.get_one::<String>("addr").unwrap().parse()?;
+        info!("Serving agent at http://{}", addr);
+        serve_agent(agent, &output)?.serve(addr).await?;
+        return Ok(());
+    }
+
+    if let Some(chat) = matches.subcommand_matches("chat") {
+        let session = chat.get_one::<String>("session").map(String::as_str);
+        return repl::run(agent, session, output).await;
+    }
+
+    // Run the agent
+    let prompt = matches.get_one::<String>("prompt").unwrap();
+    let shown_prompt = match agent.redactor() {
+        Some(redactor) => redactor.redact_text(prompt),
+        None => prompt.clone(),
+    };
+    info!("Running agent with prompt: {}", shown_prompt);
+    info!("Using model: {}", agent.model());
 
-    let matches = Command::new("tiny-agent")
+    // Collect steps as they happen so a failed run still leaves a trace
+    let (step_sender, collector) = collect_steps(output.verbose);
+    let outcome = agent.run_with_step_sender(prompt, step_sender).await;
+    let steps = collector.await?;
+
+    if let Some(path) = &output.trace_file {
+        write_trace(path, outcome.as_ref(), &steps)?;
+    }
+
+    match outcome {
+        Ok(result) => {
+            print_result(&result, output.json)?;
+            info!("Agent execution completed successfully");
+            Ok(())
+        }
+        Err(e) => {
+            error!("Agent execution failed: {}", e);
+            if output.json {
+                println!("{}", serde_json::to_string_pretty(&e.to_error_payload())?);
+            } else {
+                eprintln!("Error [{}]: {}", e.error_code(), e);
+            }
+            std::process::exit(exit_code(&e));
+        }
+    }
+}
+
+/// Command-line interface: a one-off run by default, or the `serve` and `chat` subcommands
+fn command() -> Command {
+    Command::new("tiny-agent")
         .version("0.1.0")
         .about("A lightweight Rust agent for LLM tool calling with OpenRouter")
+        .subcommand_negates_reqs(true)
         .arg(
             Arg::new("prompt")
                 .help("The prompt to send to the agent")
                 .required(true)
                 .index(1),
         )
+        .arg(
+            Arg::new("output")
+                .global(true)
+                .short('o')
+                .long("output")
+                .value_name("FORMAT")
+                .help("Print the response (each reply in chat) as text, or the full RunResult as JSON")
+                .value_parser(["text", "json"])
+                .default_value("text"),
+        )
+        .arg(
+            Arg::new("trace-file")
+                .global(true)
+                .long("trace-file")
+                .value_name("FILE")
+                .help("Write the full run trace as JSON, also when the run fails; chat rewrites it every turn and serve appends one line per run"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .global(true)
+                .short('v')
+                .long("verbose")
+                .help("Print each step to stderr as it happens")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("redact")
+                .global(true)
+                .long("redact")
+                .help("Mask API keys and tokens in logs, traces and printed steps")
+                .action(ArgAction::SetTrue),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Expose the agent as an HTTP API (OpenAI-compatible chat completions and /runs)")
+                .arg(
+                    Arg::new("addr")
+                        .long("addr")
+                        .value_name("ADDR")
+                        .help("Address to listen on")
+                        .default_value("127.0.0.1:8080"),
+                ),
+        )
+        .subcommand(
+            Command::new("chat")
+                .about("Chat interactively, keeping one conversation across turns")
+                .arg(
+                    Arg::new("session")
+                        .long("session")
+                        .value_name("FILE")
+                        .help("Resume a conversation saved with /save"),
+                ),
+        )
+        .arg(
+            Arg::new("config")
+                .global(true)
+                .short('c')
+                .long("config")
+                .value_name("FILE")
+                .help("Load agent settings from a TOML or YAML profile file; flags override it"),
+        )
+        .arg(
+            Arg::new("profile")
+                .global(true)
+                .short('p')
+                .long("profile")
+                .value_name("NAME")
+                .requires("config")
+                .help("Profile to use from the --config file"),
+        )
+        .arg(
+            Arg::new("schema")
+                .global(true)
+                .short('s')
+                .long("schema")
+                .value_name("FILE")
+                .help("JSON Schema file the final answer must match (structured output)"),
+        )
         .arg(
             Arg::new("model")
+                .global(true)
                 .short('m')
                 .long("model")
                 .value_name("MODEL")
@@ -32,6 +192,7 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
         )
         .arg(
             Arg::new("api-key")
+                .global(true)
                 .short('k')
                 .long("api-key")
                 .value_name("KEY")
@@ -39,6 +200,7 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
         )
         .arg(
             Arg::new("base-url")
+                .global(true)
                 .short('u')
                 .long("base-url")
                 .value_name("URL")
@@ -48,6 +210,7 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
         )
         .arg(
             Arg::new("timeout")
+                .global(true)
                 .short('t')
                 .long("timeout")
                 .value_name("SECONDS")
@@ -56,66 +219,321 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
         )
         .arg(
             Arg::new("max-iterations")
+                .global(true)
                 .short('i')
                 .long("max-iterations")
                 .value_name("COUNT")
                 .help("Maximum agent iterations")
                 .default_value("10"),
         )
-        .get_matches();
+}
 
-    // Get API key from argument or environment
-    let api_key = matches
-        .get_one::<String>("api-key")
-        .cloned()
+fn print_result(result: &RunResult, json_output: bool) -> Result<(), serde_json::Error> {
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(result)?);
+    } else {
+        println!("\nAgent Response:\n{}", result.output);
+        if let Some(structured) = result.structured() {
+            println!(
+                "\nStructured Output:\n{}",
+                serde_json::to_string_pretty(structured)?
+            );
+        }
+    }
+    Ok(())
+}
+
+/// How runs are reported: `--output`, `--verbose` and `--trace-file`, which apply to single
+/// runs, `chat` and `serve` alike
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// Print the full `RunResult` (or error payload) as JSON instead of the response text
+    pub json: bool,
+    /// Print each step to stderr as it happens
+    pub verbose: bool,
+    /// File that receives the trace of every run
+    pub trace_file: Option<String>,
+}
+
+impl OutputOptions {
+    fn from_matches(matches: &ArgMatches) -> Self {
+        Self {
+            json: matches
+                .get_one::<String>("output")
+                .is_some_and(|format| format == "json"),
+            verbose: matches.get_flag("verbose"),
+            trace_file: matches.get_one::<String>("trace-file").cloned(),
+        }
+    }
+}
+
+/// Server for `serve`, printing steps with `--verbose` and appending one trace line per run to
+/// the `--trace-file`
+fn serve_agent(
+    agent: Agent,
+    output: &OutputOptions,
+) -> Result<AgentServer, Box<dyn std::error::Error>> {
+    let mut server = AgentServer::new(agent);
+    if output.verbose {
+        server = server.with_step_observer(|step| eprintln!("{}", step.describe()));
+    }
+    if let Some(path) = &output.trace_file {
+        let file = Mutex::new(OpenOptions::new().create(true).append(true).open(path)?);
+        let path = path.clone();
+        server = server.with_run_observer(move |outcome, steps| {
+            let line = run_trace(outcome, steps).and_then(|trace| serde_json::to_string(&trace));
+            let written = match line {
+                Ok(line) => {
+                    writeln!(file.lock().unwrap(), "{}", line).map_err(|err| err.to_string())
+                }
+                Err(err) => Err(err.to_string()),
+            };
+            if let Err(err) = written {
+                error!("Failed to write trace to {}: {}", path, err);
+            }
+        });
+    }
+    Ok(server)
+}
+
+/// Step sender for a run, and a task that collects the steps (printing them to stderr when
+/// `verbose`) until the run drops the sender
+fn collect_steps(verbose: bool) -> (UnboundedSender<AgentStep>, JoinHandle<Vec<AgentStep>>) {
+    let (step_sender, mut step_receiver) = unbounded_channel::<AgentStep>();
+    let collector = tokio::spawn(async move {
+        let mut steps = Vec::new();
+        while let Some(step) = step_receiver.recv().await {
+            if verbose {
+                eprintln!("{}", step.describe());
+            }
+            steps.push(step);
+        }
+        steps
+    });
+    (step_sender, collector)
+}
+
+/// Write the trace of a run, successful or not, to `path` as pretty-printed JSON
+fn write_trace(
+    path: &str,
+    outcome: Result<&RunResult, &AgentError>,
+    steps: &[AgentStep],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let trace = run_trace(outcome, steps)?;
+    std::fs::write(path, serde_json::to_string_pretty(&trace)?)?;
+    Ok(())
+}
+
+/// Trace of a run: the full `RunResult`, or for a failed run the steps it streamed
+fn run_trace(
+    outcome: Result<&RunResult, &AgentError>,
+    steps: &[AgentStep],
+) -> Result<Value, serde_json::Error> {
+    match outcome {
+        Ok(result) => serde_json::to_value(result),
+        Err(error) => Ok(failure_trace(steps, error)),
+    }
+}
+
+/// Trace of a run that ended in an error: the steps taken so far and the error payload
+fn failure_trace(steps: &[AgentStep], error: &AgentError) -> Value {
+    json!({
+        "steps": steps,
+        "error": error.to_error_payload()["error"],
+    })
+}
+
+/// Process exit code for a failed run, one per [`AgentError::error_code`].
+///
+/// 1 is left for errors outside the agent (bad input files, I/O) and 2 for usage errors.
+pub fn exit_code(error: &AgentError) -> i32 {
+    match error {
+        AgentError::Config(_) => 3,
+        AgentError::OpenAI(_) => 4,
+        AgentError::Serialization(_) => 5,
+        AgentError::Validation(_) => 6,
+        AgentError::ToolExecution(_) => 7,
+        AgentError::ToolFailed(error) => match error.kind {
+            ToolErrorKind::Fatal => 15,
+            ToolErrorKind::Retryable | ToolErrorKind::UserFixable => 7,
+        },
+        AgentError::ToolNotFound(_) => 8,
+        AgentError::InvalidFunctionCall(_) => 9,
+        AgentError::Timeout(_) => 10,
+        AgentError::MaxIterations(_) => 11,
+        AgentError::RateLimit { .. } => 12,
+        AgentError::Mcp(_) => 13,
+        AgentError::Cancelled(_) => 14,
+        AgentError::Guardrail(_) => 16,
+        AgentError::Unknown(_) => 17,
+        AgentError::MaxHandoffs(_) => 18,
+    }
+}
+
+/// Build the agent from the profile file (if any) and the shared command-line options.
+///
+/// Flags given on the command line win over the profile, which wins over environment variables
+/// and the flag defaults.
+async fn build_agent(matches: &ArgMatches) -> Result<Agent, Box<dyn std::error::Error>> {
+    let profile = match matches.get_one::<String>("config") {
+        Some(path) => ProfileFile::load(path)?
+            .select(matches.get_one::<String>("profile").map(String::as_str))?,
+        None => AgentProfile::default(),
+    };
+
+    // Get API key from argument, profile or environment
+    let api_key = explicit(matches, "api-key")
+        .or(profile.api_key.clone())
         .or_else(|| env::var("OPENAI_API_KEY").ok())
         .ok_or("OpenRouter API key is required. Set OPENAI_API_KEY environment variable or use --api-key")?;
 
-    // Resolve base URL from CLI or environment
-    let base_url = matches
-        .get_one::<String>("base-url")
-        .cloned()
+    // Resolve base URL from CLI, profile or environment
+    let base_url = explicit(matches, "base-url")
+        .or(profile.base_url.clone())
         .or_else(|| env::var("OPENAI_BASE_URL").ok())
         .or_else(|| env::var("OPENROUTER_BASE_URL").ok())
         .unwrap_or_else(|| "https://openrouter.ai/api/v1".to_string());
 
-    // Set up function factory with tools
-    let mut function_factory = FunctionFactory::new();
-    function_factory.register_tool(CalculatorTool::new());
-    function_factory.register_tool(WeatherTool::new());
+    // Set up function factory with the profile's tools (calculator and weather by default)
+    let function_factory = profile.function_factory().await?;
 
     // Create agent
-    let timeout_seconds: u64 = matches.get_one::<String>("timeout").unwrap().parse()?;
-    let max_iterations: usize = matches
-        .get_one::<String>("max-iterations")
-        .unwrap()
-        .parse()?;
-
-    let agent = Agent::new(api_key, function_factory)
-        .with_model(matches.get_one::<String>("model").unwrap().as_str())
+    let model = explicit(matches, "model")
+        .or(profile.model.clone())
+        .unwrap_or_else(|| matches.get_one::<String>("model").unwrap().clone());
+    let timeout_seconds: u64 = match (explicit(matches, "timeout"), profile.timeout_secs) {
+        (None, Some(seconds)) => seconds,
+        _ => matches.get_one::<String>("timeout").unwrap().parse()?,
+    };
+    let max_iterations: usize = match (explicit(matches, "max-iterations"), profile.max_iterations)
+    {
+        (None, Some(count)) => count,
+        _ => matches
+            .get_one::<String>("max-iterations")
+            .unwrap()
+            .parse()?,
+    };
+
+    info!("Base URL: {}", base_url);
+
+    let redactor = matches
+        .get_flag("redact")
+        .then(|| Redactor::new().with_secret(&api_key));
+    let mut agent = Agent::new(api_key, function_factory)
+        .with_model(model)
         .with_timeout(std::time::Duration::from_secs(timeout_seconds))
         .with_max_iterations(max_iterations)
-        .with_base_url(base_url.clone());
+        .with_base_url(base_url);
+    let schema = match (matches.get_one::<String>("schema"), &profile.schema) {
+        (Some(path), _) => Some(SchemaHandle::from_json_schema_file(path)?),
+        (None, Some(source)) => Some(source.load()?),
+        (None, None) => None,
+    };
+    if let Some(schema) = schema {
+        agent = agent.with_schema_handle(schema);
+    }
+    if let Some(strategy) = profile.structured_output {
+        agent = agent.with_structured_output(strategy);
+    }
+    // Report schemas that strict mode would reject now rather than on the first request
+    agent.check_structured_output()?;
+    if let Some(system_prompt) = profile.system_prompt {
+        agent = agent.with_system_prompt(system_prompt);
+    }
+    if profile.max_tokens.is_some() {
+        agent = agent.with_max_tokens(profile.max_tokens);
+    }
+    if let Some(redactor) = redactor {
+        agent = agent.with_redactor(redactor);
+    }
+    Ok(agent)
+}
 
-    // Run the agent
-    let prompt = matches.get_one::<String>("prompt").unwrap();
-    info!("Running agent with prompt: {}", prompt);
-    info!(
-        "Using model: {}",
-        matches.get_one::<String>("model").unwrap()
-    );
-    info!("Base URL: {}", base_url);
+/// Value of a flag only when it was given on the command line rather than defaulted
+fn explicit(matches: &ArgMatches, name: &str) -> Option<String> {
+    match matches.value_source(name) {
+        Some(ValueSource::CommandLine) => matches.get_one::<String>(name).cloned(),
+        _ => None,
+    }
+}
 
-    match agent.run(prompt).await {
-        Ok(response) => {
-            println!("\nAgent Response:\n{}", response);
-            info!("Agent execution completed successfully");
-        }
-        Err(e) => {
-            error!("Agent execution failed: {}", e);
-            return Err(e.into());
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct_per_error_code() {
+        let errors = [
+            AgentError::Config(String::new()),
+            AgentError::OpenAI(async_openai::error::OpenAIError::InvalidArgument(
+                String::new(),
+            )),
+            AgentError::Serialization(serde_json::from_str::<serde_json::Value>("").unwrap_err()),
+            AgentError::Validation(String::new()),
+            AgentError::ToolExecution(String::new()),
+            AgentError::ToolNotFound(String::new()),
+            AgentError::InvalidFunctionCall(String::new()),
+            AgentError::Timeout(String::new()),
+            AgentError::MaxIterations(3),
+            AgentError::MaxHandoffs(3),
+            AgentError::RateLimit { retry_after: 1 },
+            AgentError::Mcp(String::new()),
+            AgentError::Cancelled(String::new()),
+            AgentError::ToolFailed(crate::ToolError::fatal("")),
+            AgentError::Guardrail(crate::GuardrailViolation {
+                guardrail: String::new(),
+                stage: crate::GuardrailStage::Input,
+                message: String::new(),
+            }),
+            AgentError::Unknown(String::new()),
+        ];
+        let mut codes: Vec<i32> = errors.iter().map(exit_code).collect();
+        assert!(codes.iter().all(|code| *code > 2));
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+        // Errors sharing an error code share the exit code
+        assert_eq!(
+            exit_code(&AgentError::ToolFailed(crate::ToolError::retryable(""))),
+            exit_code(&AgentError::ToolExecution(String::new()))
+        );
+    }
+
+    #[test]
+    fn test_output_flags_apply_to_subcommands() {
+        for args in [
+            vec!["tiny-agent", "--redact", "--verbose", "chat"],
+            vec!["tiny-agent", "chat", "--output", "json", "--redact"],
+            vec!["tiny-agent", "serve", "--trace-file", "runs.jsonl", "-v"],
+        ] {
+            let matches = command().try_get_matches_from(&args).unwrap();
+            let (_, subcommand) = matches.subcommand().unwrap();
+            let output = OutputOptions::from_matches(subcommand);
+            assert!(output.json || output.verbose || output.trace_file.is_some());
+            assert_eq!(
+                subcommand.get_flag("redact"),
+                args.contains(&"--redact"),
+                "{:?}",
+                args
+            );
         }
+
+        let matches = command()
+            .try_get_matches_from(["tiny-agent", "-o", "json", "--trace-file", "t.json", "hi"])
+            .unwrap();
+        let output = OutputOptions::from_matches(&matches);
+        assert!(output.json && !output.verbose);
+        assert_eq!(output.trace_file.as_deref(), Some("t.json"));
     }
 
-    Ok(())
+    #[test]
+    fn test_failure_trace_keeps_steps_and_error() {
+        let steps = vec![AgentStep::Task {
+            content: "hi".to_string(),
+            attachments: Vec::new(),
+        }];
+        let trace = failure_trace(&steps, &AgentError::MaxIterations(3));
+        assert_eq!(trace["steps"].as_array().unwrap().len(), 1);
+        assert_eq!(trace["error"]["code"], "MAX_ITERATIONS_EXCEEDED");
+    }
 }
@@ -0,0 +1,430 @@
+//! Agent profiles loaded from TOML or YAML files.
+//!
+//! A file holds one or more named profiles under `profiles`. String values may reference
+//! environment variables as `${NAME}` or `${NAME:-fallback}`, which keeps secrets out of the file;
+//! write `$${` for a literal `${`. Schema file paths are relative to the profile file.
+//!
+//! ```toml
+//! default = "research"
+//!
+//! [profiles.research]
+//! model = "openai/gpt-4.1"
+//! api_key = "${OPENROUTER_API_KEY}"
+//! system_prompt = "You are a careful researcher."
+//! max_iterations = 20
+//! timeout_secs = 300
+//! tools = ["calculator", { name = "jina_reader", api_key = "${JINA_API_KEY}" }]
+//! schema = "schemas/report.json"
+//! structured_output = "native"
+//!
+//! [profiles.research.mcp_servers.files]
+//! transport = "stdio"
+//! command = "mcp-server-filesystem"
+//! args = ["/tmp"]
+//! ```
+
+use crate::{
+    mcp::McpServerConfig,
+    tools::{CalculatorTool, JinaReaderTool, WeatherTool},
+    AgentError, FunctionFactory, Result, SchemaHandle, StructuredOutputStrategy,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, path::Path};
+
+/// A profile file with one or more named profiles
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileFile {
+    /// Profile used when none is selected explicitly
+    #[serde(default)]
+    pub default: Option<String>,
+    pub profiles: BTreeMap<String, AgentProfile>,
+}
+
+/// Settings for one agent; anything left out falls back to the command-line defaults
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentProfile {
+    pub model: Option<String>,
+    pub base_url: Option<String>,
+    pub api_key: Option<String>,
+    pub system_prompt: Option<String>,
+    pub max_iterations: Option<usize>,
+    pub max_tokens: Option<u32>,
+    pub timeout_secs: Option<u64>,
+    /// Built-in tools to enable; the calculator and weather tools when omitted
+    pub tools: Option<Vec<ToolEntry>>,
+    /// MCP servers whose tools are registered alongside the built-ins, namespaced by their key
+    #[serde(default)]
+    pub mcp_servers: BTreeMap<String, McpServerConfig>,
+    /// JSON Schema the final answer must match
+    pub schema: Option<SchemaSource>,
+    pub structured_output: Option<StructuredOutputStrategy>,
+}
+
+/// A completion schema, either a path to a JSON Schema file or the schema written inline
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum SchemaSource {
+    File(String),
+    Inline(Value),
+}
+
+impl SchemaSource {
+    pub fn load(&self) -> Result<SchemaHandle> {
+        match self {
+            SchemaSource::File(path) => SchemaHandle::from_json_schema_file(path),
+            SchemaSource::Inline(schema) => {
+                let title = schema.get("title").and_then(Value::as_str);
+                SchemaHandle::from_json_schema(title.unwrap_or("structured_output"), schema.clone())
+            }
+        }
+    }
+}
+
+/// A built-in tool, either by name or as a table with its settings
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum ToolEntry {
+    Name(String),
+    Configured(BuiltinTool),
+}
+
+/// Built-in tools and their settings
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "name", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BuiltinTool {
+    Calculator,
+    Weather,
+    JinaReader {
+        /// Falls back to the `JINA_API_KEY` environment variable
+        #[serde(default)]
+        api_key: Option<String>,
+    },
+}
+
+impl ToolEntry {
+    fn resolve(&self) -> Result<BuiltinTool> {
+        match self {
+            ToolEntry::Configured(tool) => Ok(tool.clone()),
+            ToolEntry::Name(name) => match name.as_str() {
+                "calculator" => Ok(BuiltinTool::Calculator),
+                "weather" => Ok(BuiltinTool::Weather),
+                "jina_reader" => Ok(BuiltinTool::JinaReader { api_key: None }),
+                other => Err(AgentError::Config(format!(
+                    "Unknown built-in tool '{}'",
+                    other
+                ))),
+            },
+        }
+    }
+}
+
+impl ProfileFile {
+    /// Load a `.toml`, `.yaml` or `.yml` profile file, expanding environment variables
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|err| {
+            AgentError::Config(format!("Failed to read {}: {}", path.display(), err))
+        })?;
+
+        let mut file = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("yaml" | "yml") => Self::from_yaml(&contents),
+            _ => Err(AgentError::Config(format!(
+                "Unsupported profile file {}: expected .toml, .yaml or .yml",
+                path.display()
+            ))),
+        }?;
+        if let Some(directory) = path.parent() {
+            file.resolve_paths(directory);
+        }
+        Ok(file)
+    }
+
+    /// Make relative schema paths relative to `directory` instead of the working directory
+    fn resolve_paths(&mut self, directory: &Path) {
+        for profile in self.profiles.values_mut() {
+            if let Some(SchemaSource::File(schema)) = &mut profile.schema {
+                *schema = directory.join(&*schema).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        let raw: Value = toml::from_str(contents)
+            .map_err(|err| AgentError::Config(format!("Invalid TOML profile file: {}", err)))?;
+        Self::from_value(raw)
+    }
+
+    pub fn from_yaml(contents: &str) -> Result<Self> {
+        let raw: Value = serde_yaml::from_str(contents)
+            .map_err(|err| AgentError::Config(format!("Invalid YAML profile file: {}", err)))?;
+        Self::from_value(raw)
+    }
+
+    fn from_value(mut raw: Value) -> Result<Self> {
+        interpolate_env(&mut raw)?;
+        serde_json::from_value(raw)
+            .map_err(|err| AgentError::Config(format!("Invalid profile file: {}", err)))
+    }
+
+    /// Pick a profile: `name` if given, then the file's `default`, then the only profile
+    pub fn select(mut self, name: Option<&str>) -> Result<AgentProfile> {
+        let name = match name.map(str::to_string).or(self.default.take()) {
+            Some(name) => name,
+            None if self.profiles.len() == 1 => self.profiles.keys().next().cloned().unwrap(),
+            None => {
+                return Err(AgentError::Config(format!(
+                    "Several profiles available ({}); choose one with --profile",
+                    self.names().join(", ")
+                )))
+            }
+        };
+
+        let names = self.names().join(", ");
+        self.profiles.remove(&name).ok_or_else(|| {
+            AgentError::Config(format!(
+                "No profile named '{}' (available: {})",
+                name, names
+            ))
+        })
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+}
+
+impl AgentProfile {
+    /// Register the profile's built-in tools and connect its MCP servers
+    pub async fn function_factory(&self) -> Result<FunctionFactory> {
+        let mut factory = FunctionFactory::new();
+
+        let default_tools = [BuiltinTool::Calculator, BuiltinTool::Weather];
+        let tools = match &self.tools {
+            Some(entries) => entries
+                .iter()
+                .map(ToolEntry::resolve)
+                .collect::<Result<Vec<_>>>()?,
+            None => default_tools.to_vec(),
+        };
+
+        for tool in tools {
+            match tool {
+                BuiltinTool::Calculator => factory.register_tool(CalculatorTool::new()),
+                BuiltinTool::Weather => factory.register_tool(WeatherTool::new()),
+                BuiltinTool::JinaReader { api_key: Some(key) } => {
+                    factory.register_tool(JinaReaderTool::new(key))
+                }
+                BuiltinTool::JinaReader { api_key: None } => {
+                    factory.register_tool(JinaReaderTool::from_env()?)
+                }
+            }?;
+        }
+
+        for (name, config) in &self.mcp_servers {
+            factory
+                .register_mcp_server(name, config)
+                .await
+                .map_err(|err| {
+                    AgentError::Config(format!("MCP server '{}' failed to start: {}", name, err))
+                })?;
+        }
+
+        Ok(factory)
+    }
+}
+
+/// Expand `${NAME}` and `${NAME:-fallback}` in every string value; `$${` stays a literal `${`
+fn interpolate_env(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(text) => *text = expand_vars(text)?,
+        Value::Array(items) => items.iter_mut().try_for_each(interpolate_env)?,
+        Value::Object(map) => map.values_mut().try_for_each(interpolate_env)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn expand_vars(text: &str) -> Result<String> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        if let Some(literal) = rest[..start].strip_suffix('$') {
+            expanded.push_str(literal);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find('}').ok_or_else(|| {
+            AgentError::Config(format!("Unterminated variable reference in '{}'", text))
+        })?;
+
+        let reference = &after[..end];
+        let (name, fallback) = match reference.split_once(":-") {
+            Some((name, fallback)) => (name, Some(fallback)),
+            None => (reference, None),
+        };
+        let resolved = match (std::env::var(name), fallback) {
+            (Ok(value), _) => value,
+            (Err(_), Some(fallback)) => fallback.to_string(),
+            (Err(_), None) => {
+                return Err(AgentError::Config(format!(
+                    "Environment variable {} is not set",
+                    name
+                )))
+            }
+        };
+
+        expanded.push_str(&resolved);
+        rest = &after[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = r#"
+default = "fast"
+
+[profiles.fast]
+model = "openai/gpt-4.1-mini"
+tools = ["calculator"]
+
+[profiles.research]
+model = "openai/gpt-4.1"
+api_key = "${TINY_AGENT_PROFILE_TEST_KEY}"
+base_url = "${TINY_AGENT_PROFILE_TEST_UNSET:-http://localhost:4000}"
+max_iterations = 20
+tools = [{ name = "jina_reader", api_key = "jina-key" }, "weather"]
+
+[profiles.research.mcp_servers.files]
+transport = "stdio"
+command = "mcp-server-filesystem"
+args = ["/tmp"]
+"#;
+
+    #[test]
+    fn test_toml_profiles_with_env_interpolation() {
+        std::env::set_var("TINY_AGENT_PROFILE_TEST_KEY", "secret");
+        let file = ProfileFile::from_toml(TOML).unwrap();
+
+        let fast = file.clone().select(None).unwrap();
+        assert_eq!(fast.model.as_deref(), Some("openai/gpt-4.1-mini"));
+        assert_eq!(
+            fast.tools,
+            Some(vec![ToolEntry::Name("calculator".to_string())])
+        );
+
+        let research = file.select(Some("research")).unwrap();
+        assert_eq!(research.api_key.as_deref(), Some("secret"));
+        assert_eq!(research.base_url.as_deref(), Some("http://localhost:4000"));
+        assert_eq!(research.max_iterations, Some(20));
+        assert_eq!(
+            research.tools.unwrap()[0],
+            ToolEntry::Configured(BuiltinTool::JinaReader {
+                api_key: Some("jina-key".to_string())
+            })
+        );
+        assert!(matches!(
+            &research.mcp_servers["files"],
+            McpServerConfig::Stdio { command, .. } if command == "mcp-server-filesystem"
+        ));
+    }
+
+    #[test]
+    fn test_yaml_profile_and_selection_errors() {
+        let file = ProfileFile::from_yaml(
+            "profiles:\n  a:\n    model: m1\n  b:\n    system_prompt: Be brief.\n",
+        )
+        .unwrap();
+        assert!(file
+            .clone()
+            .select(None)
+            .unwrap_err()
+            .to_string()
+            .contains("choose one with --profile"));
+        assert!(file.clone().select(Some("c")).is_err());
+        assert_eq!(
+            file.select(Some("b")).unwrap().system_prompt.as_deref(),
+            Some("Be brief.")
+        );
+
+        let missing = ProfileFile::from_yaml(
+            "profiles:\n  a:\n    api_key: ${TINY_AGENT_PROFILE_TEST_MISSING}\n",
+        );
+        assert!(missing
+            .unwrap_err()
+            .to_string()
+            .contains("TINY_AGENT_PROFILE_TEST_MISSING is not set"));
+    }
+
+    #[test]
+    fn test_escaped_references_stay_literal() {
+        let profile = ProfileFile::from_yaml(
+            "profiles:\n  a:\n    system_prompt: 'Fill in $${name} and $${TINY_AGENT_PROFILE_TEST_MISSING}'\n",
+        )
+        .unwrap()
+        .select(None)
+        .unwrap();
+        assert_eq!(
+            profile.system_prompt.as_deref(),
+            Some("Fill in ${name} and ${TINY_AGENT_PROFILE_TEST_MISSING}")
+        );
+    }
+
+    #[test]
+    fn test_schema_file_is_relative_to_the_profile() {
+        let directory =
+            std::env::temp_dir().join(format!("tiny-agent-profile-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("schemas")).unwrap();
+        std::fs::write(
+            directory.join("schemas/report.json"),
+            r#"{"title": "Report", "type": "object", "properties": {"summary": {"type": "string"}}}"#,
+        )
+        .unwrap();
+        let path = directory.join("agents.toml");
+        std::fs::write(&path, "[profiles.a]\nschema = \"schemas/report.json\"\n").unwrap();
+
+        let profile = ProfileFile::load(&path).unwrap().select(None).unwrap();
+        let schema = profile.schema.unwrap().load().unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(schema.schema_name(), "Report");
+    }
+
+    #[test]
+    fn test_inline_schema() {
+        let profile = ProfileFile::from_yaml(
+            "profiles:\n  triage:\n    structured_output: prompt\n    schema:\n      title: Ticket\n      type: object\n      properties:\n        priority: { type: string }\n",
+        )
+        .unwrap()
+        .select(None)
+        .unwrap();
+
+        assert_eq!(
+            profile.structured_output,
+            Some(StructuredOutputStrategy::Prompt)
+        );
+        let schema = profile.schema.unwrap().load().unwrap();
+        assert_eq!(schema.schema_name(), "Ticket");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tool_is_rejected() {
+        let profile = AgentProfile {
+            tools: Some(vec![ToolEntry::Name("teleporter".to_string())]),
+            ..AgentProfile::default()
+        };
+        assert!(profile.function_factory().await.is_err());
+    }
+}
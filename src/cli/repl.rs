@@ -0,0 +1,325 @@
+use super::{collect_steps, write_trace, OutputOptions};
+use crate::{Agent, AgentMemory, AgentStep, RunResult};
+use rustyline::{error::ReadlineError, DefaultEditor};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const HISTORY_FILE: &str = ".tiny_agent_history";
+
+const HELP: &str = "Commands:
+  /tools          List registered tools
+  /model [NAME]   Show or switch the model
+  /trace          Show the trace of the last turn
+  /save PATH      Save the conversation to a session file
+  /load PATH      Load a conversation from a session file
+  /reset          Start a new conversation
+  /help           Show this help
+  /exit           Quit";
+
+/// A line entered at the REPL prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplCommand {
+    Prompt(String),
+    Tools,
+    Model(Option<String>),
+    Trace,
+    Save(String),
+    Load(String),
+    Reset,
+    Help,
+    Exit,
+    Unknown(String),
+}
+
+impl ReplCommand {
+    /// Parse a line; blank lines yield `None`
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+
+        let Some(command) = line.strip_prefix('/') else {
+            return Some(ReplCommand::Prompt(line.to_string()));
+        };
+
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, Some(argument.trim().to_string())),
+            None => (command, None),
+        };
+        let argument = argument.filter(|argument| !argument.is_empty());
+
+        Some(match (name, argument) {
+            ("tools", _) => ReplCommand::Tools,
+            ("model", argument) => ReplCommand::Model(argument),
+            ("trace", _) => ReplCommand::Trace,
+            ("save", Some(path)) => ReplCommand::Save(path),
+            ("load", Some(path)) => ReplCommand::Load(path),
+            ("reset", _) => ReplCommand::Reset,
+            ("help", _) => ReplCommand::Help,
+            ("exit" | "quit", _) => ReplCommand::Exit,
+            _ => ReplCommand::Unknown(line.to_string()),
+        })
+    }
+}
+
+/// Conversation saved by `/save`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionFile {
+    pub model: String,
+    pub memory: AgentMemory,
+}
+
+/// One conversation kept alive across REPL turns
+#[derive(Debug)]
+pub struct ReplSession {
+    agent: Agent,
+    memory: AgentMemory,
+    last_run: Option<RunResult>,
+    output: OutputOptions,
+}
+
+impl ReplSession {
+    pub fn new(agent: Agent) -> Self {
+        Self {
+            agent,
+            memory: AgentMemory::new(None),
+            last_run: None,
+            output: OutputOptions::default(),
+        }
+    }
+
+    /// Print replies as JSON, print steps as they happen, or write each turn's trace to a file
+    pub fn with_output(mut self, output: OutputOptions) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Conversation so far
+    pub fn memory(&self) -> &AgentMemory {
+        &self.memory
+    }
+
+    /// Handle one command and return the text to print; `None` means the session should end
+    pub async fn handle(&mut self, command: ReplCommand) -> Option<String> {
+        let output = match command {
+            ReplCommand::Prompt(prompt) => self.ask(&prompt).await,
+            ReplCommand::Tools => self.list_tools(),
+            ReplCommand::Model(None) => format!("Model: {}", self.agent.model()),
+            ReplCommand::Model(Some(model)) => {
+                self.agent.set_model(model.as_str());
+                format!("Switched to model {}", model)
+            }
+            ReplCommand::Trace => match &self.last_run {
+                Some(run) => run.replay(),
+                None => "No turns yet".to_string(),
+            },
+            ReplCommand::Save(path) => match self.save(&path) {
+                Ok(()) => format!("Saved session to {}", path),
+                Err(err) => format!("Failed to save session: {}", err),
+            },
+            ReplCommand::Load(path) => match self.load(&path) {
+                Ok(()) => format!(
+                    "Loaded session from {} ({} steps)",
+                    path,
+                    self.memory.step_count()
+                ),
+                Err(err) => format!("Failed to load session: {}", err),
+            },
+            ReplCommand::Reset => {
+                self.memory = AgentMemory::new(None);
+                self.last_run = None;
+                "Started a new conversation".to_string()
+            }
+            ReplCommand::Help => HELP.to_string(),
+            ReplCommand::Exit => return None,
+            ReplCommand::Unknown(line) => format!("Unknown command: {} (try /help)", line),
+        };
+        Some(output)
+    }
+
+    async fn ask(&mut self, prompt: &str) -> String {
+        // Only keep the turn once it succeeds, so a failed turn can simply be retried
+        let mut memory = self.memory.clone();
+        let (step_sender, collector) = collect_steps(self.output.verbose);
+        memory.set_redactor(self.agent.redactor().cloned());
+        memory.set_step_sender(Some(step_sender));
+        memory.add_step(AgentStep::Task {
+            content: prompt.to_string(),
+            attachments: Vec::new(),
+        });
+
+        let outcome = self.agent.run_with_memory(&mut memory).await;
+        memory.set_step_sender(None);
+        let steps = collector.await.unwrap_or_default();
+        if let Some(path) = &self.output.trace_file {
+            if let Err(err) = write_trace(path, outcome.as_ref(), &steps) {
+                eprintln!("Failed to write trace to {}: {}", path, err);
+            }
+        }
+
+        match outcome {
+            Ok(run) => {
+                let output = if self.output.json {
+                    serde_json::to_string_pretty(&run).unwrap_or_default()
+                } else {
+                    run.output.clone()
+                };
+                self.memory = memory;
+                self.last_run = Some(run);
+                output
+            }
+            Err(err) if self.output.json => {
+                serde_json::to_string_pretty(&err.to_error_payload()).unwrap_or_default()
+            }
+            Err(err) => format!("Error [{}]: {}", err.error_code(), err),
+        }
+    }
+
+    fn list_tools(&self) -> String {
+        let tools = self.agent.function_factory().get_openai_tools();
+        if tools.is_empty() {
+            return "No tools registered".to_string();
+        }
+
+        let mut lines: Vec<String> = tools
+            .iter()
+            .map(|tool| {
+                format!(
+                    "{}: {}",
+                    tool["function"]["name"].as_str().unwrap_or_default(),
+                    tool["function"]["description"].as_str().unwrap_or_default()
+                )
+            })
+            .collect();
+        lines.sort();
+        lines.join("\n")
+    }
+
+    fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let session = SessionFile {
+            model: self.agent.model().to_string(),
+            memory: self.memory.clone(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&session)?)?;
+        Ok(())
+    }
+
+    fn load(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let session: SessionFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        self.agent.set_model(session.model);
+        self.memory = session.memory;
+        self.last_run = None;
+        Ok(())
+    }
+}
+
+/// Interactive chat loop with line editing and persistent history
+pub async fn run(
+    agent: Agent,
+    session: Option<&str>,
+    output: OutputOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut repl = ReplSession::new(agent).with_output(output);
+    if let Some(path) = session {
+        if let Some(output) = repl.handle(ReplCommand::Load(path.to_string())).await {
+            println!("{}", output);
+        }
+    }
+
+    let mut editor = DefaultEditor::new()?;
+    let history = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+
+    println!(
+        "tiny-agent chat ({}). Type /help for commands.",
+        repl.agent.model()
+    );
+
+    loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        };
+
+        let Some(command) = ReplCommand::parse(&line) else {
+            continue;
+        };
+        let _ = editor.add_history_entry(line.trim());
+
+        match repl.handle(command).await {
+            Some(output) => println!("{}\n", output),
+            None => break,
+        }
+    }
+
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tools::CalculatorTool, FunctionFactory};
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(ReplCommand::parse("   "), None);
+        assert_eq!(
+            ReplCommand::parse("what is 2 + 2?"),
+            Some(ReplCommand::Prompt("what is 2 + 2?".to_string()))
+        );
+        assert_eq!(
+            ReplCommand::parse("/model  openai/gpt-4.1 "),
+            Some(ReplCommand::Model(Some("openai/gpt-4.1".to_string())))
+        );
+        assert_eq!(ReplCommand::parse("/model"), Some(ReplCommand::Model(None)));
+        assert_eq!(
+            ReplCommand::parse("/save"),
+            Some(ReplCommand::Unknown("/save".to_string()))
+        );
+        assert_eq!(ReplCommand::parse("/quit"), Some(ReplCommand::Exit));
+    }
+
+    #[tokio::test]
+    async fn test_session_commands() {
+        let mut factory = FunctionFactory::new();
+        factory.register_tool(CalculatorTool::new()).unwrap();
+        let mut repl = ReplSession::new(Agent::new("key".to_string(), factory));
+
+        let tools = repl.handle(ReplCommand::Tools).await.unwrap();
+        assert!(tools.starts_with("calculator: "));
+
+        repl.handle(ReplCommand::Model(Some("test/model".to_string())))
+            .await;
+        assert_eq!(
+            repl.handle(ReplCommand::Model(None)).await.unwrap(),
+            "Model: test/model"
+        );
+
+        repl.memory.add_step(AgentStep::Task {
+            content: "remember me".to_string(),
+            attachments: Vec::new(),
+        });
+        let path =
+            std::env::temp_dir().join(format!("tiny-agent-repl-{}.json", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        repl.handle(ReplCommand::Save(path.clone())).await;
+
+        repl.handle(ReplCommand::Reset).await;
+        assert!(repl.memory().is_empty());
+
+        repl.handle(ReplCommand::Model(Some("other/model".to_string())))
+            .await;
+        repl.handle(ReplCommand::Load(path.clone())).await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(repl.memory().step_count(), 1);
+        assert_eq!(repl.agent.model(), "test/model");
+        assert_eq!(repl.handle(ReplCommand::Exit).await, None);
+    }
+}
@@ -1,11 +1,18 @@
+use super::{
+    guardrails::{Guardrail, GuardrailAction, Guardrails},
+    prompt::{PromptContext, PromptTemplate},
+    redaction::Redactor,
+};
 use crate::{
     error::{AgentError, Result},
-    schemas::{CompletionSchema, SchemaHandle},
-    services::openai_client::OpenAIClient,
-    tools::FunctionFactory,
+    schemas::{
+        validation::schema_instructions, CompletionSchema, SchemaHandle, StructuredOutputStrategy,
+    },
+    services::{openai_client::OpenAIClient, planning::PlanningConfig},
+    tools::{FunctionFactory, ToolChoice, ToolRetrieval, ToolSelection, UserData},
 };
 use serde_json::{json, Value};
-use std::time::Duration;
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
 
 /// Main agent
 #[derive(Debug)]
@@ -17,6 +24,27 @@ pub struct Agent {
     max_tokens: Option<u32>,
     timeout: Duration,
     completion_schema: Option<SchemaHandle>,
+    structured_output: StructuredOutputStrategy,
+    system_prompt: PromptTemplate,
+    planning: Option<PlanningConfig>,
+    replay_reasoning: bool,
+    user_data: UserData,
+    tool_selector: Option<ToolSelector>,
+    tool_choice: ToolChoice,
+    iteration_tool_choices: HashMap<usize, ToolChoice>,
+    tool_retrieval: Option<ToolRetrieval>,
+    guardrails: Guardrails,
+    redactor: Option<Arc<Redactor>>,
+}
+
+/// Picks the tools offered to the model for an iteration
+#[derive(Clone)]
+struct ToolSelector(Arc<dyn Fn(usize) -> ToolSelection + Send + Sync>);
+
+impl fmt::Debug for ToolSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ToolSelector")
+    }
 }
 
 impl Agent {
@@ -29,14 +57,30 @@ impl Agent {
             max_tokens: Some(1000),
             timeout: Duration::from_secs(120),
             completion_schema: None,
+            structured_output: StructuredOutputStrategy::default(),
+            system_prompt: PromptTemplate::default(),
+            planning: None,
+            replay_reasoning: false,
+            user_data: UserData::new(),
+            tool_selector: None,
+            tool_choice: ToolChoice::default(),
+            iteration_tool_choices: HashMap::new(),
+            tool_retrieval: None,
+            guardrails: Guardrails::default(),
+            redactor: None,
         }
     }
 
     pub fn with_model(mut self, model: impl Into<String>) -> Self {
-        self.model = model.into();
+        self.set_model(model);
         self
     }
 
+    /// Switch the model used by later runs, e.g. between turns of a conversation
+    pub fn set_model(&mut self, model: impl Into<String>) {
+        self.model = model.into();
+    }
+
     pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
         self.openai_client.set_base_url(base_url);
         self
@@ -62,14 +106,224 @@ impl Agent {
         self
     }
 
+    /// Use a completion schema built at runtime, e.g. with [`SchemaHandle::from_json_schema`].
+    ///
+    /// The structured payload is validated against it and returned as JSON in
+    /// [`RunResult::structured`](crate::RunResult::structured).
+    pub fn with_schema_handle(mut self, schema: SchemaHandle) -> Self {
+        self.completion_schema = Some(schema);
+        self
+    }
+
+    /// Choose how the completion schema is requested from the model.
+    pub fn with_structured_output(mut self, strategy: StructuredOutputStrategy) -> Self {
+        self.structured_output = strategy;
+        self
+    }
+
+    /// Replace the system prompt; see [`PromptTemplate`] for the supported variables.
+    pub fn with_system_prompt(mut self, system_prompt: impl Into<PromptTemplate>) -> Self {
+        self.system_prompt = system_prompt.into();
+        self
+    }
+
+    /// Set a user variable for the system prompt template
+    pub fn with_prompt_variable(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.system_prompt.set_variable(name, value);
+        self
+    }
+
+    /// Generate a plan before the first iteration and re-plan according to `config`
+    pub fn with_planning(mut self, config: PlanningConfig) -> Self {
+        self.planning = Some(config);
+        self
+    }
+
+    /// Send captured reasoning back to the provider on subsequent turns.
+    ///
+    /// Needed for providers that require prior reasoning blocks (e.g. OpenRouter with
+    /// Anthropic or Gemini reasoning models); leave disabled for providers that reject them.
+    pub fn with_reasoning_replay(mut self, replay_reasoning: bool) -> Self {
+        self.replay_reasoning = replay_reasoning;
+        self
+    }
+
+    /// Make `value` available to every tool through [`ToolContext::get`](crate::ToolContext::get)
+    pub fn with_user_data<T: std::any::Any + Send + Sync>(mut self, value: T) -> Self {
+        self.user_data.insert(value);
+        self
+    }
+
+    /// Only offer the tools in `selection` to the model.
+    ///
+    /// Calls to other tools fail with [`AgentError::ToolNotFound`] and go back to the model.
+    /// A run can narrow this further with
+    /// [`AgentMemory::set_tool_selection`](crate::AgentMemory::set_tool_selection).
+    pub fn with_tool_selection(self, selection: ToolSelection) -> Self {
+        self.with_tool_selector(move |_| selection.clone())
+    }
+
+    /// Choose the tools offered to the model for each (1-based) iteration
+    pub fn with_tool_selector<F>(mut self, selector: F) -> Self
+    where
+        F: Fn(usize) -> ToolSelection + Send + Sync + 'static,
+    {
+        self.tool_selector = Some(ToolSelector(Arc::new(selector)));
+        self
+    }
+
+    /// Tools offered to the model in `iteration`
+    pub(crate) fn tool_selection(&self, iteration: usize) -> ToolSelection {
+        self.tool_selector
+            .as_ref()
+            .map(|selector| (selector.0)(iteration))
+            .unwrap_or_default()
+    }
+
+    /// Control whether the model must call a tool; [`ToolChoice::Auto`] by default.
+    ///
+    /// With [`ToolChoice::None`] a plain reply is the final answer. A tool forced with
+    /// [`ToolChoice::tool`] is forced until the model has called it; later iterations use
+    /// [`ToolChoice::Auto`] so the run can finish.
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = tool_choice;
+        self
+    }
+
+    /// Use `tool_choice` on the given (1-based) iteration only, e.g. to force a lookup first
+    pub fn with_iteration_tool_choice(mut self, iteration: usize, tool_choice: ToolChoice) -> Self {
+        self.iteration_tool_choices.insert(iteration, tool_choice);
+        self
+    }
+
+    /// Tool choice sent in `iteration`; `forced_called` says whether the run already called the
+    /// tool the agent-wide choice forces
+    pub(crate) fn tool_choice(&self, iteration: usize, forced_called: bool) -> ToolChoice {
+        if let Some(tool_choice) = self.iteration_tool_choices.get(&iteration) {
+            return tool_choice.clone();
+        }
+        match &self.tool_choice {
+            ToolChoice::Tool(_) if forced_called => ToolChoice::Auto,
+            tool_choice => tool_choice.clone(),
+        }
+    }
+
+    /// Function name the agent-wide tool choice forces, if any
+    pub(crate) fn forced_function(&self) -> Option<String> {
+        self.tool_choice.forced_function()
+    }
+
+    /// Offer each run only the registered tools most relevant to its task.
+    ///
+    /// Applies on top of the tool selection; see [`ToolRetrieval`].
+    pub fn with_tool_retrieval(mut self, retrieval: ToolRetrieval) -> Self {
+        self.tool_retrieval = Some(retrieval);
+        self
+    }
+
+    pub(crate) fn tool_retrieval(&self) -> Option<&ToolRetrieval> {
+        self.tool_retrieval.as_ref()
+    }
+
+    /// Check the task of every run before the first model call.
+    ///
+    /// A rejected task fails the run with [`AgentError::Guardrail`].
+    pub fn with_input_guardrail(mut self, guardrail: impl Guardrail + 'static) -> Self {
+        self.guardrails.add_input(Arc::new(guardrail));
+        self
+    }
+
+    /// Check the final answer and structured payload before a run returns them
+    pub fn with_output_guardrail(
+        mut self,
+        guardrail: impl Guardrail + 'static,
+        action: GuardrailAction,
+    ) -> Self {
+        self.guardrails.add_output(Arc::new(guardrail), action);
+        self
+    }
+
+    pub(crate) fn guardrails(&self) -> &Guardrails {
+        &self.guardrails
+    }
+
+    /// Mask secrets in step logs, streamed steps and the traces of runs; the model still sees
+    /// the real values
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(Arc::new(redactor));
+        self
+    }
+
+    pub(crate) fn redactor(&self) -> Option<&Arc<Redactor>> {
+        self.redactor.as_ref()
+    }
+
+    /// Render the system prompt for the current tools and completion schema
+    pub fn system_prompt(&self) -> String {
+        let tools = self
+            .function_factory
+            .get_openai_tools()
+            .iter()
+            .filter_map(|tool| {
+                let function = tool.get("function")?;
+                let name = function.get("name")?.as_str()?;
+                let description = function.get("description")?.as_str()?;
+                Some((name.to_string(), description.to_string()))
+            })
+            .collect();
+
+        let context = PromptContext {
+            tools,
+            schema_instructions: self
+                .completion_schema
+                .as_ref()
+                .map(|schema| schema_instructions(schema, self.structured_output)),
+        };
+
+        self.system_prompt.render(&context)
+    }
+
     pub(crate) fn max_iterations(&self) -> usize {
         self.max_iterations
     }
 
+    /// Check that the completion schema can be requested with the chosen strategy.
+    ///
+    /// With [`StructuredOutputStrategy::Native`] the schema is sent in strict mode, so this
+    /// reports every construct strict mode rejects. Runs do this before their first request.
+    pub fn check_structured_output(&self) -> Result<()> {
+        match (&self.completion_schema, self.structured_output) {
+            (Some(schema), StructuredOutputStrategy::Native) => {
+                schema.strict_schema_json().map(|_| ())
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub(crate) fn completion_schema(&self) -> Option<&SchemaHandle> {
         self.completion_schema.as_ref()
     }
 
+    pub(crate) fn structured_output(&self) -> StructuredOutputStrategy {
+        self.structured_output
+    }
+
+    pub(crate) fn planning(&self) -> Option<&PlanningConfig> {
+        self.planning.as_ref()
+    }
+
+    pub(crate) fn replay_reasoning(&self) -> bool {
+        self.replay_reasoning
+    }
+
+    pub(crate) fn user_data(&self) -> &UserData {
+        &self.user_data
+    }
+
     pub(crate) fn function_factory(&self) -> &FunctionFactory {
         &self.function_factory
     }
@@ -95,7 +349,7 @@ impl Agent {
         let messages = vec![
             json!({
                 "role": "system",
-                "content": "You are a helpful assistant with access to tools. Use tools when necessary to provide accurate information. Be concise and helpful. When you are ready to give the final response, you MUST call the `final_answer` tool with an `answer` string instead of replying directly."
+                "content": self.system_prompt()
             }),
             json!({
                 "role": "user",
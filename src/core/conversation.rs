@@ -1,6 +1,9 @@
 #![allow(dead_code)]
 
-use crate::schemas::{CompletionSchema, SchemaHandle};
+use crate::{
+    error::Result,
+    schemas::{CompletionSchema, SchemaHandle},
+};
 use serde_json::{json, Value};
 
 #[derive(Clone, Debug, Default)]
@@ -25,16 +28,23 @@ impl SchemaContext {
         self.active.as_ref()
     }
 
-    pub fn response_format(&self) -> Option<Value> {
-        self.active.as_ref().map(|handle| {
-            json!({
-                "type": "json_schema",
-                "json_schema": {
-                    "name": handle.schema_name(),
-                    "schema": handle.schema_json()
-                }
-            })
-        })
+    /// Strict `response_format` for the active schema.
+    ///
+    /// Fails with the `Config` error from [`SchemaHandle::strict_schema_json`] when the schema
+    /// has no strict form, the same error runs report before their first request.
+    pub fn response_format(&self) -> Result<Option<Value>> {
+        let Some(handle) = &self.active else {
+            return Ok(None);
+        };
+        let schema = handle.strict_schema_json()?;
+        Ok(Some(json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": handle.schema_name(),
+                "schema": schema.as_ref(),
+                "strict": true
+            }
+        })))
     }
 }
 
@@ -92,7 +102,47 @@ impl Conversation {
         self.schema.clear();
     }
 
-    pub fn response_format(&self) -> Option<Value> {
+    pub fn response_format(&self) -> Result<Option<Value>> {
         self.schema.response_format()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_format_requires_a_strict_schema() {
+        let mut context = SchemaContext::default();
+        assert!(context.response_format().unwrap().is_none());
+
+        context.set_handle(
+            SchemaHandle::from_json_schema(
+                "Answer",
+                json!({
+                    "type": "object",
+                    "properties": { "text": { "type": "string" } }
+                }),
+            )
+            .unwrap(),
+        );
+        let format = context.response_format().unwrap().unwrap();
+        assert_eq!(format["json_schema"]["strict"], json!(true));
+        assert_eq!(
+            format["json_schema"]["schema"]["additionalProperties"],
+            json!(false)
+        );
+
+        let labels = json!({
+            "type": "object",
+            "properties": {
+                "labels": { "type": "object", "additionalProperties": { "type": "string" } }
+            }
+        });
+        context.set_handle(SchemaHandle::from_json_schema("Labels", labels).unwrap());
+        assert!(matches!(
+            context.response_format(),
+            Err(crate::AgentError::Config(_))
+        ));
+    }
+}
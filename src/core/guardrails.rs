@@ -0,0 +1,432 @@
+//! Policy checks on the task a run starts from and the answer it returns
+
+use crate::error::{AgentError, GuardrailStage, GuardrailViolation, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::{fmt, future::Future, pin::Pin, sync::Arc};
+
+/// What a guardrail inspects
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardrailContent {
+    pub stage: GuardrailStage,
+    /// The task, or the final answer
+    pub text: String,
+    /// The structured payload of the final answer, if any
+    pub structured: Option<Value>,
+}
+
+impl GuardrailContent {
+    /// The text followed by the structured payload as JSON, for pattern checks
+    pub fn full_text(&self) -> String {
+        match &self.structured {
+            Some(structured) => format!("{}\n{}", self.text, structured),
+            None => self.text.clone(),
+        }
+    }
+}
+
+/// Outcome of a guardrail check; `Err` holds the reason the content is rejected
+pub type GuardrailResult = std::result::Result<(), String>;
+
+/// A policy check on the input or output of a run
+pub trait Guardrail: Send + Sync + fmt::Debug {
+    /// Name reported in violations
+    fn name(&self) -> &str;
+
+    fn check<'a>(
+        &'a self,
+        content: &'a GuardrailContent,
+    ) -> Pin<Box<dyn Future<Output = GuardrailResult> + Send + 'a>>;
+}
+
+/// What happens when an output guardrail rejects the final answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GuardrailAction {
+    /// Fail the run with [`AgentError::Guardrail`]
+    #[default]
+    Abort,
+    /// Send the violation back to the model, like a schema failure, so it can answer again
+    Correct,
+}
+
+/// Rejects content that mentions any of the given words or phrases (case-insensitive)
+#[derive(Debug, Clone)]
+pub struct BlockedTopics {
+    topics: Vec<String>,
+    pattern: Regex,
+}
+
+impl BlockedTopics {
+    pub fn new<I, S>(topics: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let topics: Vec<String> = topics.into_iter().map(Into::into).collect();
+        let alternatives = topics
+            .iter()
+            .map(|topic| regex::escape(topic))
+            .collect::<Vec<_>>()
+            .join("|");
+        // An empty alternation would match everything
+        let pattern = if topics.is_empty() {
+            r"[^\s\S]".to_string()
+        } else {
+            format!(r"(?i)\b(?:{})\b", alternatives)
+        };
+        Self {
+            topics,
+            pattern: Regex::new(&pattern).expect("escaped topics form a valid pattern"),
+        }
+    }
+
+    pub fn topics(&self) -> &[String] {
+        &self.topics
+    }
+}
+
+impl Guardrail for BlockedTopics {
+    fn name(&self) -> &str {
+        "blocked_topics"
+    }
+
+    fn check<'a>(
+        &'a self,
+        content: &'a GuardrailContent,
+    ) -> Pin<Box<dyn Future<Output = GuardrailResult> + Send + 'a>> {
+        let result = match self.pattern.find(&content.full_text()) {
+            Some(found) => Err(format!(
+                "mentions the blocked topic '{}'",
+                found.as_str().to_lowercase()
+            )),
+            None => Ok(()),
+        };
+        Box::pin(async move { result })
+    }
+}
+
+/// Rejects content matching a regular expression
+#[derive(Debug, Clone)]
+pub struct RegexGuardrail {
+    name: String,
+    pattern: Regex,
+    message: String,
+}
+
+impl RegexGuardrail {
+    /// Reject content matching `pattern`, reporting `message` as the reason
+    pub fn new(name: impl Into<String>, pattern: &str, message: impl Into<String>) -> Result<Self> {
+        let name = name.into();
+        let pattern = Regex::new(pattern).map_err(|err| {
+            AgentError::Config(format!(
+                "Guardrail '{}' has an invalid pattern: {}",
+                name, err
+            ))
+        })?;
+        Ok(Self {
+            name,
+            pattern,
+            message: message.into(),
+        })
+    }
+}
+
+impl Guardrail for RegexGuardrail {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check<'a>(
+        &'a self,
+        content: &'a GuardrailContent,
+    ) -> Pin<Box<dyn Future<Output = GuardrailResult> + Send + 'a>> {
+        let result = if self.pattern.is_match(&content.full_text()) {
+            Err(self.message.clone())
+        } else {
+            Ok(())
+        };
+        Box::pin(async move { result })
+    }
+}
+
+/// Rejects content containing email addresses, phone numbers, card numbers or US SSNs
+#[derive(Debug, Clone)]
+pub struct PiiGuardrail {
+    detectors: Vec<(&'static str, Regex)>,
+}
+
+impl PiiGuardrail {
+    pub fn new() -> Self {
+        let detectors = [
+            ("an email address", r"[\w.+-]+@[\w-]+\.[\w.-]+"),
+            ("a card number", r"\b(?:\d[ -]?){12,18}\d\b"),
+            ("a social security number", r"\b\d{3}-\d{2}-\d{4}\b"),
+            (
+                "a phone number",
+                r"(?:\+\d{1,3}[ .-]?)?\(?\b\d{3}\)?[ .-]\d{3}[ .-]\d{4}\b",
+            ),
+        ];
+        Self {
+            detectors: detectors
+                .into_iter()
+                .map(|(label, pattern)| {
+                    (
+                        label,
+                        Regex::new(pattern).expect("built-in PII patterns are valid"),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Default for PiiGuardrail {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Guardrail for PiiGuardrail {
+    fn name(&self) -> &str {
+        "pii"
+    }
+
+    fn check<'a>(
+        &'a self,
+        content: &'a GuardrailContent,
+    ) -> Pin<Box<dyn Future<Output = GuardrailResult> + Send + 'a>> {
+        let text = content.full_text();
+        let result = match self
+            .detectors
+            .iter()
+            .find(|(_, pattern)| pattern.is_match(&text))
+        {
+            Some((label, _)) => Err(format!("contains {}", label)),
+            None => Ok(()),
+        };
+        Box::pin(async move { result })
+    }
+}
+
+/// Rejects text longer than a number of characters
+#[derive(Debug, Clone, Copy)]
+pub struct MaxLength {
+    max_chars: usize,
+}
+
+impl MaxLength {
+    pub fn new(max_chars: usize) -> Self {
+        Self { max_chars }
+    }
+}
+
+impl Guardrail for MaxLength {
+    fn name(&self) -> &str {
+        "max_length"
+    }
+
+    fn check<'a>(
+        &'a self,
+        content: &'a GuardrailContent,
+    ) -> Pin<Box<dyn Future<Output = GuardrailResult> + Send + 'a>> {
+        let length = content.text.chars().count();
+        let result = if length > self.max_chars {
+            Err(format!(
+                "is {} characters long; the limit is {}",
+                length, self.max_chars
+            ))
+        } else {
+            Ok(())
+        };
+        Box::pin(async move { result })
+    }
+}
+
+/// A guardrail written as an async closure, e.g. to call a moderation API
+pub struct FnGuardrail<F> {
+    name: String,
+    check: F,
+}
+
+impl<F, Fut> FnGuardrail<F>
+where
+    F: Fn(GuardrailContent) -> Fut + Send + Sync,
+    Fut: Future<Output = GuardrailResult> + Send + 'static,
+{
+    pub fn new(name: impl Into<String>, check: F) -> Self {
+        Self {
+            name: name.into(),
+            check,
+        }
+    }
+}
+
+impl<F> fmt::Debug for FnGuardrail<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnGuardrail")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, Fut> Guardrail for FnGuardrail<F>
+where
+    F: Fn(GuardrailContent) -> Fut + Send + Sync,
+    Fut: Future<Output = GuardrailResult> + Send + 'static,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check<'a>(
+        &'a self,
+        content: &'a GuardrailContent,
+    ) -> Pin<Box<dyn Future<Output = GuardrailResult> + Send + 'a>> {
+        Box::pin((self.check)(content.clone()))
+    }
+}
+
+/// The guardrails an agent applies to its runs
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Guardrails {
+    input: Vec<Arc<dyn Guardrail>>,
+    output: Vec<(Arc<dyn Guardrail>, GuardrailAction)>,
+}
+
+impl Guardrails {
+    pub(crate) fn add_input(&mut self, guardrail: Arc<dyn Guardrail>) {
+        self.input.push(guardrail);
+    }
+
+    pub(crate) fn add_output(&mut self, guardrail: Arc<dyn Guardrail>, action: GuardrailAction) {
+        self.output.push((guardrail, action));
+    }
+
+    /// Fail with [`AgentError::Guardrail`] if any input guardrail rejects `task`
+    pub(crate) async fn check_input(&self, task: &str) -> Result<()> {
+        let content = GuardrailContent {
+            stage: GuardrailStage::Input,
+            text: task.to_string(),
+            structured: None,
+        };
+        for guardrail in &self.input {
+            if let Err(message) = guardrail.check(&content).await {
+                return Err(violation(guardrail.as_ref(), GuardrailStage::Input, message).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a final answer: `Ok(Some(..))` is a violation to send back to the model, and a
+    /// violation of an aborting guardrail is returned as an error
+    pub(crate) async fn check_output(
+        &self,
+        answer: &str,
+        structured: Option<&Value>,
+    ) -> Result<Option<GuardrailViolation>> {
+        if self.output.is_empty() {
+            return Ok(None);
+        }
+
+        let content = GuardrailContent {
+            stage: GuardrailStage::Output,
+            text: answer.to_string(),
+            structured: structured.cloned(),
+        };
+        for (guardrail, action) in &self.output {
+            if let Err(message) = guardrail.check(&content).await {
+                let violation = violation(guardrail.as_ref(), GuardrailStage::Output, message);
+                return match action {
+                    GuardrailAction::Abort => Err(violation.into()),
+                    GuardrailAction::Correct => Ok(Some(violation)),
+                };
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn violation(
+    guardrail: &dyn Guardrail,
+    stage: GuardrailStage,
+    message: String,
+) -> GuardrailViolation {
+    GuardrailViolation {
+        guardrail: guardrail.name().to_string(),
+        stage,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn output(text: &str, structured: Option<Value>) -> GuardrailContent {
+        GuardrailContent {
+            stage: GuardrailStage::Output,
+            text: text.to_string(),
+            structured,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocked_topics_match_whole_words() {
+        let guardrail = BlockedTopics::new(["crypto", "stock tips"]);
+        assert!(guardrail
+            .check(&output("Any Stock Tips?", None))
+            .await
+            .is_err());
+        assert!(guardrail
+            .check(&output("cryptography basics", None))
+            .await
+            .is_ok());
+        assert!(BlockedTopics::new(Vec::<String>::new())
+            .check(&output("anything", None))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pii_is_found_in_text_and_structured_payload() {
+        let guardrail = PiiGuardrail::new();
+        assert_eq!(
+            guardrail
+                .check(&output("Reach me at jane@example.com", None))
+                .await,
+            Err("contains an email address".to_string())
+        );
+        assert_eq!(
+            guardrail
+                .check(&output(
+                    "Done",
+                    Some(json!({ "card": "4111 1111 1111 1111" }))
+                ))
+                .await,
+            Err("contains a card number".to_string())
+        );
+        assert!(guardrail
+            .check(&output("Order 12345 ships on 2024-05-01", None))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_output_actions_decide_between_abort_and_correction() {
+        let mut guardrails = Guardrails::default();
+        guardrails.add_output(Arc::new(MaxLength::new(5)), GuardrailAction::Correct);
+        let violation = guardrails.check_output("too long", None).await.unwrap();
+        assert_eq!(violation.unwrap().guardrail, "max_length");
+
+        guardrails.add_input(Arc::new(
+            RegexGuardrail::new("no_sql", r"(?i)\bdrop\s+table\b", "looks like SQL").unwrap(),
+        ));
+        let error = guardrails
+            .check_input("DROP TABLE users")
+            .await
+            .unwrap_err();
+        assert_eq!(error.error_code(), "GUARDRAIL_VIOLATION");
+        assert!(RegexGuardrail::new("bad", "(", "never").is_err());
+    }
+}
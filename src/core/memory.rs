@@ -1,6 +1,16 @@
-use super::steps::AgentStep;
+use super::{
+    prompt::{PromptContext, PromptTemplate},
+    redaction::Redactor,
+    steps::{attach_reasoning, AgentStep},
+};
+use crate::{
+    services::reasoning::extract_reasoning, tools::ToolSelection,
+    types::content::split_message_content,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
 use tracing::info;
 
 /// Memory structure that replaces raw `Vec<Value>` messages
@@ -9,6 +19,15 @@ use tracing::info;
 pub struct AgentMemory {
     steps: Vec<AgentStep>,
     system_prompt: Option<String>,
+    #[serde(default)]
+    replay_reasoning: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "ToolSelection::is_all")]
+    tool_selection: ToolSelection,
+    #[serde(skip)]
+    step_sender: Option<UnboundedSender<AgentStep>>,
+    #[serde(skip)]
+    redactor: Option<Arc<Redactor>>,
 }
 
 impl AgentMemory {
@@ -17,23 +36,89 @@ impl AgentMemory {
         Self {
             steps: Vec::new(),
             system_prompt,
+            replay_reasoning: false,
+            tool_selection: ToolSelection::all(),
+            step_sender: None,
+            redactor: None,
         }
     }
 
+    /// Send captured reasoning back on the next assistant message (required by some providers)
+    pub fn set_replay_reasoning(&mut self, replay_reasoning: bool) {
+        self.replay_reasoning = replay_reasoning;
+    }
+
+    /// Only offer the tools in `selection` to runs on this memory, on top of the agent's own
+    /// selection
+    pub fn set_tool_selection(&mut self, selection: ToolSelection) {
+        self.tool_selection = selection;
+    }
+
+    pub fn tool_selection(&self) -> &ToolSelection {
+        &self.tool_selection
+    }
+
+    /// Replace the system prompt, e.g. when another agent takes over the conversation
+    pub fn set_system_prompt(&mut self, system_prompt: Option<String>) {
+        self.system_prompt = system_prompt;
+    }
+
+    /// System prompt sent before the steps
+    pub fn system_prompt(&self) -> Option<&str> {
+        self.system_prompt.as_deref()
+    }
+
     /// Create memory with default system prompt
     pub fn with_default_system() -> Self {
         Self::new(Some(
-            "You are a helpful assistant with access to tools. Use tools when necessary to provide accurate information. Be concise and helpful. When you are ready to give the final response, you MUST call the `final_answer` tool with an `answer` string instead of replying directly.".to_string()
+            PromptTemplate::default().render(&PromptContext::default()),
         ))
     }
 
     /// Add a step to memory
     pub fn add_step(&mut self, step: AgentStep) {
-        let description = step.describe();
-        info!(target: "tinyagent::steps", "{}", description);
+        let shown = self.redacted_step(&step);
+        info!(target: "tinyagent::steps", "{}", shown.describe());
+        if let Some(sender) = &self.step_sender {
+            // A dropped receiver only means nobody is listening anymore
+            let _ = sender.send(shown);
+        }
         self.steps.push(step);
     }
 
+    /// Mask secrets in the steps that are logged and sent to the step sender; the steps kept
+    /// in memory (and sent to the model) are unchanged
+    pub(crate) fn set_redactor(&mut self, redactor: Option<Arc<Redactor>>) {
+        self.redactor = redactor;
+    }
+
+    /// `step` as it is logged and streamed
+    pub(crate) fn redacted_step(&self, step: &AgentStep) -> AgentStep {
+        match &self.redactor {
+            Some(redactor) => redactor.redact_step(step, &self.steps),
+            None => step.clone(),
+        }
+    }
+
+    /// Send every step added from now on to `sender` (e.g. to stream a run live)
+    pub fn set_step_sender(&mut self, sender: Option<UnboundedSender<AgentStep>>) {
+        self.step_sender = sender;
+    }
+
+    pub(crate) fn step_sender(&self) -> Option<&UnboundedSender<AgentStep>> {
+        self.step_sender.as_ref()
+    }
+
+    /// Append steps that were already sent to the step sender while they happened
+    pub(crate) fn record_sent_steps(&mut self, steps: Vec<AgentStep>) {
+        self.steps.extend(steps);
+    }
+
+    /// Replace all steps without notifying the step sender
+    pub(crate) fn replace_steps(&mut self, steps: Vec<AgentStep>) {
+        self.steps = steps;
+    }
+
     /// Get all steps
     pub fn steps(&self) -> &[AgentStep] {
         &self.steps
@@ -55,8 +140,23 @@ impl AgentMemory {
             }));
         }
 
+        let mut pending_reasoning: Option<(&str, &[Value])> = None;
         for step in &self.steps {
-            messages.push(step.to_message());
+            if let AgentStep::Reasoning { content, details } = step {
+                pending_reasoning = Some((content.as_str(), details.as_slice()));
+                continue;
+            }
+
+            for mut message in step.to_messages() {
+                if message.get("role").and_then(|role| role.as_str()) == Some("assistant") {
+                    if let Some((content, details)) = pending_reasoning.take() {
+                        if self.replay_reasoning {
+                            attach_reasoning(&mut message, content, details);
+                        }
+                    }
+                }
+                messages.push(message);
+            }
         }
 
         messages
@@ -133,13 +233,23 @@ impl From<Vec<Value>> for AgentMemory {
                         }
                     }
                     "user" => {
-                        if let Some(content) = msg.get("content").and_then(|c| c.as_str()) {
+                        if let Some((content, attachments)) =
+                            msg.get("content").and_then(split_message_content)
+                        {
                             memory.add_step(AgentStep::Task {
-                                content: content.to_string(),
+                                content,
+                                attachments,
                             });
                         }
                     }
                     "assistant" => {
+                        if let Some(reasoning) = extract_reasoning(&msg) {
+                            memory.add_step(AgentStep::Reasoning {
+                                content: reasoning.content,
+                                details: reasoning.details,
+                            });
+                        }
+
                         if let Some(tool_calls) = msg.get("tool_calls") {
                             if let Some(calls_array) = tool_calls.as_array() {
                                 for call in calls_array {
@@ -185,6 +295,9 @@ impl From<Vec<Value>> for AgentMemory {
                                 tool_call_id: id.to_string(),
                                 result: content.to_string(),
                                 is_error,
+                                attachments: Vec::new(),
+                                structured: None,
+                                artifacts: Vec::new(),
                             });
                         }
                     }
@@ -197,7 +310,7 @@ impl From<Vec<Value>> for AgentMemory {
     }
 }
 
-fn detect_tool_error(content: &str) -> bool {
+pub(crate) fn detect_tool_error(content: &str) -> bool {
     match serde_json::from_str::<Value>(content) {
         Ok(Value::Object(map)) => map.get("error").map(|err| !err.is_null()).unwrap_or(false),
         _ => false,
@@ -207,6 +320,7 @@ fn detect_tool_error(content: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::content::ContentPart;
 
     #[test]
     fn test_memory_creation() {
@@ -220,6 +334,7 @@ mod tests {
         let mut memory = AgentMemory::default();
         memory.add_step(AgentStep::Task {
             content: "Test task".to_string(),
+            attachments: Vec::new(),
         });
         assert_eq!(memory.step_count(), 1);
         assert!(!memory.is_empty());
@@ -230,6 +345,7 @@ mod tests {
         let mut memory = AgentMemory::with_default_system();
         memory.add_step(AgentStep::Task {
             content: "Hello".to_string(),
+            attachments: Vec::new(),
         });
 
         let messages = memory.as_messages();
@@ -238,6 +354,76 @@ mod tests {
         assert_eq!(messages[1]["role"], "user");
     }
 
+    #[test]
+    fn test_tool_attachments_follow_tool_message() {
+        let mut memory = AgentMemory::new(None);
+        memory.add_step(AgentStep::Observation {
+            tool_call_id: "call_1".to_string(),
+            result: "{\"status\":\"captured\"}".to_string(),
+            is_error: false,
+            attachments: vec![ContentPart::image_base64("image/png", "AQID")],
+            structured: None,
+            artifacts: Vec::new(),
+        });
+
+        let messages = memory.as_messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["role"], "tool");
+        assert_eq!(messages[1]["role"], "user");
+        assert_eq!(
+            messages[1]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,AQID"
+        );
+    }
+
+    #[test]
+    fn test_from_messages_keeps_image_parts() {
+        let memory = AgentMemory::from(vec![serde_json::json!({
+            "role": "user",
+            "content": [
+                {"type": "text", "text": "What is in this picture?"},
+                {"type": "image_url", "image_url": {"url": "https://example.com/cat.png"}}
+            ]
+        })]);
+
+        match memory.last_step() {
+            Some(AgentStep::Task {
+                content,
+                attachments,
+            }) => {
+                assert_eq!(content, "What is in this picture?");
+                assert_eq!(
+                    attachments,
+                    &vec![ContentPart::image_url("https://example.com/cat.png")]
+                );
+            }
+            other => panic!("expected task step, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reasoning_replayed_on_next_assistant_message() {
+        let mut memory = AgentMemory::new(None);
+        memory.add_step(AgentStep::Reasoning {
+            content: "Need the calculator".to_string(),
+            details: vec![serde_json::json!({"type": "reasoning.encrypted", "data": "abc"})],
+        });
+        memory.add_step(AgentStep::Action {
+            tool_name: "calculator".to_string(),
+            tool_call_id: "1".to_string(),
+            arguments: Value::Null,
+        });
+
+        let messages = memory.as_messages();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].get("reasoning").is_none());
+
+        memory.set_replay_reasoning(true);
+        let messages = memory.as_messages();
+        assert_eq!(messages[0]["reasoning"], "Need the calculator");
+        assert_eq!(messages[0]["reasoning_details"][0]["data"], "abc");
+    }
+
     #[test]
     fn test_count_actions() {
         let mut memory = AgentMemory::default();
@@ -1,14 +1,26 @@
 pub mod agent;
 pub(crate) mod conversation;
+pub mod guardrails;
 pub mod memory;
+pub mod prompt;
+pub mod redaction;
 pub mod steps;
+pub mod team;
 pub mod tool_call;
 
 pub use crate::services::planning::{
     generate_planning_prompt, generate_tool_planning_prompt, get_tool_names, is_planning_response,
+    PlanningConfig,
 };
 pub use crate::types::result::{RunResult, TokenUsage};
 pub use agent::Agent;
+pub use guardrails::{
+    BlockedTopics, FnGuardrail, Guardrail, GuardrailAction, GuardrailContent, MaxLength,
+    PiiGuardrail, RegexGuardrail,
+};
 pub use memory::AgentMemory;
+pub use prompt::{PromptContext, PromptTemplate, DEFAULT_SYSTEM_PROMPT};
+pub use redaction::Redactor;
 pub use steps::AgentStep;
+pub use team::Team;
 pub use tool_call::{ToolCall, ToolExecution, ToolOutput};
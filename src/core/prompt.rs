@@ -0,0 +1,237 @@
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Default system prompt used when an agent has not been given one
+pub const DEFAULT_SYSTEM_PROMPT: &str = "You are a helpful assistant with access to tools. Use tools when necessary to provide accurate information. Be concise and helpful. {{completion_instructions}}";
+
+/// Instructions telling the model how to finish a run without a completion schema
+pub(crate) const FINAL_ANSWER_INSTRUCTIONS: &str = "When you are ready to give the final response, you MUST call the `final_answer` tool with an `answer` string instead of replying directly.";
+
+/// Values the agent supplies to built-in template variables
+#[derive(Debug, Clone, Default)]
+pub struct PromptContext {
+    /// Registered tools as `(name, description)` pairs
+    pub tools: Vec<(String, String)>,
+    /// Structured response instructions for the active completion schema, if any
+    pub schema_instructions: Option<String>,
+}
+
+/// System prompt template with `{{variable}}` placeholders.
+///
+/// Built-in variables:
+/// - `{{tools}}`: one `- name: description` line per registered tool
+/// - `{{tool_names}}`: comma-separated tool names
+/// - `{{date}}`: current UTC date as `YYYY-MM-DD`
+/// - `{{schema_instructions}}`: structured response instructions (empty without a schema)
+/// - `{{completion_instructions}}`: how to finish the run (`final_answer` or schema instructions)
+///
+/// If the template references neither `completion_instructions` nor (with a schema)
+/// `schema_instructions`, the completion instructions are appended so the agent loop can still
+/// terminate.
+/// Unknown placeholders are left untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    template: String,
+    variables: BTreeMap<String, String>,
+}
+
+impl PromptTemplate {
+    /// Create a template from raw text
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            variables: BTreeMap::new(),
+        }
+    }
+
+    /// Set a user-supplied variable
+    pub fn with_variable(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set_variable(name, value);
+        self
+    }
+
+    /// Set a user-supplied variable in place
+    pub fn set_variable(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.variables.insert(name.into(), value.into());
+    }
+
+    /// Raw template text
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Render the template with built-in and user variables
+    pub fn render(&self, context: &PromptContext) -> String {
+        let completion_instructions = context
+            .schema_instructions
+            .clone()
+            .unwrap_or_else(|| FINAL_ANSWER_INSTRUCTIONS.to_string());
+
+        let mut output = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+        let mut placed_instructions = false;
+
+        while let Some(start) = rest.find("{{") {
+            output.push_str(&rest[..start]);
+            let after_open = &rest[start + 2..];
+
+            let Some(end) = after_open.find("}}") else {
+                output.push_str(&rest[start..]);
+                rest = "";
+                break;
+            };
+
+            let name = after_open[..end].trim();
+            let value = match name {
+                "tools" => Some(render_tool_list(&context.tools)),
+                "tool_names" => Some(
+                    context
+                        .tools
+                        .iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+                "date" => Some(current_date()),
+                "schema_instructions" => {
+                    // Without a schema this is empty, so `final_answer` instructions still apply
+                    placed_instructions |= context.schema_instructions.is_some();
+                    Some(context.schema_instructions.clone().unwrap_or_default())
+                }
+                "completion_instructions" => {
+                    placed_instructions = true;
+                    Some(completion_instructions.clone())
+                }
+                other => self.variables.get(other).cloned(),
+            };
+
+            match value {
+                Some(value) => output.push_str(&value),
+                None => output.push_str(&rest[start..start + 2 + end + 2]),
+            }
+            rest = &after_open[end + 2..];
+        }
+        output.push_str(rest);
+
+        let mut rendered = output.trim_end().to_string();
+        if !placed_instructions {
+            rendered.push_str("\n\n");
+            rendered.push_str(&completion_instructions);
+        }
+        rendered
+    }
+}
+
+impl Default for PromptTemplate {
+    fn default() -> Self {
+        Self::new(DEFAULT_SYSTEM_PROMPT)
+    }
+}
+
+impl From<&str> for PromptTemplate {
+    fn from(template: &str) -> Self {
+        Self::new(template)
+    }
+}
+
+impl From<String> for PromptTemplate {
+    fn from(template: String) -> Self {
+        Self::new(template)
+    }
+}
+
+fn render_tool_list(tools: &[(String, String)]) -> String {
+    if tools.is_empty() {
+        return "No tools available".to_string();
+    }
+
+    tools
+        .iter()
+        .map(|(name, description)| format!("- {}: {}", name, description))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Current UTC date formatted as `YYYY-MM-DD`
+fn current_date() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Convert days since 1970-01-01 into a proleptic Gregorian date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_prompt_includes_final_answer_instructions() {
+        let rendered = PromptTemplate::default().render(&PromptContext::default());
+        assert!(rendered.starts_with("You are a helpful assistant"));
+        assert!(rendered.ends_with(FINAL_ANSWER_INSTRUCTIONS));
+    }
+
+    #[test]
+    fn test_render_builtin_and_user_variables() {
+        let template = PromptTemplate::new("You are {{persona}}. Tools:\n{{tools}}\n{{unknown}}")
+            .with_variable("persona", "a travel agent");
+        let context = PromptContext {
+            tools: vec![("weather".to_string(), "Get weather".to_string())],
+            schema_instructions: None,
+        };
+
+        let rendered = template.render(&context);
+        assert!(rendered.starts_with("You are a travel agent. Tools:\n- weather: Get weather"));
+        assert!(rendered.contains("{{unknown}}"));
+        assert!(rendered.ends_with(FINAL_ANSWER_INSTRUCTIONS));
+    }
+
+    #[test]
+    fn test_schema_instructions_placed_once() {
+        let template = PromptTemplate::new("{{schema_instructions}}\nBe brief.");
+        let context = PromptContext {
+            tools: Vec::new(),
+            schema_instructions: Some("Structured response requirement: X".to_string()),
+        };
+
+        let rendered = template.render(&context);
+        assert_eq!(rendered, "Structured response requirement: X\nBe brief.");
+    }
+
+    #[test]
+    fn test_schema_instructions_without_schema_keep_final_answer_instructions() {
+        let template = PromptTemplate::new("Be brief.{{schema_instructions}}");
+
+        let rendered = template.render(&PromptContext::default());
+        assert_eq!(
+            rendered,
+            format!("Be brief.\n\n{}", FINAL_ANSWER_INSTRUCTIONS)
+        );
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+}
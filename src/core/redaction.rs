@@ -0,0 +1,369 @@
+//! Masking of secrets in logs and traces; the model always sees the real values
+
+use super::steps::AgentStep;
+use crate::{
+    error::{AgentError, Result},
+    tools::tool::wire_name,
+    types::result::RunResult,
+};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Text that replaces a secret unless [`Redactor::with_replacement`] sets another
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Common credential formats masked by [`Redactor::new`]
+const DEFAULT_PATTERNS: &[&str] = &[
+    // OpenAI, Anthropic and OpenRouter style API keys
+    r"\bsk-[A-Za-z0-9_-]{16,}",
+    r"(?i)\bbearer\s+[A-Za-z0-9._~+/-]{8,}=*",
+    r"\bAKIA[0-9A-Z]{16}\b",
+    r"\bgh[pousr]_[A-Za-z0-9]{30,}\b",
+    r"\bxox[abprs]-[A-Za-z0-9-]{10,}",
+    r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+    r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+];
+
+/// Masks secrets in step logs, streamed steps, `replay()`/`explain()` and serialized
+/// [`RunResult`]s.
+///
+/// Text is matched against patterns (common API key formats by default), and JSON fields are
+/// masked by name, for every tool or per tool. Memory keeps the real values, so the model and
+/// saved conversations are unaffected.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    patterns: Vec<Regex>,
+    fields: Vec<String>,
+    tool_fields: HashMap<String, Vec<String>>,
+    replacement: String,
+}
+
+impl Redactor {
+    /// Redactor that masks common API key, token and private key formats
+    pub fn new() -> Self {
+        let mut redactor = Self::empty();
+        redactor.patterns = DEFAULT_PATTERNS
+            .iter()
+            .map(|pattern| Regex::new(pattern).expect("built-in redaction patterns are valid"))
+            .collect();
+        redactor
+    }
+
+    /// Redactor without any patterns or field masks
+    pub fn empty() -> Self {
+        Self {
+            patterns: Vec::new(),
+            fields: Vec::new(),
+            tool_fields: HashMap::new(),
+            replacement: REDACTED.to_string(),
+        }
+    }
+
+    /// Also mask text matching `pattern`
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern).map_err(|err| {
+            AgentError::Config(format!("Invalid redaction pattern '{}': {}", pattern, err))
+        })?;
+        self.patterns.push(regex);
+        Ok(self)
+    }
+
+    /// Also mask a known secret value wherever it appears, e.g. a token read from the environment
+    pub fn with_secret(mut self, secret: impl AsRef<str>) -> Self {
+        let secret = secret.as_ref();
+        if !secret.is_empty() {
+            let regex = Regex::new(&regex::escape(secret)).expect("escaped secrets are valid");
+            self.patterns.push(regex);
+        }
+        self
+    }
+
+    /// Mask these JSON fields (at any depth, case-insensitive) in the arguments and results of
+    /// every tool
+    pub fn with_fields<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.fields
+            .extend(fields.into_iter().map(|field| field.into().to_lowercase()));
+        self
+    }
+
+    /// Mask these JSON fields in the arguments and results of one tool, by registered name
+    pub fn with_tool_fields<I, S>(mut self, tool: &str, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tool_fields
+            .entry(wire_name(tool))
+            .or_default()
+            .extend(fields.into_iter().map(|field| field.into().to_lowercase()));
+        self
+    }
+
+    /// Text that replaces masked values (`[REDACTED]` by default)
+    pub fn with_replacement(mut self, replacement: impl Into<String>) -> Self {
+        self.replacement = replacement.into();
+        self
+    }
+
+    /// Mask every pattern match in `text`
+    pub fn redact_text(&self, text: &str) -> String {
+        let mut redacted = text.to_string();
+        for pattern in &self.patterns {
+            if pattern.is_match(&redacted) {
+                redacted = pattern
+                    .replace_all(&redacted, self.replacement.as_str())
+                    .into_owned();
+            }
+        }
+        redacted
+    }
+
+    /// Mask pattern matches in the strings of `value` and the fields masked for every tool
+    pub fn redact_value(&self, value: &Value) -> Value {
+        self.mask(value, None)
+    }
+
+    /// Copy of `step` with secrets masked; `history` holds the steps before it, used to find
+    /// which tool an observation or progress report belongs to
+    pub fn redact_step(&self, step: &AgentStep, history: &[AgentStep]) -> AgentStep {
+        match step {
+            AgentStep::Task {
+                content,
+                attachments,
+            } => AgentStep::Task {
+                content: self.redact_text(content),
+                attachments: attachments.clone(),
+            },
+            AgentStep::Planning { plan } => AgentStep::Planning {
+                plan: self.redact_text(plan),
+            },
+            AgentStep::Reasoning { content, details } => AgentStep::Reasoning {
+                content: self.redact_text(content),
+                details: details.clone(),
+            },
+            AgentStep::Action {
+                tool_name,
+                tool_call_id,
+                arguments,
+            } => AgentStep::Action {
+                tool_name: tool_name.clone(),
+                tool_call_id: tool_call_id.clone(),
+                arguments: self.mask(arguments, Some(tool_name)),
+            },
+            AgentStep::Observation {
+                tool_call_id,
+                result,
+                is_error,
+                attachments,
+                structured,
+                artifacts,
+            } => {
+                let tool = tool_for_call(history, tool_call_id);
+                AgentStep::Observation {
+                    tool_call_id: tool_call_id.clone(),
+                    result: self.redact_result(result, tool),
+                    is_error: *is_error,
+                    attachments: attachments.clone(),
+                    structured: structured.as_ref().map(|value| self.mask(value, tool)),
+                    artifacts: artifacts.clone(),
+                }
+            }
+            AgentStep::Progress {
+                tool_call_id,
+                message,
+                data,
+            } => {
+                let tool = tool_for_call(history, tool_call_id);
+                AgentStep::Progress {
+                    tool_call_id: tool_call_id.clone(),
+                    message: self.redact_text(message),
+                    data: data.as_ref().map(|value| self.mask(value, tool)),
+                }
+            }
+            AgentStep::Delegation {
+                tool_call_id,
+                agent,
+                depth,
+                run,
+            } => AgentStep::Delegation {
+                tool_call_id: tool_call_id.clone(),
+                agent: agent.clone(),
+                depth: *depth,
+                run: Box::new(self.redact_run(run)),
+            },
+            AgentStep::Reply { content } => AgentStep::Reply {
+                content: self.redact_text(content),
+            },
+            AgentStep::Reminder { label, message } => AgentStep::Reminder {
+                label: label.clone(),
+                message: self.redact_text(message),
+            },
+            AgentStep::Handoff { from, to, reason } => AgentStep::Handoff {
+                from: from.clone(),
+                to: to.clone(),
+                reason: reason.as_deref().map(|reason| self.redact_text(reason)),
+            },
+            AgentStep::FinalAnswer { answer, structured } => AgentStep::FinalAnswer {
+                answer: self.redact_text(answer),
+                structured: structured.as_ref().map(|value| self.redact_value(value)),
+            },
+        }
+    }
+
+    /// Copy of a whole trace with secrets masked
+    pub fn redact_steps(&self, steps: &[AgentStep]) -> Vec<AgentStep> {
+        steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| self.redact_step(step, &steps[..index]))
+            .collect()
+    }
+
+    /// Copy of `run` with its output, structured payload and steps masked
+    pub fn redact_run(&self, run: &RunResult) -> RunResult {
+        let mut redacted = run.clone();
+        redacted.output = self.redact_text(&run.output);
+        redacted.structured = run
+            .structured
+            .as_ref()
+            .map(|value| self.redact_value(value));
+        redacted.steps = self.redact_steps(&run.steps);
+        redacted.redactor = None;
+        redacted
+    }
+
+    /// Tool results are usually JSON text, so mask their fields before matching patterns
+    fn redact_result(&self, result: &str, tool: Option<&str>) -> String {
+        match serde_json::from_str::<Value>(result) {
+            Ok(value @ (Value::Object(_) | Value::Array(_))) => self.mask(&value, tool).to_string(),
+            _ => self.redact_text(result),
+        }
+    }
+
+    fn mask(&self, value: &Value, tool: Option<&str>) -> Value {
+        let tool_fields = tool
+            .and_then(|tool| self.tool_fields.get(&wire_name(tool)))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        self.mask_fields(value, tool_fields)
+    }
+
+    fn mask_fields(&self, value: &Value, tool_fields: &[String]) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.redact_text(text)),
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.mask_fields(item, tool_fields))
+                    .collect(),
+            ),
+            Value::Object(object) => Value::Object(
+                object
+                    .iter()
+                    .map(|(key, value)| {
+                        let key_lower = key.to_lowercase();
+                        let masked = if self.fields.contains(&key_lower)
+                            || tool_fields.contains(&key_lower)
+                        {
+                            Value::String(self.replacement.clone())
+                        } else {
+                            self.mask_fields(value, tool_fields)
+                        };
+                        (key.clone(), masked)
+                    })
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Name of the tool the model called with `tool_call_id`
+fn tool_for_call<'a>(history: &'a [AgentStep], tool_call_id: &str) -> Option<&'a str> {
+    history.iter().rev().find_map(|step| match step {
+        AgentStep::Action {
+            tool_name,
+            tool_call_id: id,
+            ..
+        } if id == tool_call_id => Some(tool_name.as_str()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_default_patterns_mask_common_credentials() {
+        let redactor = Redactor::new();
+        assert_eq!(
+            redactor.redact_text("key sk-abcdefghijklmnopqrstuvwx and Bearer abc.def-ghi_jkl"),
+            "key [REDACTED] and [REDACTED]"
+        );
+        assert_eq!(redactor.redact_text("nothing secret"), "nothing secret");
+        assert!(Redactor::empty().with_pattern("(").is_err());
+    }
+
+    #[test]
+    fn test_tool_fields_mask_arguments_and_results_of_that_tool() {
+        let redactor = Redactor::empty()
+            .with_secret("hunter2")
+            .with_tool_fields("web.fetch", ["Authorization"]);
+        let steps = vec![
+            AgentStep::Action {
+                tool_name: "web__fetch".to_string(),
+                tool_call_id: "call_1".to_string(),
+                arguments: json!({ "url": "https://x.test", "headers": { "authorization": "t0ken" } }),
+            },
+            AgentStep::Observation {
+                tool_call_id: "call_1".to_string(),
+                result: json!({ "authorization": "t0ken", "body": "pw hunter2" }).to_string(),
+                is_error: false,
+                attachments: Vec::new(),
+                structured: None,
+                artifacts: Vec::new(),
+            },
+            AgentStep::Action {
+                tool_name: "calculator".to_string(),
+                tool_call_id: "call_2".to_string(),
+                arguments: json!({ "authorization": "kept" }),
+            },
+        ];
+
+        let redacted = redactor.redact_steps(&steps);
+        match &redacted[0] {
+            AgentStep::Action { arguments, .. } => {
+                assert_eq!(arguments["headers"]["authorization"], REDACTED);
+                assert_eq!(arguments["url"], "https://x.test");
+            }
+            other => panic!("unexpected step {:?}", other),
+        }
+        match &redacted[1] {
+            AgentStep::Observation { result, .. } => {
+                let result: Value = serde_json::from_str(result).unwrap();
+                assert_eq!(
+                    result,
+                    json!({ "authorization": REDACTED, "body": "pw [REDACTED]" })
+                );
+            }
+            other => panic!("unexpected step {:?}", other),
+        }
+        match &redacted[2] {
+            AgentStep::Action { arguments, .. } => assert_eq!(arguments["authorization"], "kept"),
+            other => panic!("unexpected step {:?}", other),
+        }
+    }
+}
@@ -1,3 +1,10 @@
+use crate::{
+    tools::result::Artifact,
+    types::{
+        content::{message_content, tool_attachments_message, ContentPart},
+        result::RunResult,
+    },
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -6,9 +13,23 @@ use serde_json::Value;
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum AgentStep {
     /// Initial task provided by the user
-    Task { content: String },
+    Task {
+        content: String,
+        /// Images or files sent alongside the task text
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        attachments: Vec<ContentPart>,
+    },
     /// Planning step where the agent thinks about how to approach the task
     Planning { plan: String },
+    /// Reasoning returned by a reasoning model before its reply
+    Reasoning {
+        content: String,
+        /// Raw `reasoning_details` blocks (including encrypted ones) kept for replay
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        details: Vec<Value>,
+    },
     /// Action step where the agent calls a tool
     Action {
         tool_name: String,
@@ -20,6 +41,50 @@ pub enum AgentStep {
         tool_call_id: String,
         result: String,
         is_error: bool,
+        /// Images or files returned by the tool, forwarded to the model as a user message
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        attachments: Vec<ContentPart>,
+        /// Structured data from a [`ToolResult`](crate::ToolResult), kept for the caller only
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        structured: Option<Value>,
+        /// Files from a [`ToolResult`](crate::ToolResult), kept for the caller only
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        artifacts: Vec<Artifact>,
+    },
+    /// Progress reported by a running tool through [`crate::tools::ToolContext::progress`]
+    Progress {
+        tool_call_id: String,
+        message: String,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        data: Option<Value>,
+    },
+    /// Run of a sub-agent invoked through a tool call, nested into this trace
+    Delegation {
+        tool_call_id: String,
+        agent: String,
+        /// Nesting level of the sub-agent (1 for agents called directly by the root agent)
+        depth: usize,
+        run: Box<RunResult>,
+    },
+    /// Plain assistant reply that did not end the run, e.g. a payload that failed validation
+    Reply { content: String },
+    /// Correction sent to the model after a reply it has to redo
+    Reminder {
+        /// What asked for the correction: the schema, tool or guardrail involved
+        label: String,
+        message: String,
+    },
+    /// Conversation transferred from one team member to another
+    Handoff {
+        from: String,
+        to: String,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
     },
     /// Final answer from the agent
     FinalAnswer {
@@ -34,10 +99,13 @@ impl AgentStep {
     /// Convert step to OpenAI message format
     pub fn to_message(&self) -> Value {
         match self {
-            AgentStep::Task { content } => {
+            AgentStep::Task {
+                content,
+                attachments,
+            } => {
                 serde_json::json!({
                     "role": "user",
-                    "content": content
+                    "content": message_content(content, attachments)
                 })
             }
             AgentStep::Planning { plan } => {
@@ -46,6 +114,14 @@ impl AgentStep {
                     "content": plan
                 })
             }
+            AgentStep::Reasoning { content, details } => {
+                let mut message = serde_json::json!({
+                    "role": "assistant",
+                    "content": null,
+                });
+                attach_reasoning(&mut message, content, details);
+                message
+            }
             AgentStep::Action {
                 tool_name,
                 tool_call_id,
@@ -75,6 +151,36 @@ impl AgentStep {
                     "content": result
                 })
             }
+            AgentStep::Progress { message, .. } => {
+                serde_json::json!({
+                    "role": "system",
+                    "content": format!("Tool progress: {}", message)
+                })
+            }
+            AgentStep::Delegation { run, .. } => {
+                serde_json::json!({
+                    "role": "assistant",
+                    "content": run.output
+                })
+            }
+            AgentStep::Reply { content } => {
+                serde_json::json!({
+                    "role": "assistant",
+                    "content": content
+                })
+            }
+            AgentStep::Reminder { message, .. } => {
+                serde_json::json!({
+                    "role": "system",
+                    "content": message
+                })
+            }
+            AgentStep::Handoff { from, to, .. } => {
+                serde_json::json!({
+                    "role": "system",
+                    "content": format!("Conversation transferred from {} to {}.", from, to)
+                })
+            }
             AgentStep::FinalAnswer { answer, .. } => {
                 serde_json::json!({
                     "role": "assistant",
@@ -84,11 +190,68 @@ impl AgentStep {
         }
     }
 
+    /// Convert step to OpenAI messages, including a follow-up user message for tool attachments.
+    ///
+    /// Reasoning steps produce no standalone message; [`AgentMemory`](super::AgentMemory)
+    /// merges them into the next assistant message when reasoning replay is enabled.
+    /// Progress, delegation and handoff steps are trace-only: the model sees their outcome
+    /// through the corresponding tool call and observation.
+    pub fn to_messages(&self) -> Vec<Value> {
+        if matches!(
+            self,
+            AgentStep::Reasoning { .. }
+                | AgentStep::Progress { .. }
+                | AgentStep::Delegation { .. }
+                | AgentStep::Handoff { .. }
+        ) {
+            return Vec::new();
+        }
+
+        let mut messages = vec![self.to_message()];
+        if let AgentStep::Observation {
+            tool_call_id,
+            attachments,
+            ..
+        } = self
+        {
+            if !attachments.is_empty() {
+                messages.push(tool_attachments_message(tool_call_id, attachments));
+            }
+        }
+        messages
+    }
+
+    /// Attachments carried by this step
+    pub fn attachments(&self) -> &[ContentPart] {
+        match self {
+            AgentStep::Task { attachments, .. } | AgentStep::Observation { attachments, .. } => {
+                attachments
+            }
+            _ => &[],
+        }
+    }
+
     /// Get a human-readable description of the step
     pub fn describe(&self) -> String {
+        let description = self.describe_text();
+        match self.attachments().len() {
+            0 => description,
+            1 => format!("{} [+1 attachment]", description),
+            count => format!("{} [+{} attachments]", description, count),
+        }
+    }
+
+    fn describe_text(&self) -> String {
         match self {
-            AgentStep::Task { content } => format!("🧭 Task: {}", content),
+            AgentStep::Task { content, .. } => format!("🧭 Task: {}", content),
             AgentStep::Planning { plan } => format!("🧩 Plan: {}", plan),
+            AgentStep::Reasoning { content, details } => {
+                if content.is_empty() {
+                    format!("💭 Reasoning: [{} encrypted block(s)]", details.len())
+                } else {
+                    format!("💭 Reasoning: {}", content)
+                }
+            }
             AgentStep::Action {
                 tool_name,
                 arguments,
@@ -105,7 +268,38 @@ impl AgentStep {
                     format!("👁 Observation: {}", result)
                 }
             }
+            AgentStep::Progress { message, data, .. } => match data {
+                Some(data) => format!("⏳ Progress: {} {}", message, data),
+                None => format!("⏳ Progress: {}", message),
+            },
+            AgentStep::Delegation {
+                agent, depth, run, ..
+            } => format!(
+                "🤝 Delegation: {} (depth {}, {} steps): {}",
+                agent,
+                depth,
+                run.steps.len(),
+                run.output
+            ),
+            AgentStep::Reply { content } => format!("💬 Reply: {}", content),
+            AgentStep::Reminder { label, message } => {
+                format!("📌 Reminder ({}): {}", label, message)
+            }
+            AgentStep::Handoff { from, to, reason } => match reason {
+                Some(reason) => format!("🔀 Handoff: {} → {} ({})", from, to, reason),
+                None => format!("🔀 Handoff: {} → {}", from, to),
+            },
             AgentStep::FinalAnswer { answer, .. } => format!("✅ Final Answer: {}", answer),
         }
     }
 }
+
+/// Add reasoning fields to an assistant message so providers can resume their reasoning
+pub(crate) fn attach_reasoning(message: &mut Value, content: &str, details: &[Value]) {
+    if !content.is_empty() {
+        message["reasoning"] = Value::String(content.to_string());
+    }
+    if !details.is_empty() {
+        message["reasoning_details"] = Value::Array(details.to_vec());
+    }
+}
@@ -0,0 +1,289 @@
+use super::{agent::Agent, memory::AgentMemory, steps::AgentStep};
+use crate::{
+    error::{AgentError, Result},
+    tools::CancellationToken,
+    types::result::{accumulate_usage, RunResult, TokenUsage},
+};
+use serde_json::{json, Value};
+use std::time::Instant;
+
+/// Default number of handoffs allowed in a single team run
+pub const DEFAULT_MAX_HANDOFFS: usize = 5;
+
+/// A team member another agent can transfer the conversation to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HandoffTarget {
+    pub name: String,
+    pub tool_name: String,
+    pub description: String,
+}
+
+impl HandoffTarget {
+    /// OpenAI tool definition for the `transfer_to_<name>` tool
+    pub fn tool_definition(&self) -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.tool_name,
+                "description": format!(
+                    "Transfer the conversation to the {} agent: {}",
+                    self.name, self.description
+                ),
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "reason": {
+                            "type": "string",
+                            "description": "Why the conversation is being transferred"
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// How a single agent's turn in a shared conversation ended
+#[derive(Debug)]
+pub(crate) enum LoopOutcome {
+    Finished(RunResult),
+    Handoff {
+        memory: AgentMemory,
+        target: String,
+        reason: Option<String>,
+        tokens: Option<TokenUsage>,
+        iterations: usize,
+    },
+}
+
+#[derive(Debug)]
+struct TeamMember {
+    name: String,
+    description: String,
+    agent: Agent,
+}
+
+/// Named agents that hand a conversation over to each other.
+///
+/// Every member is offered a `transfer_to_<name>` tool for each other member. Calling it ends
+/// that member's turn and the receiving agent continues with the same [`AgentMemory`], under its
+/// own system prompt and tools. Handoffs are recorded as [`AgentStep::Handoff`] in the trace.
+#[derive(Debug)]
+pub struct Team {
+    members: Vec<TeamMember>,
+    entry: Option<String>,
+    max_handoffs: usize,
+}
+
+impl Team {
+    /// Create an empty team
+    pub fn new() -> Self {
+        Self {
+            members: Vec::new(),
+            entry: None,
+            max_handoffs: DEFAULT_MAX_HANDOFFS,
+        }
+    }
+
+    /// Add a member; the first member added handles new conversations unless
+    /// [`with_entry`](Self::with_entry) says otherwise. Runs fail with [`AgentError::Config`]
+    /// if two members' names give the same [handoff tool name](Self::handoff_tool_name)
+    pub fn with_agent(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        agent: Agent,
+    ) -> Self {
+        self.members.push(TeamMember {
+            name: name.into(),
+            description: description.into(),
+            agent,
+        });
+        self
+    }
+
+    /// Choose the member that receives new conversations; runs fail with
+    /// [`AgentError::Config`] if no member has this name
+    pub fn with_entry(mut self, name: impl Into<String>) -> Self {
+        self.entry = Some(name.into());
+        self
+    }
+
+    /// Stop with [`AgentError::MaxHandoffs`] after this many handoffs, so agents cannot bounce
+    /// forever
+    pub fn with_max_handoffs(mut self, max_handoffs: usize) -> Self {
+        self.max_handoffs = max_handoffs;
+        self
+    }
+
+    /// Names of all members in the order they were added
+    pub fn agent_names(&self) -> Vec<&str> {
+        self.members
+            .iter()
+            .map(|member| member.name.as_str())
+            .collect()
+    }
+
+    /// Tool name used to transfer the conversation to `name`
+    pub fn handoff_tool_name(name: &str) -> String {
+        let sanitized: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("transfer_to_{}", sanitized)
+    }
+
+    /// Start a new conversation with the entry agent
+    pub async fn run(&self, prompt: &str) -> Result<RunResult> {
+        let mut memory = AgentMemory::new(None);
+        memory.add_step(AgentStep::Task {
+            content: prompt.to_string(),
+            attachments: Vec::new(),
+        });
+        self.run_with_memory(memory).await
+    }
+
+    /// Continue an existing conversation with the entry agent
+    pub async fn run_with_memory(&self, mut memory: AgentMemory) -> Result<RunResult> {
+        if self.members.is_empty() {
+            return Err(AgentError::Config("Team has no agents".to_string()));
+        }
+        self.check_handoff_tool_names()?;
+
+        let mut current = match &self.entry {
+            Some(name) => self.position(name).ok_or_else(|| {
+                AgentError::Config(format!(
+                    "Team has no agent named '{}' (available: {})",
+                    name,
+                    self.agent_names().join(", ")
+                ))
+            })?,
+            None => 0,
+        };
+
+        // One token for the whole team run, shared by every member it passes through
+        let cancellation = CancellationToken::new();
+        let start_time = Instant::now();
+        let mut total_usage: Option<TokenUsage> = None;
+        let mut iterations = 0;
+        let mut handoffs = 0;
+
+        loop {
+            let member = &self.members[current];
+            memory.set_system_prompt(Some(member.agent.system_prompt()));
+            memory.set_replay_reasoning(member.agent.replay_reasoning());
+
+            match member
+                .agent
+                .run_memory(
+                    memory,
+                    &self.targets_for(current),
+                    member.agent.run_context(cancellation.clone(), None),
+                )
+                .await?
+            {
+                LoopOutcome::Finished(mut result) => {
+                    if let Some(usage) = &result.tokens {
+                        accumulate_usage(&mut total_usage, usage);
+                    }
+                    result.tokens = total_usage;
+                    result.iterations += iterations;
+                    result.duration = start_time.elapsed();
+                    return Ok(result);
+                }
+                LoopOutcome::Handoff {
+                    memory: handed_over,
+                    target,
+                    reason,
+                    tokens,
+                    iterations: used,
+                } => {
+                    if let Some(usage) = &tokens {
+                        accumulate_usage(&mut total_usage, usage);
+                    }
+                    iterations += used;
+
+                    handoffs += 1;
+                    if handoffs > self.max_handoffs {
+                        return Err(AgentError::MaxHandoffs(self.max_handoffs));
+                    }
+
+                    let next = self.position(&target).ok_or_else(|| {
+                        AgentError::ToolNotFound(Self::handoff_tool_name(&target))
+                    })?;
+
+                    memory = handed_over;
+                    memory.add_step(AgentStep::Handoff {
+                        from: member.name.clone(),
+                        to: target,
+                        reason,
+                    });
+                    current = next;
+                }
+            }
+        }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.members.iter().position(|member| member.name == name)
+    }
+
+    /// Fail if two members would be offered as the same `transfer_to_<name>` tool
+    fn check_handoff_tool_names(&self) -> Result<()> {
+        for (index, member) in self.members.iter().enumerate() {
+            let tool_name = Self::handoff_tool_name(&member.name);
+            if let Some(other) = self.members[..index]
+                .iter()
+                .find(|other| Self::handoff_tool_name(&other.name) == tool_name)
+            {
+                return Err(AgentError::Config(format!(
+                    "Team members '{}' and '{}' would share the handoff tool '{}'",
+                    other.name, member.name, tool_name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn targets_for(&self, current: usize) -> Vec<HandoffTarget> {
+        self.members
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != current)
+            .map(|(_, member)| HandoffTarget {
+                name: member.name.clone(),
+                tool_name: Self::handoff_tool_name(&member.name),
+                description: member.description.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Default for Team {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handoff_tool_names_are_sanitized() {
+        assert_eq!(
+            Team::handoff_tool_name("Billing Desk"),
+            "transfer_to_billing_desk"
+        );
+        assert_eq!(
+            Team::handoff_tool_name("tech-support"),
+            "transfer_to_tech_support"
+        );
+    }
+}
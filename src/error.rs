@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Main error type for the agent system
@@ -18,6 +19,9 @@ pub enum AgentError {
     #[error("Tool execution error: {0}")]
     ToolExecution(String),
 
+    #[error("Tool execution error: {0}")]
+    ToolFailed(#[from] ToolError),
+
     #[error("Tool not found: {0}")]
     ToolNotFound(String),
 
@@ -30,9 +34,21 @@ pub enum AgentError {
     #[error("Maximum iterations exceeded: {0}")]
     MaxIterations(usize),
 
+    #[error("Maximum handoffs exceeded: {0}")]
+    MaxHandoffs(usize),
+
     #[error("Rate limit exceeded: retry after {retry_after}s")]
     RateLimit { retry_after: u64 },
 
+    #[error("MCP error: {0}")]
+    Mcp(String),
+
+    #[error("Run cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Guardrail violation: {0}")]
+    Guardrail(#[from] GuardrailViolation),
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
@@ -50,10 +66,16 @@ impl AgentError {
             AgentError::Validation(_) => true,
             AgentError::RateLimit { .. } => true,
             AgentError::Timeout(_) => true,
+            AgentError::ToolFailed(error) => error.kind == ToolErrorKind::Retryable,
             _ => false,
         }
     }
 
+    /// Check if this error ends the run instead of being reported back to the model
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, AgentError::ToolFailed(error) if error.kind == ToolErrorKind::Fatal)
+    }
+
     /// Get the error code for structured responses
     pub fn error_code(&self) -> &'static str {
         match self {
@@ -62,23 +84,108 @@ impl AgentError {
             AgentError::Serialization(_) => "SERIALIZATION_ERROR",
             AgentError::Validation(_) => "VALIDATION_ERROR",
             AgentError::ToolExecution(_) => "TOOL_EXECUTION_ERROR",
+            AgentError::Guardrail(_) => "GUARDRAIL_VIOLATION",
+            AgentError::ToolFailed(error) => match error.kind {
+                ToolErrorKind::Fatal => "TOOL_FATAL_ERROR",
+                ToolErrorKind::Retryable | ToolErrorKind::UserFixable => "TOOL_EXECUTION_ERROR",
+            },
             AgentError::ToolNotFound(_) => "TOOL_NOT_FOUND",
             AgentError::InvalidFunctionCall(_) => "INVALID_FUNCTION_CALL",
             AgentError::Timeout(_) => "TIMEOUT_ERROR",
             AgentError::MaxIterations(_) => "MAX_ITERATIONS_EXCEEDED",
+            AgentError::MaxHandoffs(_) => "MAX_HANDOFFS_EXCEEDED",
             AgentError::RateLimit { .. } => "RATE_LIMIT_ERROR",
+            AgentError::Mcp(_) => "MCP_ERROR",
+            AgentError::Cancelled(_) => "CANCELLED",
             AgentError::Unknown(_) => "UNKNOWN_ERROR",
         }
     }
 
     /// Convert to a structured error payload
     pub fn to_error_payload(&self) -> serde_json::Value {
-        serde_json::json!({
+        let mut payload = serde_json::json!({
             "error": {
                 "code": self.error_code(),
                 "message": self.to_string(),
                 "retryable": self.is_retryable()
             }
-        })
+        });
+        if let AgentError::ToolFailed(error) = self {
+            payload["error"]["kind"] = serde_json::json!(error.kind);
+        }
+        if let AgentError::Guardrail(violation) = self {
+            payload["error"]["guardrail"] = serde_json::json!(violation.guardrail);
+            payload["error"]["stage"] = serde_json::json!(violation.stage);
+        }
+        payload
     }
 }
+
+/// How a failed tool call should be handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolErrorKind {
+    /// Transient failure (network, rate limit); the same call may succeed later
+    Retryable,
+    /// The call cannot succeed as made; the model should change its arguments or ask the user
+    UserFixable,
+    /// The run cannot continue; the error is returned from the run instead of the model
+    Fatal,
+}
+
+/// Error a tool returns to say how the agent should react to it
+#[derive(Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[error("{message}")]
+pub struct ToolError {
+    pub kind: ToolErrorKind,
+    pub message: String,
+}
+
+impl ToolError {
+    pub fn new(kind: ToolErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn retryable(message: impl Into<String>) -> Self {
+        Self::new(ToolErrorKind::Retryable, message)
+    }
+
+    pub fn user_fixable(message: impl Into<String>) -> Self {
+        Self::new(ToolErrorKind::UserFixable, message)
+    }
+
+    pub fn fatal(message: impl Into<String>) -> Self {
+        Self::new(ToolErrorKind::Fatal, message)
+    }
+}
+
+/// Which side of a run a guardrail checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailStage {
+    /// The user's task, before the first model call
+    Input,
+    /// The final answer and structured payload, before the run returns
+    Output,
+}
+
+impl std::fmt::Display for GuardrailStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GuardrailStage::Input => f.write_str("input"),
+            GuardrailStage::Output => f.write_str("output"),
+        }
+    }
+}
+
+/// A guardrail rejected the input or output of a run
+#[derive(Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[error("'{guardrail}' rejected the {stage}: {message}")]
+pub struct GuardrailViolation {
+    pub guardrail: String,
+    pub stage: GuardrailStage,
+    pub message: String,
+}
@@ -12,7 +12,7 @@
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
 //!     let api_key = std::env::var("OPENAI_API_KEY")?;
 //!     let mut function_factory = FunctionFactory::new();
-//!     function_factory.register_tool(CalculatorTool::new());
+//!     function_factory.register_tool(CalculatorTool::new())?;
 //!     
 //!     let agent = Agent::new(api_key, function_factory);
 //!
@@ -26,6 +26,7 @@ extern crate self as tiny_agent_rs;
 
 pub mod core;
 pub mod error;
+pub mod mcp;
 pub mod schemas;
 pub(crate) mod services;
 pub mod tools;
@@ -33,13 +34,19 @@ pub mod types;
 
 pub use core::{
     generate_planning_prompt, generate_tool_planning_prompt, get_tool_names, is_planning_response,
-    Agent, AgentMemory, AgentStep, RunResult, TokenUsage, ToolCall, ToolExecution, ToolOutput,
+    Agent, AgentMemory, AgentStep, Guardrail, GuardrailAction, PlanningConfig, PromptTemplate,
+    Redactor, RunResult, Team, TokenUsage, ToolCall, ToolExecution, ToolOutput,
 };
-pub use error::{AgentError, Result};
+pub use error::{AgentError, GuardrailStage, GuardrailViolation, Result, ToolError, ToolErrorKind};
+pub use mcp::{McpClient, McpServer, McpServerConfig};
 pub use schemas::validator::Validator;
-pub use schemas::{schema_type_name, CompletionSchema, SchemaHandle};
+pub use schemas::{schema_type_name, CompletionSchema, SchemaHandle, StructuredOutputStrategy};
 pub use tinyagent_macros::{completion_schema, tool};
-pub use tools::{FunctionFactory, Tool};
+pub use tools::{
+    CancellationToken, FunctionFactory, SubAgentTool, Tool, ToolChoice, ToolContext, ToolResponse,
+    ToolResult, ToolSelection,
+};
+pub use types::content::ContentPart;
 pub use types::response::{deserialize_structured_response, StructuredPayload};
 
 pub use core as agent;
@@ -50,3 +57,5 @@ pub use types::vacation_types;
 
 #[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "server")]
+pub mod server;
@@ -0,0 +1,469 @@
+use super::protocol::{self, McpToolInfo, PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS};
+use crate::error::{AgentError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    process::Stdio,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    process::{Child, Command},
+    sync::Mutex,
+    time::timeout,
+};
+use tracing::debug;
+
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// How to reach an MCP server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "transport", rename_all = "snake_case")]
+pub enum McpServerConfig {
+    /// Spawn the server as a subprocess and talk JSON-RPC over its stdin/stdout
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+    },
+    /// Streamable HTTP endpoint
+    Http {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+/// Client for a single MCP server connection
+pub struct McpClient {
+    transport: Mutex<Transport>,
+    next_id: AtomicU64,
+    server_info: Value,
+    protocol_version: String,
+    request_timeout: Duration,
+}
+
+impl McpClient {
+    /// Connect using a server configuration and perform the `initialize` handshake
+    pub async fn connect(config: &McpServerConfig) -> Result<Self> {
+        match config {
+            McpServerConfig::Stdio { command, args, env } => {
+                let mut child = Command::new(command)
+                    .args(args)
+                    .envs(env)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::inherit())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .map_err(|err| {
+                        AgentError::Mcp(format!("failed to spawn `{}`: {}", command, err))
+                    })?;
+
+                let stdin = child.stdin.take().ok_or_else(|| {
+                    AgentError::Mcp(format!("`{}` stdin is not available", command))
+                })?;
+                let stdout = child.stdout.take().ok_or_else(|| {
+                    AgentError::Mcp(format!("`{}` stdout is not available", command))
+                })?;
+
+                let transport = Transport::Stdio(StdioTransport {
+                    reader: BufReader::new(Box::new(stdout)),
+                    writer: Box::new(stdin),
+                    child: Some(child),
+                });
+                Self::initialize(transport).await
+            }
+            McpServerConfig::Http { url, headers } => {
+                let transport = Transport::Http(HttpTransport {
+                    client: reqwest::Client::new(),
+                    url: url.clone(),
+                    headers: headers.clone(),
+                    session_id: None,
+                    protocol_version: None,
+                });
+                Self::initialize(transport).await
+            }
+        }
+    }
+
+    /// Spawn a stdio server
+    pub async fn connect_stdio(
+        command: impl Into<String>,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<Self> {
+        Self::connect(&McpServerConfig::Stdio {
+            command: command.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            env: BTreeMap::new(),
+        })
+        .await
+    }
+
+    /// Connect to a streamable HTTP server
+    pub async fn connect_http(url: impl Into<String>) -> Result<Self> {
+        Self::connect(&McpServerConfig::Http {
+            url: url.into(),
+            headers: BTreeMap::new(),
+        })
+        .await
+    }
+
+    /// Speak newline-delimited JSON-RPC over arbitrary streams (useful for in-process servers)
+    pub async fn connect_streams<R, W>(reader: R, writer: W) -> Result<Self>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let transport = Transport::Stdio(StdioTransport {
+            reader: BufReader::new(Box::new(reader)),
+            writer: Box::new(writer),
+            child: None,
+        });
+        Self::initialize(transport).await
+    }
+
+    async fn initialize(transport: Transport) -> Result<Self> {
+        let mut client = Self {
+            transport: Mutex::new(transport),
+            next_id: AtomicU64::new(1),
+            server_info: Value::Null,
+            protocol_version: PROTOCOL_VERSION.to_string(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+        };
+
+        let result = client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "tiny-agent-rs",
+                        "version": env!("CARGO_PKG_VERSION")
+                    }
+                }),
+            )
+            .await?;
+        client.server_info = result.get("serverInfo").cloned().unwrap_or(Value::Null);
+
+        // The server answers with the revision it will speak, which may be older than ours
+        let version = result
+            .get("protocolVersion")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                AgentError::Mcp("initialize result has no protocolVersion".to_string())
+            })?;
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&version) {
+            return Err(AgentError::Mcp(format!(
+                "server requires unsupported protocol version {}",
+                version
+            )));
+        }
+        client.protocol_version = version.to_string();
+
+        let mut transport = client.transport.lock().await;
+        transport.set_protocol_version(version);
+        transport
+            .notify(&protocol::notification(
+                "notifications/initialized",
+                json!({}),
+            ))
+            .await?;
+        drop(transport);
+
+        Ok(client)
+    }
+
+    /// Override the per-request timeout (default 60s)
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// `serverInfo` reported during initialization
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    /// Protocol revision agreed with the server during initialization
+    pub fn protocol_version(&self) -> &str {
+        &self.protocol_version
+    }
+
+    /// List all tools exposed by the server, following pagination cursors
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request("tools/list", params).await?;
+
+            let page: Vec<McpToolInfo> = serde_json::from_value(
+                result.get("tools").cloned().unwrap_or(json!([])),
+            )
+            .map_err(|err| AgentError::Mcp(format!("invalid tools/list result: {}", err)))?;
+            tools.extend(page);
+
+            cursor = result
+                .get("nextCursor")
+                .and_then(|c| c.as_str())
+                .map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// Invoke `tools/call` and return the raw MCP result
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value> {
+        self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let message = protocol::request(id, method, params);
+        debug!(target: "tinyagent::mcp", method, id, "sending MCP request");
+
+        let mut transport = self.transport.lock().await;
+        let response = timeout(self.request_timeout, transport.request(id, &message))
+            .await
+            .map_err(|_| AgentError::Timeout(format!("MCP request `{}` timed out", method)))??;
+
+        protocol::response_result(response)
+    }
+}
+
+impl fmt::Debug for McpClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("McpClient")
+            .field("server_info", &self.server_info)
+            .field("protocol_version", &self.protocol_version)
+            .field("request_timeout", &self.request_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+enum Transport {
+    Stdio(StdioTransport),
+    Http(HttpTransport),
+}
+
+impl Transport {
+    async fn request(&mut self, id: u64, message: &Value) -> Result<Value> {
+        match self {
+            Transport::Stdio(stdio) => stdio.request(id, message).await,
+            Transport::Http(http) => http.request(id, message).await,
+        }
+    }
+
+    /// Revision to announce on every later request; only HTTP carries it outside `initialize`
+    fn set_protocol_version(&mut self, version: &str) {
+        if let Transport::Http(http) = self {
+            http.protocol_version = Some(version.to_string());
+        }
+    }
+
+    async fn notify(&mut self, message: &Value) -> Result<()> {
+        match self {
+            Transport::Stdio(stdio) => stdio.send(message).await,
+            Transport::Http(http) => http.post(message).await.map(|_| ()),
+        }
+    }
+}
+
+struct StdioTransport {
+    reader: BufReader<Box<dyn AsyncRead + Send + Unpin>>,
+    writer: Box<dyn AsyncWrite + Send + Unpin>,
+    // Held so the subprocess is killed when the client is dropped
+    #[allow(dead_code)]
+    child: Option<Child>,
+}
+
+impl StdioTransport {
+    async fn send(&mut self, message: &Value) -> Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|err| AgentError::Mcp(format!("failed to write to server: {}", err)))?;
+        self.writer
+            .flush()
+            .await
+            .map_err(|err| AgentError::Mcp(format!("failed to flush server stdin: {}", err)))
+    }
+
+    async fn request(&mut self, id: u64, message: &Value) -> Result<Value> {
+        self.send(message).await?;
+
+        loop {
+            let mut line = String::new();
+            let read =
+                self.reader.read_line(&mut line).await.map_err(|err| {
+                    AgentError::Mcp(format!("failed to read from server: {}", err))
+                })?;
+            if read == 0 {
+                return Err(AgentError::Mcp("server closed the connection".to_string()));
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let incoming: Value = serde_json::from_str(line.trim())
+                .map_err(|err| AgentError::Mcp(format!("invalid JSON from server: {}", err)))?;
+
+            if incoming.get("method").is_some() {
+                // Server-initiated request or notification; answer pings, reject everything else
+                if let Some(request_id) = incoming.get("id").cloned() {
+                    let reply = if incoming.get("method").and_then(|m| m.as_str()) == Some("ping") {
+                        protocol::response(request_id, json!({}))
+                    } else {
+                        protocol::error_response(
+                            request_id,
+                            protocol::METHOD_NOT_FOUND,
+                            "Method not supported by client",
+                            None,
+                        )
+                    };
+                    self.send(&reply).await?;
+                }
+                continue;
+            }
+
+            if incoming.get("id").and_then(|v| v.as_u64()) == Some(id) {
+                return Ok(incoming);
+            }
+        }
+    }
+}
+
+struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    headers: BTreeMap<String, String>,
+    session_id: Option<String>,
+    /// Negotiated revision, sent as `MCP-Protocol-Version` once `initialize` has answered
+    protocol_version: Option<String>,
+}
+
+impl HttpTransport {
+    async fn post(&mut self, message: &Value) -> Result<Option<reqwest::Response>> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Accept", "application/json, text/event-stream")
+            .json(message);
+        if let Some(version) = &self.protocol_version {
+            request = request.header(PROTOCOL_VERSION_HEADER, version);
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(session_id) = &self.session_id {
+            request = request.header(SESSION_HEADER, session_id);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|err| AgentError::Mcp(format!("HTTP request failed: {}", err)))?;
+
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            self.session_id = Some(session_id.to_string());
+        }
+
+        let status = response.status();
+        if status == reqwest::StatusCode::ACCEPTED {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(AgentError::Mcp(format!("HTTP {}: {}", status, body)));
+        }
+
+        Ok(Some(response))
+    }
+
+    async fn request(&mut self, id: u64, message: &Value) -> Result<Value> {
+        let response = self.post(message).await?.ok_or_else(|| {
+            AgentError::Mcp("server accepted a request without a response".into())
+        })?;
+
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        let body = response
+            .text()
+            .await
+            .map_err(|err| AgentError::Mcp(format!("failed to read HTTP response: {}", err)))?;
+
+        if !is_event_stream {
+            return serde_json::from_str(&body)
+                .map_err(|err| AgentError::Mcp(format!("invalid JSON response: {}", err)));
+        }
+
+        parse_event_stream(&body)
+            .into_iter()
+            .find(|event| event.get("id").and_then(|v| v.as_u64()) == Some(id))
+            .ok_or_else(|| AgentError::Mcp(format!("no response for request {} in stream", id)))
+    }
+}
+
+/// Collect the JSON payloads of every `data:` event in an SSE body
+fn parse_event_stream(body: &str) -> Vec<Value> {
+    let mut events = Vec::new();
+    let mut data = String::new();
+
+    for line in body.lines().chain(std::iter::once("")) {
+        if line.is_empty() {
+            if !data.is_empty() {
+                if let Ok(event) = serde_json::from_str(&data) {
+                    events.push(event);
+                }
+                data.clear();
+            }
+        } else if let Some(chunk) = line.strip_prefix("data:") {
+            if !data.is_empty() {
+                data.push('\n');
+            }
+            data.push_str(chunk.trim_start());
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_event_stream() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\nevent: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":2,\ndata: \"result\":{}}\n\n";
+        let events = parse_event_stream(body);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1]["id"], 2);
+    }
+}
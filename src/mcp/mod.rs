@@ -0,0 +1,11 @@
+//! Model Context Protocol support: import tools from MCP servers and serve our own tools over MCP
+
+pub mod client;
+pub mod protocol;
+pub mod server;
+pub mod tool;
+
+pub use client::{McpClient, McpServerConfig};
+pub use protocol::{McpToolInfo, PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS};
+pub use server::McpServer;
+pub use tool::McpTool;
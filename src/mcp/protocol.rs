@@ -0,0 +1,300 @@
+use crate::{
+    error::{AgentError, Result},
+    tools::result::{Artifact, ToolResponse},
+    types::content::{split_tool_attachments, with_attachments, ContentPart},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Latest MCP protocol revision, requested by the client and offered by the server
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// Revisions accepted during `initialize`, newest first: the server agrees to any of them and
+/// the client fails on anything else; the tool methods are the same in all of them
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &[PROTOCOL_VERSION, "2024-11-05"];
+
+pub(crate) const JSONRPC_VERSION: &str = "2.0";
+
+/// JSON-RPC error codes used by MCP
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+
+/// Tool metadata as returned by `tools/list`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpToolInfo {
+    pub name: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "inputSchema", default = "empty_object_schema")]
+    pub input_schema: Value,
+}
+
+pub(crate) fn empty_object_schema() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+/// Build a JSON-RPC request
+pub(crate) fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({
+        "jsonrpc": JSONRPC_VERSION,
+        "id": id,
+        "method": method,
+        "params": params
+    })
+}
+
+/// Build a JSON-RPC notification (no response expected)
+pub(crate) fn notification(method: &str, params: Value) -> Value {
+    json!({
+        "jsonrpc": JSONRPC_VERSION,
+        "method": method,
+        "params": params
+    })
+}
+
+/// Build a successful JSON-RPC response
+pub(crate) fn response(id: Value, result: Value) -> Value {
+    json!({
+        "jsonrpc": JSONRPC_VERSION,
+        "id": id,
+        "result": result
+    })
+}
+
+/// Build a JSON-RPC error response
+pub(crate) fn error_response(id: Value, code: i64, message: &str, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({
+        "jsonrpc": JSONRPC_VERSION,
+        "id": id,
+        "error": error
+    })
+}
+
+/// Extract the `result` of a JSON-RPC response, mapping `error` objects to `AgentError::Mcp`
+pub(crate) fn response_result(response: Value) -> Result<Value> {
+    if let Some(error) = response.get("error") {
+        let code = error.get("code").and_then(|c| c.as_i64()).unwrap_or(0);
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("unknown error");
+        return Err(AgentError::Mcp(format!("{} (code {})", message, code)));
+    }
+
+    response
+        .get("result")
+        .cloned()
+        .ok_or_else(|| AgentError::Mcp("response is missing `result`".to_string()))
+}
+
+/// Convert a `tools/call` result into the JSON value returned by `Tool::execute`.
+///
+/// `structuredContent` wins when present; otherwise text blocks are joined (and parsed as JSON
+/// when possible) and image blocks become attachments.
+pub(crate) fn call_result_to_value(tool_name: &str, result: &Value) -> Result<Value> {
+    let blocks = result
+        .get("content")
+        .and_then(|c| c.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut texts = Vec::new();
+    let mut attachments = Vec::new();
+    for block in &blocks {
+        match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => {
+                if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                    texts.push(text.to_string());
+                }
+            }
+            Some("image") => {
+                if let (Some(data), Some(mime_type)) = (
+                    block.get("data").and_then(|d| d.as_str()),
+                    block.get("mimeType").and_then(|m| m.as_str()),
+                ) {
+                    attachments.push(ContentPart::image_base64(mime_type, data));
+                }
+            }
+            _ => {}
+        }
+    }
+    let text = texts.join("\n");
+
+    if result
+        .get("isError")
+        .and_then(|e| e.as_bool())
+        .unwrap_or(false)
+    {
+        return Err(AgentError::ToolExecution(format!(
+            "MCP tool `{}` failed: {}",
+            tool_name, text
+        )));
+    }
+
+    let value = match result.get("structuredContent") {
+        Some(structured) if !structured.is_null() => structured.clone(),
+        _ => serde_json::from_str::<Value>(&text)
+            .ok()
+            .filter(|parsed| parsed.is_object() || parsed.is_array())
+            .unwrap_or(Value::String(text)),
+    };
+
+    Ok(with_attachments(value, attachments))
+}
+
+/// Convert a tool's output into a `tools/call` result.
+///
+/// Objects are also returned as `structuredContent`; attachments become image or resource blocks.
+/// A [`ToolResult`](crate::ToolResult) sends its content as text, its structured data as
+/// `structuredContent` and its artifacts as resource blocks.
+pub(crate) fn response_to_call_result(output: ToolResponse) -> Value {
+    let (text, structured, artifacts, attachments) = match output {
+        ToolResponse::Value(value) => {
+            let (value, attachments) = split_tool_attachments(value);
+            let text = match &value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            (text, Some(value), Vec::new(), attachments)
+        }
+        ToolResponse::Result(result) => (
+            result.content,
+            result.structured,
+            result.artifacts,
+            Vec::new(),
+        ),
+    };
+
+    let mut content = vec![json!({ "type": "text", "text": text })];
+    content.extend(attachments.iter().filter_map(attachment_block));
+    content.extend(artifacts.iter().map(artifact_block));
+
+    let mut result = json!({ "content": content, "isError": false });
+    if let Some(structured) = structured.filter(Value::is_object) {
+        result["structuredContent"] = structured;
+    }
+    result
+}
+
+/// Convert a tool failure into a `tools/call` result with `isError` set
+pub(crate) fn error_call_result(error: &AgentError) -> Value {
+    let payload = error.to_error_payload();
+    json!({
+        "content": [{ "type": "text", "text": payload.to_string() }],
+        "structuredContent": payload,
+        "isError": true
+    })
+}
+
+fn artifact_block(artifact: &Artifact) -> Value {
+    json!({
+        "type": "resource",
+        "resource": {
+            "uri": format!("artifact:///{}", artifact.name),
+            "mimeType": artifact.media_type,
+            "blob": artifact.data
+        }
+    })
+}
+
+fn attachment_block(part: &ContentPart) -> Option<Value> {
+    match part {
+        ContentPart::Image { media_type, data } => Some(json!({
+            "type": "image",
+            "data": data,
+            "mimeType": media_type
+        })),
+        ContentPart::File {
+            filename,
+            media_type,
+            data,
+        } => Some(json!({
+            "type": "resource",
+            "resource": {
+                "uri": format!("attachment:///{}", filename),
+                "mimeType": media_type,
+                "blob": data
+            }
+        })),
+        ContentPart::ImageUrl { url, .. } => Some(json!({ "type": "text", "text": url })),
+        ContentPart::Text { text } => Some(json!({ "type": "text", "text": text })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_result_to_value() {
+        let result = json!({
+            "content": [{"type": "text", "text": "{\"sum\": 3}"}],
+            "isError": false
+        });
+        assert_eq!(
+            call_result_to_value("add", &result).unwrap(),
+            json!({"sum": 3})
+        );
+
+        let result = json!({"content": [{"type": "text", "text": "plain"}]});
+        assert_eq!(
+            call_result_to_value("echo", &result).unwrap(),
+            json!("plain")
+        );
+
+        let result = json!({"content": [{"type": "text", "text": "boom"}], "isError": true});
+        let err = call_result_to_value("explode", &result).unwrap_err();
+        assert!(err.to_string().contains("boom"));
+    }
+
+    #[test]
+    fn test_response_result_maps_errors() {
+        let err = response_result(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": {"code": -32601, "message": "Method not found"}
+        }))
+        .unwrap_err();
+        assert_eq!(err.error_code(), "MCP_ERROR");
+        assert!(err.to_string().contains("-32601"));
+    }
+
+    #[test]
+    fn test_response_to_call_result_round_trips() {
+        let output = with_attachments(
+            json!({"status": "ok"}),
+            vec![ContentPart::image_base64("image/png", "AQID")],
+        );
+        let result = response_to_call_result(output.clone().into());
+        assert_eq!(result["structuredContent"], json!({"status": "ok"}));
+        assert_eq!(result["content"][1]["mimeType"], "image/png");
+        assert_eq!(call_result_to_value("snap", &result).unwrap(), output);
+
+        let failure = error_call_result(&AgentError::ToolExecution("disk full".to_string()));
+        assert_eq!(failure["isError"], true);
+        assert_eq!(
+            failure["structuredContent"]["error"]["code"],
+            "TOOL_EXECUTION_ERROR"
+        );
+    }
+
+    #[test]
+    fn test_tool_result_becomes_text_structured_content_and_resources() {
+        let output = crate::ToolResult::text("1 file")
+            .with_structured(json!({ "count": 1 }))
+            .with_artifact(Artifact::from_bytes("a.txt", "text/plain", b"hi"));
+
+        let result = response_to_call_result(output.into());
+        assert_eq!(result["content"][0]["text"], "1 file");
+        assert_eq!(result["structuredContent"], json!({ "count": 1 }));
+        assert_eq!(result["content"][1]["resource"]["uri"], "artifact:///a.txt");
+        assert_eq!(result["content"][1]["resource"]["blob"], "aGk=");
+    }
+}
@@ -0,0 +1,294 @@
+use super::protocol::{self, PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS};
+use crate::{
+    error::{AgentError, Result},
+    tools::{FunctionFactory, ToolContext},
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::debug;
+
+/// Serves every tool registered in a `FunctionFactory` to MCP clients
+#[derive(Debug, Clone)]
+pub struct McpServer {
+    factory: Arc<FunctionFactory>,
+    name: String,
+    version: String,
+}
+
+impl McpServer {
+    /// Create a server for the given tools
+    pub fn new(factory: FunctionFactory) -> Self {
+        Self::from_shared(Arc::new(factory))
+    }
+
+    /// Create a server for tools that are also used elsewhere
+    pub fn from_shared(factory: Arc<FunctionFactory>) -> Self {
+        Self {
+            factory,
+            name: "tiny-agent-rs".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Override the `serverInfo` reported to clients
+    pub fn with_server_info(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.name = name.into();
+        self.version = version.into();
+        self
+    }
+
+    /// Handle one JSON-RPC message, returning the response (`None` for notifications)
+    pub async fn handle_message(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(|m| m.as_str()) else {
+            return id.map(|id| {
+                protocol::error_response(id, protocol::INVALID_REQUEST, "Missing method", None)
+            });
+        };
+        // Notifications (including `notifications/initialized`) never get a reply
+        let id = id?;
+        let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+        debug!(target: "tinyagent::mcp", method, "handling MCP request");
+
+        let response = match method {
+            "initialize" => protocol::response(id, self.initialize_result(&params)),
+            "ping" => protocol::response(id, json!({})),
+            "tools/list" => protocol::response(id, json!({ "tools": self.list_tools() })),
+            "tools/call" => self.call_tool(id, &params).await,
+            other => protocol::error_response(
+                id,
+                protocol::METHOD_NOT_FOUND,
+                &format!("Method not found: {}", other),
+                None,
+            ),
+        };
+        Some(response)
+    }
+
+    /// Serve over the process's stdin/stdout until stdin closes
+    pub async fn serve_stdio(&self) -> Result<()> {
+        self.serve_streams(tokio::io::stdin(), tokio::io::stdout())
+            .await
+    }
+
+    /// Serve newline-delimited JSON-RPC over arbitrary streams until the reader closes
+    pub async fn serve_streams<R, W>(&self, reader: R, mut writer: W) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Some(line) = lines
+            .next_line()
+            .await
+            .map_err(|err| AgentError::Mcp(format!("failed to read request: {}", err)))?
+        {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle_message(message).await,
+                Err(err) => Some(protocol::error_response(
+                    Value::Null,
+                    protocol::PARSE_ERROR,
+                    &format!("Parse error: {}", err),
+                    None,
+                )),
+            };
+
+            if let Some(response) = response {
+                let mut out = serde_json::to_string(&response)?;
+                out.push('\n');
+                writer
+                    .write_all(out.as_bytes())
+                    .await
+                    .map_err(|err| AgentError::Mcp(format!("failed to write response: {}", err)))?;
+                writer
+                    .flush()
+                    .await
+                    .map_err(|err| AgentError::Mcp(format!("failed to flush response: {}", err)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Router serving MCP streamable HTTP at `/mcp`
+    #[cfg(feature = "server")]
+    pub fn http_router(self) -> axum::Router {
+        use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+
+        async fn handle(
+            axum::extract::State(server): axum::extract::State<McpServer>,
+            Json(message): Json<Value>,
+        ) -> axum::response::Response {
+            match server.handle_message(message).await {
+                Some(response) => Json(response).into_response(),
+                None => StatusCode::ACCEPTED.into_response(),
+            }
+        }
+
+        Router::new().route("/mcp", post(handle)).with_state(self)
+    }
+
+    /// Serve MCP streamable HTTP on `addr` until the process exits
+    #[cfg(feature = "server")]
+    pub async fn serve_http(self, addr: std::net::SocketAddr) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|err| AgentError::Mcp(format!("failed to bind {}: {}", addr, err)))?;
+        axum::serve(listener, self.http_router())
+            .await
+            .map_err(|err| AgentError::Mcp(format!("HTTP server error: {}", err)))
+    }
+
+    fn initialize_result(&self, params: &Value) -> Value {
+        // Echo the client's revision when we speak it, otherwise offer our latest
+        let version = params
+            .get("protocolVersion")
+            .and_then(|v| v.as_str())
+            .filter(|requested| SUPPORTED_PROTOCOL_VERSIONS.contains(requested))
+            .unwrap_or(PROTOCOL_VERSION);
+
+        json!({
+            "protocolVersion": version,
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": self.name, "version": self.version }
+        })
+    }
+
+    fn list_tools(&self) -> Vec<Value> {
+        self.factory
+            .get_openai_tools()
+            .into_iter()
+            .map(|tool| {
+                let function = &tool["function"];
+                json!({
+                    "name": function["name"],
+                    "description": function["description"],
+                    "inputSchema": function["parameters"]
+                })
+            })
+            .collect()
+    }
+
+    async fn call_tool(&self, id: Value, params: &Value) -> Value {
+        let Some(name) = params.get("name").and_then(|n| n.as_str()) else {
+            return protocol::error_response(
+                id,
+                protocol::INVALID_PARAMS,
+                "tools/call requires a `name`",
+                None,
+            );
+        };
+
+        if !self.factory.has_function(name) {
+            let error = AgentError::ToolNotFound(name.to_string());
+            return protocol::error_response(
+                id,
+                protocol::INVALID_PARAMS,
+                &error.to_string(),
+                Some(error.to_error_payload()),
+            );
+        }
+
+        let arguments = params
+            .get("arguments")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+        let result = match self
+            .factory
+            .execute_function_with_context(name, arguments, ToolContext::default())
+            .await
+        {
+            Ok(output) => protocol::response_to_call_result(output),
+            Err(error) => protocol::error_call_result(&error),
+        };
+        protocol::response(id, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::CalculatorTool;
+
+    fn server() -> McpServer {
+        let mut factory = FunctionFactory::new();
+        factory.register_tool(CalculatorTool::new()).unwrap();
+        McpServer::new(factory)
+    }
+
+    #[tokio::test]
+    async fn test_notifications_and_unknown_methods() {
+        let server = server();
+        assert!(server
+            .handle_message(json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .await
+            .is_none());
+
+        let response = server
+            .handle_message(json!({"jsonrpc": "2.0", "id": 7, "method": "resources/list"}))
+            .await
+            .unwrap();
+        assert_eq!(response["error"]["code"], protocol::METHOD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_initialize_negotiates_the_protocol_version() {
+        let server = server();
+        for (requested, negotiated) in [
+            ("2024-11-05", "2024-11-05"),
+            (PROTOCOL_VERSION, PROTOCOL_VERSION),
+            ("1999-01-01", PROTOCOL_VERSION),
+        ] {
+            let response = server
+                .handle_message(json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "initialize",
+                    "params": { "protocolVersion": requested }
+                }))
+                .await
+                .unwrap();
+            assert_eq!(response["result"]["protocolVersion"], negotiated);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_errors_use_agent_error_payload() {
+        let server = server();
+        let response = server
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {
+                    "name": "calculator",
+                    "arguments": {"operation": "divide", "a": 1.0, "b": 0.0}
+                }
+            }))
+            .await
+            .unwrap();
+        assert_eq!(response["result"]["isError"], true);
+        assert_eq!(
+            response["result"]["structuredContent"]["error"]["code"],
+            "TOOL_EXECUTION_ERROR"
+        );
+
+        let response = server
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "tools/call",
+                "params": {"name": "missing"}
+            }))
+            .await
+            .unwrap();
+        assert_eq!(response["error"]["code"], protocol::INVALID_PARAMS);
+        assert_eq!(response["error"]["data"]["error"]["code"], "TOOL_NOT_FOUND");
+    }
+}
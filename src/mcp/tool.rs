@@ -0,0 +1,65 @@
+use super::{
+    client::McpClient,
+    protocol::{call_result_to_value, McpToolInfo},
+};
+use crate::tools::Tool;
+use serde_json::Value;
+use std::{fmt, pin::Pin, sync::Arc};
+
+/// A tool exposed by an MCP server, registered like any local tool
+pub struct McpTool {
+    client: Arc<McpClient>,
+    name: String,
+    description: String,
+    input_schema: Value,
+}
+
+impl McpTool {
+    /// Wrap a tool listed by `client`
+    pub fn new(client: Arc<McpClient>, info: McpToolInfo) -> Self {
+        let description = info
+            .description
+            .unwrap_or_else(|| format!("MCP tool `{}`", info.name));
+
+        Self {
+            client,
+            name: info.name,
+            description,
+            input_schema: info.input_schema,
+        }
+    }
+}
+
+impl fmt::Debug for McpTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("McpTool")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters_schema(&self) -> Value {
+        self.input_schema.clone()
+    }
+
+    fn execute(
+        &self,
+        parameters: Value,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<Value, crate::AgentError>> + Send + '_>>
+    {
+        Box::pin(async move {
+            let result = self.client.call_tool(&self.name, parameters).await?;
+            call_result_to_value(&self.name, &result)
+        })
+    }
+}
@@ -1,5 +1,11 @@
 mod schema;
+mod strategy;
+pub mod strict;
 pub(crate) mod validation;
 pub mod validator;
 
-pub use schema::{apply_doc_comments, schema_type_name, CompletionSchema, SchemaHandle};
+pub use schema::{
+    apply_doc_comments, apply_variant_doc_comments, cached_schema_handle, schema_type_name,
+    CompletionSchema, SchemaHandle,
+};
+pub use strategy::StructuredOutputStrategy;
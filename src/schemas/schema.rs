@@ -1,39 +1,112 @@
+use super::strict::convert_strict;
+use crate::{AgentError, Result};
+use jsonschema::{Draft, JSONSchema};
 use schemars::schema::{ObjectValidation, RootSchema, Schema, SchemaObject};
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 use std::{
     any::{type_name, TypeId},
-    sync::Arc,
+    borrow::Cow,
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex, OnceLock},
 };
 
 /// Cached JSON schema handle associated with a response type.
+///
+/// Handles either come from a Rust type via [`CompletionSchema`], or are built at runtime from
+/// a raw JSON Schema document with [`SchemaHandle::from_json_schema`]. Runtime handles have no
+/// Rust type; their payloads are validated the same way and stay `serde_json::Value`.
 #[derive(Clone, Debug)]
 pub struct SchemaHandle {
-    schema_name: &'static str,
+    schema_name: Cow<'static, str>,
     type_name: &'static str,
     type_id: TypeId,
     schema_json: Arc<Value>,
+    /// Strict-mode form of the schema, converted on first use and shared between clones
+    strict_json: Arc<OnceLock<std::result::Result<Arc<Value>, String>>>,
 }
 
 impl SchemaHandle {
     pub fn from_root_schema<T: 'static>(
-        schema_name: &'static str,
+        schema_name: impl Into<Cow<'static, str>>,
         type_name: &'static str,
-        root: RootSchema,
+        mut root: RootSchema,
     ) -> Self {
+        inline_root_reference(&mut root);
         let schema_json = serde_json::to_value(root)
             .unwrap_or_else(|err| panic!("failed to serialize schema for {}: {}", type_name, err));
 
         Self {
-            schema_name,
+            schema_name: schema_name.into(),
             type_name,
             type_id: TypeId::of::<T>(),
             schema_json: Arc::new(schema_json),
+            strict_json: Arc::default(),
         }
     }
 
-    pub fn schema_name(&self) -> &'static str {
-        self.schema_name
+    /// Build a handle from a JSON Schema document supplied at runtime.
+    ///
+    /// The schema must describe an object and compile as Draft 7. `name` is used in prompts and
+    /// `response_format`; characters other than ASCII letters, digits, `_` and `-` become `_`.
+    pub fn from_json_schema(name: &str, schema: Value) -> Result<Self> {
+        let name = sanitize_schema_name(name);
+
+        if schema.get("type").and_then(Value::as_str) != Some("object") {
+            return Err(AgentError::Config(format!(
+                "Schema `{}` must describe an object (\"type\": \"object\")",
+                name
+            )));
+        }
+
+        if let Err(err) = JSONSchema::options()
+            .with_draft(Draft::Draft7)
+            .compile(&schema)
+        {
+            return Err(AgentError::Config(format!(
+                "Schema `{}` is not a valid JSON Schema: {}",
+                name, err
+            )));
+        }
+
+        Ok(Self {
+            schema_name: Cow::Owned(name),
+            type_name: type_name::<Value>(),
+            type_id: TypeId::of::<Value>(),
+            schema_json: Arc::new(schema),
+            strict_json: Arc::default(),
+        })
+    }
+
+    /// Load a JSON Schema file; the schema's `title` names it, falling back to the file name
+    pub fn from_json_schema_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|err| {
+            AgentError::Config(format!("Failed to read {}: {}", path.display(), err))
+        })?;
+        let schema: Value = serde_json::from_str(&contents).map_err(|err| {
+            AgentError::Config(format!("Invalid JSON in {}: {}", path.display(), err))
+        })?;
+
+        let name = match schema.get("title").and_then(Value::as_str) {
+            Some(title) => title.to_string(),
+            None => path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .to_string(),
+        };
+        Self::from_json_schema(&name, schema)
+    }
+
+    /// Whether this handle was built from a JSON Schema at runtime rather than a Rust type
+    pub fn is_runtime(&self) -> bool {
+        self.type_id == TypeId::of::<Value>()
+    }
+
+    pub fn schema_name(&self) -> &str {
+        &self.schema_name
     }
 
     pub fn type_name(&self) -> &'static str {
@@ -51,16 +124,60 @@ impl SchemaHandle {
     pub fn schema_json_arc(&self) -> Arc<Value> {
         Arc::clone(&self.schema_json)
     }
+
+    /// The schema rewritten for OpenAI strict structured outputs (see [`super::strict`]).
+    ///
+    /// Fails with a `Config` error naming every unsupported construct; call it at startup to
+    /// surface those before the first request.
+    pub fn strict_schema_json(&self) -> Result<Arc<Value>> {
+        self.strict_json
+            .get_or_init(|| {
+                convert_strict(&self.schema_json)
+                    .map(Arc::new)
+                    .map_err(|issues| {
+                        format!(
+                            "Schema `{}` is not compatible with strict structured outputs: {}",
+                            self.schema_name,
+                            issues.join("; ")
+                        )
+                    })
+            })
+            .clone()
+            .map_err(AgentError::Config)
+    }
 }
 
 pub trait CompletionSchema: DeserializeOwned + Send + Sync + 'static {
     fn schema() -> &'static SchemaHandle;
 }
 
+/// Schema handle for a generic completion schema type, built once per concrete type.
+///
+/// `#[completion_schema]` uses this for generic types, where a `static` in the impl would be
+/// shared by every instantiation.
+pub fn cached_schema_handle<T: 'static>(
+    init: impl FnOnce() -> SchemaHandle,
+) -> &'static SchemaHandle {
+    static HANDLES: OnceLock<Mutex<HashMap<TypeId, &'static SchemaHandle>>> = OnceLock::new();
+    let handles = HANDLES.get_or_init(Default::default);
+
+    if let Some(handle) = handles.lock().unwrap().get(&TypeId::of::<T>()) {
+        return handle;
+    }
+
+    // Built outside the lock; one handle per type is leaked, and a racing duplicate is dropped
+    let handle = init();
+    let mut handles = handles.lock().unwrap();
+    let cached: &'static SchemaHandle = handles
+        .entry(TypeId::of::<T>())
+        .or_insert_with(|| Box::leak(Box::new(handle)));
+    cached
+}
+
 /// Apply doc comments captured by the procedural macro to the generated schema metadata.
 pub fn apply_doc_comments(
     root: &mut RootSchema,
-    title: &'static str,
+    title: &str,
     description: Option<&'static str>,
     field_docs: &[(&'static str, &'static str)],
 ) {
@@ -74,7 +191,7 @@ pub fn apply_doc_comments(
 
 fn apply_struct_metadata(
     schema_object: &mut SchemaObject,
-    title: &'static str,
+    title: &str,
     description: Option<&'static str>,
 ) {
     let metadata = schema_object.metadata();
@@ -104,7 +221,163 @@ fn apply_field_metadata(
     }
 }
 
+/// Replace a root that only points at a definition (as schemars emits for newtype structs)
+/// with the definition itself, so strategies that read top-level `properties` see them.
+fn inline_root_reference(root: &mut RootSchema) {
+    let reference = match (&root.schema.reference, root.schema.subschemas.as_deref()) {
+        (Some(reference), _) => reference.clone(),
+        (None, Some(subschemas)) => match subschemas.all_of.as_deref() {
+            Some(
+                [Schema::Object(SchemaObject {
+                    reference: Some(reference),
+                    ..
+                })],
+            ) => reference.clone(),
+            _ => return,
+        },
+        _ => return,
+    };
+
+    let Some(Schema::Object(definition)) = reference
+        .strip_prefix("#/definitions/")
+        .and_then(|name| root.definitions.get(name))
+        .cloned()
+    else {
+        return;
+    };
+
+    let metadata = root.schema.metadata.take();
+    root.schema = definition;
+    if let Some(metadata) = metadata {
+        let target = root.schema.metadata();
+        if metadata.title.is_some() {
+            target.title = metadata.title;
+        }
+        if metadata.description.is_some() {
+            target.description = metadata.description;
+        }
+    }
+}
+
+/// Apply enum variant doc comments to the matching `oneOf`/`anyOf` entries.
+///
+/// Variants are matched by name: the single property of an externally tagged variant, or the
+/// tag value of a unit, internally or adjacently tagged one.
+pub fn apply_variant_doc_comments(root: &mut RootSchema, variant_docs: &[(&str, &str)]) {
+    if variant_docs.is_empty() {
+        return;
+    }
+
+    let Some(subschemas) = root.schema.subschemas.as_mut() else {
+        return;
+    };
+
+    let variants = subschemas
+        .one_of
+        .iter_mut()
+        .chain(subschemas.any_of.iter_mut())
+        .flatten();
+
+    for variant in variants {
+        let Schema::Object(variant_object) = variant else {
+            continue;
+        };
+        let Some(name) = variant_name(variant_object) else {
+            continue;
+        };
+
+        if let Some((_, doc)) = variant_docs.iter().find(|(variant, _)| *variant == name) {
+            let metadata = variant_object.metadata();
+            if metadata.description.is_none() {
+                metadata.description = Some((*doc).to_string());
+            }
+        }
+    }
+}
+
+fn variant_name(schema: &SchemaObject) -> Option<String> {
+    if let Some(name) = single_string_value(schema) {
+        return Some(name);
+    }
+
+    let object = schema.object.as_ref()?;
+    if object.properties.len() == 1 && object.required.len() == 1 {
+        return object.required.iter().next().cloned();
+    }
+
+    object
+        .properties
+        .values()
+        .find_map(|property| match property {
+            Schema::Object(property) => single_string_value(property),
+            Schema::Bool(_) => None,
+        })
+}
+
+fn single_string_value(schema: &SchemaObject) -> Option<String> {
+    match schema.enum_values.as_deref() {
+        Some([Value::String(value)]) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn sanitize_schema_name(name: &str) -> String {
+    let sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if sanitized.is_empty() {
+        "structured_output".to_string()
+    } else {
+        sanitized
+    }
+}
+
 /// Helper so callers can retrieve the Rust type name of a schema provider.
 pub fn schema_type_name<T>() -> &'static str {
     type_name::<T>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_runtime_schema_handle() {
+        let handle = SchemaHandle::from_json_schema(
+            "Support Ticket",
+            json!({
+                "type": "object",
+                "properties": { "priority": { "type": "string", "enum": ["low", "high"] } },
+                "required": ["priority"]
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(handle.schema_name(), "Support_Ticket");
+        assert!(handle.is_runtime());
+        assert_eq!(handle.schema_json()["required"][0], "priority");
+    }
+
+    #[test]
+    fn test_runtime_schema_handle_rejects_invalid_documents() {
+        let err = SchemaHandle::from_json_schema("list", json!({ "type": "array" })).unwrap_err();
+        assert!(err.to_string().contains("must describe an object"));
+
+        let err = SchemaHandle::from_json_schema(
+            "broken",
+            json!({ "type": "object", "properties": { "a": { "type": 7 } } }),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not a valid JSON Schema"));
+    }
+}
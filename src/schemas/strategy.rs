@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// How the agent asks the model to produce the active completion schema.
+///
+/// Every strategy validates the final payload against the schema before the run completes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StructuredOutputStrategy {
+    /// Finish by calling the `structured_response` tool with the payload (works with any tool-calling model).
+    #[default]
+    Tool,
+    /// Send the schema as a strict `json_schema` `response_format` and read the payload from the reply content.
+    Native,
+    /// Describe the schema in the system prompt and parse the reply content as JSON.
+    Prompt,
+}
@@ -0,0 +1,577 @@
+//! Conversion of completion schemas into the subset accepted by OpenAI strict structured outputs.
+//!
+//! Strict mode wants every object closed (`additionalProperties: false`) with all of its
+//! properties listed in `required`, and no `$ref`/`definitions` indirection from schemars. The
+//! conversion:
+//!
+//! - inlines `$ref`s into `definitions`/`$defs` and drops the definition tables
+//! - closes every object and marks every property required, turning optional properties into
+//!   nullable unions so they can still be left empty
+//! - unwraps single-entry `allOf` wrappers and rewrites `oneOf` as `anyOf`
+//! - strips validation-only keywords (`format`, lengths, ranges, ...) that strict mode rejects;
+//!   the payload is still validated against the original schema when it comes back, after
+//!   [`strip_optional_nulls`] removes the `null`s sent for optional properties
+//!
+//! Constructs that cannot be expressed (free-form maps, tuples, recursive types, ...) are
+//! collected and reported together, with the JSON path of each.
+
+use crate::{AgentError, Result};
+use serde_json::{json, Map, Value};
+
+/// Keywords that only constrain values; strict mode rejects them, local validation keeps them
+const STRIPPED_KEYWORDS: &[&str] = &[
+    "$schema",
+    "$id",
+    "format",
+    "default",
+    "examples",
+    "readOnly",
+    "writeOnly",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+    "minItems",
+    "maxItems",
+    "uniqueItems",
+    "minProperties",
+    "maxProperties",
+];
+
+/// Keywords with no strict-mode equivalent
+const UNSUPPORTED_KEYWORDS: &[&str] = &[
+    "not",
+    "if",
+    "then",
+    "else",
+    "dependencies",
+    "dependentRequired",
+    "dependentSchemas",
+    "patternProperties",
+    "propertyNames",
+    "unevaluatedProperties",
+    "unevaluatedItems",
+    "contains",
+    "additionalItems",
+    "prefixItems",
+];
+
+/// Rewrite `schema` into a form accepted by OpenAI strict structured outputs.
+///
+/// Returns a `Config` error listing every unsupported construct.
+pub fn to_strict_schema(schema: &Value) -> Result<Value> {
+    convert_strict(schema).map_err(|issues| {
+        AgentError::Config(format!(
+            "Schema is not compatible with strict structured outputs: {}",
+            issues.join("; ")
+        ))
+    })
+}
+
+/// Strict form of `schema`, or every problem found, each prefixed with its JSON path
+pub(crate) fn convert_strict(schema: &Value) -> std::result::Result<Value, Vec<String>> {
+    let mut converter = StrictConverter {
+        definitions: collect_definitions(schema),
+        expanding: Vec::new(),
+        issues: Vec::new(),
+    };
+
+    let strict = converter.convert(schema, "#");
+    if strict.get("type").and_then(Value::as_str) != Some("object") {
+        converter
+            .issues
+            .push("#: the root of a strict schema must be an object".to_string());
+    }
+
+    if converter.issues.is_empty() {
+        Ok(strict)
+    } else {
+        Err(converter.issues)
+    }
+}
+
+/// Remove the `null`s a strict-mode reply carries for properties that are optional in `schema`.
+///
+/// Strict mode makes every property required, so the model sends `null` where the original
+/// schema (and the Rust type) expects the property to be left out.
+pub(crate) fn strip_optional_nulls(schema: &Value, payload: &mut Value) {
+    let definitions = collect_definitions(schema);
+    strip_nulls(schema, payload, &definitions);
+}
+
+/// Follow `$ref`s and single-entry `allOf` wrappers to the schema that describes a value
+fn resolve_schema<'a>(mut schema: &'a Value, definitions: &'a Map<String, Value>) -> &'a Value {
+    // Strict schemas cannot be recursive, but stop on reference cycles anyway
+    for _ in 0..32 {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match definitions.get(reference) {
+                Some(definition) => schema = definition,
+                None => break,
+            }
+        } else if let Some(Value::Array(parts)) = schema.get("allOf") {
+            match parts.as_slice() {
+                [inner] => schema = inner,
+                _ => break,
+            }
+        } else {
+            break;
+        }
+    }
+    schema
+}
+
+fn strip_nulls(schema: &Value, payload: &mut Value, definitions: &Map<String, Value>) {
+    let schema = resolve_schema(schema, definitions);
+
+    let variants = ["anyOf", "oneOf"]
+        .iter()
+        .find_map(|keyword| schema.get(*keyword).and_then(Value::as_array));
+    if let Some(variants) = variants {
+        let variant = variants
+            .iter()
+            .map(|variant| resolve_schema(variant, definitions))
+            .find(|variant| describes_shape(variant, payload));
+        if let Some(variant) = variant {
+            strip_nulls(variant, payload, definitions);
+        }
+        return;
+    }
+
+    match payload {
+        Value::Object(object) => {
+            let Some(Value::Object(properties)) = schema.get("properties") else {
+                return;
+            };
+            let required: Vec<&str> = schema
+                .get("required")
+                .and_then(Value::as_array)
+                .map(|names| names.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+
+            object.retain(|name, value| {
+                !(value.is_null()
+                    && properties.contains_key(name)
+                    && !required.contains(&name.as_str()))
+            });
+            for (name, value) in object.iter_mut() {
+                if let Some(property) = properties.get(name) {
+                    strip_nulls(property, value, definitions);
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema @ Value::Object(_)) = schema.get("items") {
+                for item in items {
+                    strip_nulls(item_schema, item, definitions);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether `variant` describes a value shaped like `payload`: an object whose properties cover
+/// the payload's keys, or an array
+fn describes_shape(variant: &Value, payload: &Value) -> bool {
+    match payload {
+        Value::Object(object) => match variant.get("properties") {
+            Some(Value::Object(properties)) => {
+                object.keys().all(|key| properties.contains_key(key))
+            }
+            _ => false,
+        },
+        Value::Array(_) => variant.get("items").is_some(),
+        _ => false,
+    }
+}
+
+fn collect_definitions(schema: &Value) -> Map<String, Value> {
+    let mut definitions = Map::new();
+    for table in ["definitions", "$defs"] {
+        if let Some(Value::Object(entries)) = schema.get(table) {
+            for (name, definition) in entries {
+                definitions.insert(format!("#/{}/{}", table, name), definition.clone());
+            }
+        }
+    }
+    definitions
+}
+
+struct StrictConverter {
+    definitions: Map<String, Value>,
+    /// References currently being inlined, to detect recursive types
+    expanding: Vec<String>,
+    issues: Vec<String>,
+}
+
+impl StrictConverter {
+    fn convert(&mut self, schema: &Value, path: &str) -> Value {
+        let object = match schema {
+            Value::Object(object) => object,
+            Value::Bool(_) => {
+                self.issues.push(format!(
+                    "{}: boolean schemas (any value) are not supported",
+                    path
+                ));
+                return schema.clone();
+            }
+            _ => {
+                self.issues
+                    .push(format!("{}: expected a schema object", path));
+                return schema.clone();
+            }
+        };
+
+        if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+            return self.inline_reference(reference, object, path);
+        }
+
+        // schemars wraps a documented `$ref` field as `allOf: [{ "$ref": ... }]`
+        if let Some(Value::Array(parts)) = object.get("allOf") {
+            if parts.len() == 1 {
+                let mut merged = match &parts[0] {
+                    Value::Object(inner) => inner.clone(),
+                    _ => Map::new(),
+                };
+                for (key, value) in object {
+                    if key != "allOf" {
+                        merged.insert(key.clone(), value.clone());
+                    }
+                }
+                return self.convert(&Value::Object(merged), path);
+            }
+            self.issues.push(format!(
+                "{}: allOf with several schemas is not supported",
+                path
+            ));
+        }
+
+        let mut strict = Map::new();
+        for (key, value) in object {
+            let key = key.as_str();
+            if STRIPPED_KEYWORDS.contains(&key) || key == "definitions" || key == "$defs" {
+                continue;
+            }
+            if UNSUPPORTED_KEYWORDS.contains(&key) {
+                self.issues
+                    .push(format!("{}: `{}` is not supported", path, key));
+                continue;
+            }
+
+            let converted = match key {
+                "anyOf" | "oneOf" => self.convert_variants(value, &format!("{}/{}", path, key)),
+                "items" => match value {
+                    Value::Array(_) => {
+                        self.issues.push(format!(
+                            "{}/items: tuples (positional items) are not supported",
+                            path
+                        ));
+                        value.clone()
+                    }
+                    item => self.convert(item, &format!("{}/items", path)),
+                },
+                "properties" | "additionalProperties" | "required" => continue,
+                _ => value.clone(),
+            };
+
+            // Strict mode only understands anyOf; for payloads that match exactly one variant
+            // the two are equivalent
+            let key = if key == "oneOf" { "anyOf" } else { key };
+            strict.insert(key.to_string(), converted);
+        }
+
+        if is_object_schema(object) {
+            self.close_object(object, &mut strict, path);
+        }
+
+        Value::Object(strict)
+    }
+
+    fn convert_variants(&mut self, variants: &Value, path: &str) -> Value {
+        match variants {
+            Value::Array(variants) => Value::Array(
+                variants
+                    .iter()
+                    .enumerate()
+                    .map(|(index, variant)| self.convert(variant, &format!("{}/{}", path, index)))
+                    .collect(),
+            ),
+            other => {
+                self.issues
+                    .push(format!("{}: expected an array of schemas", path));
+                other.clone()
+            }
+        }
+    }
+
+    fn inline_reference(
+        &mut self,
+        reference: &str,
+        object: &Map<String, Value>,
+        path: &str,
+    ) -> Value {
+        if self.expanding.iter().any(|active| active == reference) {
+            self.issues.push(format!(
+                "{}: recursive reference {} cannot be inlined",
+                path, reference
+            ));
+            return Value::Object(object.clone());
+        }
+
+        let Some(definition) = self.definitions.get(reference).cloned() else {
+            self.issues
+                .push(format!("{}: unresolved reference {}", path, reference));
+            return Value::Object(object.clone());
+        };
+
+        self.expanding.push(reference.to_string());
+        let mut inlined = self.convert(&definition, path);
+        self.expanding.pop();
+
+        // Keywords next to the `$ref` (usually a field's description) win over the definition's
+        if let Value::Object(inlined_object) = &mut inlined {
+            for (key, value) in object {
+                if key != "$ref" && !STRIPPED_KEYWORDS.contains(&key.as_str()) {
+                    inlined_object.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        inlined
+    }
+
+    fn close_object(
+        &mut self,
+        object: &Map<String, Value>,
+        strict: &mut Map<String, Value>,
+        path: &str,
+    ) {
+        match object.get("additionalProperties") {
+            None | Some(Value::Bool(false)) => {}
+            Some(_) => self.issues.push(format!(
+                "{}: objects with arbitrary keys (maps, additionalProperties) are not supported",
+                path
+            )),
+        }
+
+        let properties = match object.get("properties") {
+            Some(Value::Object(properties)) => properties,
+            _ => {
+                self.issues
+                    .push(format!("{}: objects must declare their properties", path));
+                return;
+            }
+        };
+
+        let required: Vec<&str> = object
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|names| names.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let mut strict_properties = Map::new();
+        for (name, property) in properties {
+            let mut converted = self.convert(property, &format!("{}/properties/{}", path, name));
+            if !required.contains(&name.as_str()) {
+                make_nullable(&mut converted);
+            }
+            strict_properties.insert(name.clone(), converted);
+        }
+
+        strict.insert(
+            "required".to_string(),
+            Value::Array(properties.keys().map(|name| json!(name)).collect()),
+        );
+        strict.insert("properties".to_string(), Value::Object(strict_properties));
+        strict.insert("additionalProperties".to_string(), json!(false));
+    }
+}
+
+fn is_object_schema(object: &Map<String, Value>) -> bool {
+    match object.get("type") {
+        Some(Value::String(kind)) => kind == "object",
+        Some(Value::Array(kinds)) => kinds.iter().any(|kind| kind == "object"),
+        _ => object.contains_key("properties"),
+    }
+}
+
+/// Allow `null` in addition to whatever `schema` accepts
+fn make_nullable(schema: &mut Value) {
+    let Value::Object(object) = schema else {
+        return;
+    };
+    let null = json!("null");
+
+    if let Some(Value::Array(variants)) = object.get_mut("anyOf") {
+        if !variants
+            .iter()
+            .any(|variant| variant.get("type") == Some(&null))
+        {
+            variants.push(json!({ "type": "null" }));
+        }
+        return;
+    }
+
+    match object.get_mut("type") {
+        Some(Value::String(kind)) if kind == "null" => return,
+        Some(Value::String(kind)) => {
+            let kind = Value::String(std::mem::take(kind));
+            object.insert("type".to_string(), json!([kind, "null"]));
+        }
+        Some(Value::Array(kinds)) => {
+            if !kinds.contains(&null) {
+                kinds.push(null.clone());
+            }
+        }
+        _ => {
+            let inner = std::mem::take(object);
+            object.insert(
+                "anyOf".to_string(),
+                json!([Value::Object(inner), { "type": "null" }]),
+            );
+            return;
+        }
+    }
+
+    if let Some(Value::Array(values)) = object.get_mut("enum") {
+        if !values.contains(&Value::Null) {
+            values.push(Value::Null);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schemars_output_is_made_strict() {
+        let schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Report",
+            "type": "object",
+            "required": ["author", "score"],
+            "properties": {
+                "author": {
+                    "description": "Who wrote it",
+                    "allOf": [{ "$ref": "#/definitions/Person" }]
+                },
+                "score": { "type": "integer", "format": "uint8", "minimum": 0.0 },
+                "reviewer": {
+                    "anyOf": [{ "$ref": "#/definitions/Person" }, { "type": "null" }]
+                },
+                "tags": { "type": ["array", "null"], "items": { "type": "string" } },
+                "status": { "type": "string", "enum": ["draft", "final"] }
+            },
+            "definitions": {
+                "Person": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "email": { "type": "string", "format": "email" }
+                    }
+                }
+            }
+        });
+
+        let strict = to_strict_schema(&schema).unwrap();
+        assert!(strict.get("$schema").is_none());
+        assert!(strict.get("definitions").is_none());
+        assert_eq!(strict["additionalProperties"], false);
+        assert_eq!(
+            strict["required"],
+            json!(["author", "reviewer", "score", "status", "tags"])
+        );
+
+        let author = &strict["properties"]["author"];
+        assert_eq!(author["description"], "Who wrote it");
+        assert_eq!(author["additionalProperties"], false);
+        assert_eq!(author["required"], json!(["email", "name"]));
+        assert_eq!(
+            author["properties"]["email"],
+            json!({ "type": ["string", "null"] })
+        );
+
+        assert_eq!(strict["properties"]["score"], json!({ "type": "integer" }));
+        assert_eq!(
+            strict["properties"]["reviewer"]["anyOf"][0]["required"],
+            json!(["email", "name"])
+        );
+        assert_eq!(
+            strict["properties"]["status"],
+            json!({ "type": ["string", "null"], "enum": ["draft", "final", null] })
+        );
+    }
+
+    #[test]
+    fn test_unsupported_constructs_are_all_reported() {
+        let schema = json!({
+            "type": "object",
+            "required": ["labels", "next", "pair"],
+            "properties": {
+                "labels": { "type": "object", "additionalProperties": { "type": "string" } },
+                "next": { "$ref": "#/definitions/Node" },
+                "pair": { "type": "array", "items": [{ "type": "string" }, { "type": "integer" }] }
+            },
+            "definitions": {
+                "Node": {
+                    "type": "object",
+                    "required": ["next"],
+                    "properties": { "next": { "$ref": "#/definitions/Node" } }
+                }
+            }
+        });
+
+        let message = to_strict_schema(&schema).unwrap_err().to_string();
+        assert!(message.contains("#/properties/labels: objects with arbitrary keys"));
+        assert!(message.contains("recursive reference #/definitions/Node"));
+        assert!(message.contains("#/properties/pair/items: tuples"));
+    }
+
+    #[test]
+    fn test_strip_optional_nulls_keeps_required_nulls() {
+        let schema = json!({
+            "type": "object",
+            "required": ["title", "parent"],
+            "properties": {
+                "title": { "type": "string" },
+                "notes": { "type": "string" },
+                "parent": { "type": ["string", "null"] },
+                "author": { "allOf": [{ "$ref": "#/definitions/Person" }] },
+                "reviewers": { "type": "array", "items": { "$ref": "#/definitions/Person" } }
+            },
+            "definitions": {
+                "Person": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "email": { "type": "string" }
+                    }
+                }
+            }
+        });
+
+        let mut payload = json!({
+            "title": "Plan",
+            "notes": null,
+            "parent": null,
+            "author": { "name": "Ada", "email": null },
+            "reviewers": [{ "name": "Bo", "email": null }]
+        });
+        strip_optional_nulls(&schema, &mut payload);
+        assert_eq!(
+            payload,
+            json!({
+                "title": "Plan",
+                "parent": null,
+                "author": { "name": "Ada" },
+                "reviewers": [{ "name": "Bo" }]
+            })
+        );
+    }
+}
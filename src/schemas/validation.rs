@@ -1,4 +1,7 @@
-use crate::{error::AgentError, schemas::SchemaHandle};
+use crate::{
+    error::AgentError,
+    schemas::{strict::strip_optional_nulls, SchemaHandle, StructuredOutputStrategy},
+};
 use jsonschema::{Draft, JSONSchema};
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -110,11 +113,11 @@ pub(crate) fn final_answer_tool_definition() -> Value {
 
 /// Generate the structured_response tool definition with the actual schema
 pub(crate) fn structured_response_tool_definition(schema: &SchemaHandle) -> Value {
+    let schema_json = schema.schema_json();
     let mut properties = serde_json::Map::new();
 
     // Instead of a generic object, use the actual schema
     let mut structured_param = serde_json::Map::new();
-    structured_param.insert("type".to_string(), json!("object"));
     structured_param.insert(
         "description".to_string(),
         json!(format!(
@@ -123,17 +126,40 @@ pub(crate) fn structured_response_tool_definition(schema: &SchemaHandle) -> Valu
         )),
     );
 
-    // Inject the actual schema properties
-    if let Some(schema_props) = schema.schema_json().get("properties") {
+    if let Some(schema_props) = schema_json.get("properties") {
+        // Inject the actual schema properties
+        structured_param.insert("type".to_string(), json!("object"));
         structured_param.insert("properties".to_string(), schema_props.clone());
+        if let Some(schema_required) = schema_json.get("required") {
+            structured_param.insert("required".to_string(), schema_required.clone());
+        }
+        structured_param.insert("additionalProperties".to_string(), json!(false));
+    } else {
+        // Enums, unions and tuple structs have no top-level properties; keep their variants
+        // and positional items as-is
+        for keyword in [
+            "type", "oneOf", "anyOf", "enum", "items", "minItems", "maxItems",
+        ] {
+            if let Some(value) = schema_json.get(keyword) {
+                structured_param.insert(keyword.to_string(), value.clone());
+            }
+        }
     }
-    if let Some(schema_required) = schema.schema_json().get("required") {
-        structured_param.insert("required".to_string(), schema_required.clone());
-    }
-    structured_param.insert("additionalProperties".to_string(), json!(false));
 
     properties.insert("structured".to_string(), json!(structured_param));
 
+    let mut parameters = json!({
+        "type": "object",
+        "properties": properties,
+        "required": ["structured"],
+        "additionalProperties": false
+    });
+
+    // `$ref`s point at `#/definitions/...`, so the definitions move to the parameters root
+    if let Some(definitions) = schema_json.get("definitions") {
+        parameters["definitions"] = definitions.clone();
+    }
+
     json!({
         "type": "function",
         "function": {
@@ -142,18 +168,17 @@ pub(crate) fn structured_response_tool_definition(schema: &SchemaHandle) -> Valu
                 "Complete the task by providing a {} object with all required fields.",
                 schema.schema_name()
             ),
-            "parameters": {
-                "type": "object",
-                "properties": properties,
-                "required": ["structured"],
-                "additionalProperties": false
-            }
+            "parameters": parameters
         }
     })
 }
 
 /// Inject schema instructions into the first system message
-pub(crate) fn inject_schema_instructions(messages: &mut [Value], schema: &SchemaHandle) {
+pub(crate) fn inject_schema_instructions(
+    messages: &mut [Value],
+    schema: &SchemaHandle,
+    strategy: StructuredOutputStrategy,
+) {
     let Some(first_message) = messages.first_mut() else {
         return;
     };
@@ -170,11 +195,8 @@ pub(crate) fn inject_schema_instructions(messages: &mut [Value], schema: &Schema
                 }
 
                 let mut updated = content_str.to_string();
-                updated.push_str(&format!(
-                    "\n\nStructured response requirement: when you finish the task, you MUST call the `{}` tool with a JSON payload that strictly conforms to the `{}` schema. This is the ONLY way to complete the task.",
-                    STRUCTURED_RESPONSE_TOOL_NAME,
-                    schema.schema_name()
-                ));
+                updated.push_str("\n\n");
+                updated.push_str(&schema_instructions(schema, strategy));
 
                 *content_value = Value::String(updated);
             }
@@ -182,6 +204,138 @@ pub(crate) fn inject_schema_instructions(messages: &mut [Value], schema: &Schema
     }
 }
 
+/// Build the structured response instructions for the given strategy
+pub(crate) fn schema_instructions(
+    schema: &SchemaHandle,
+    strategy: StructuredOutputStrategy,
+) -> String {
+    match strategy {
+        StructuredOutputStrategy::Tool => format!(
+            "Structured response requirement: when you finish the task, you MUST call the `{}` tool with a JSON payload that strictly conforms to the `{}` schema. This is the ONLY way to complete the task.",
+            STRUCTURED_RESPONSE_TOOL_NAME,
+            schema.schema_name()
+        ),
+        StructuredOutputStrategy::Native => format!(
+            "Structured response requirement: when you finish the task, reply with a single JSON object that strictly conforms to the `{}` schema and nothing else.",
+            schema.schema_name()
+        ),
+        StructuredOutputStrategy::Prompt => format!(
+            "Structured response requirement: when you finish the task, reply with a single JSON object that strictly conforms to the `{}` schema below and nothing else. Do not wrap it in prose.\n\nJSON Schema:\n{}",
+            schema.schema_name(),
+            schema.schema_json()
+        ),
+    }
+}
+
+/// Parse an assistant reply that should contain a structured JSON payload.
+///
+/// Native replies follow the strict schema, so the `null`s sent for optional properties are
+/// dropped before the payload is validated against the original schema.
+pub(crate) fn parse_structured_content(
+    schema: &SchemaHandle,
+    strategy: StructuredOutputStrategy,
+    content: &str,
+) -> std::result::Result<Value, AgentError> {
+    let trimmed = content.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|inner| inner.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(trimmed);
+
+    let mut payload: Value = serde_json::from_str(unfenced).map_err(|err| {
+        AgentError::Validation(format!(
+            "Response is not valid JSON for the `{}` schema: {}",
+            schema.schema_name(),
+            err
+        ))
+    })?;
+
+    if strategy == StructuredOutputStrategy::Native {
+        strip_optional_nulls(schema.schema_json(), &mut payload);
+    }
+
+    validate_structured_payload(schema, &payload)?;
+    Ok(payload)
+}
+
 pub(crate) fn structured_response_tool_name() -> &'static str {
     STRUCTURED_RESPONSE_TOOL_NAME
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{completion_schema, schemas::CompletionSchema};
+    use schemars::JsonSchema;
+    use serde::Serialize;
+
+    #[derive(Debug, Serialize, Deserialize, JsonSchema)]
+    #[completion_schema]
+    struct Verdict {
+        approved: bool,
+    }
+
+    #[test]
+    fn test_parse_structured_content_accepts_fenced_json() {
+        let payload = parse_structured_content(
+            Verdict::schema(),
+            StructuredOutputStrategy::Prompt,
+            "```json\n{\"approved\": true}\n```",
+        )
+        .unwrap();
+        assert_eq!(payload["approved"], true);
+    }
+
+    #[test]
+    fn test_parse_structured_content_rejects_schema_mismatch() {
+        let err = parse_structured_content(
+            Verdict::schema(),
+            StructuredOutputStrategy::Prompt,
+            "{\"approved\": \"yes\"}",
+        )
+        .unwrap_err();
+        assert_eq!(err.error_code(), "VALIDATION_ERROR");
+
+        let err = parse_structured_content(
+            Verdict::schema(),
+            StructuredOutputStrategy::Prompt,
+            "Looks good to me",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not valid JSON"));
+    }
+
+    #[derive(Debug, Serialize, Deserialize, JsonSchema)]
+    #[completion_schema]
+    struct Range(u32, u32);
+
+    #[test]
+    fn test_tuple_struct_tool_definition_keeps_positional_items() {
+        let definition = structured_response_tool_definition(Range::schema());
+        let structured = &definition["function"]["parameters"]["properties"]["structured"];
+        assert_eq!(structured["type"], "array");
+        assert_eq!(structured["items"].as_array().unwrap().len(), 2);
+        assert_eq!(structured["minItems"], 2);
+        assert_eq!(structured["maxItems"], 2);
+
+        assert!(validate_structured_payload(Range::schema(), &json!([1, 5])).is_ok());
+        assert!(validate_structured_payload(Range::schema(), &json!([1])).is_err());
+    }
+
+    #[test]
+    fn test_prompt_instructions_embed_schema() {
+        let mut messages = vec![json!({"role": "system", "content": "Base"})];
+        inject_schema_instructions(
+            &mut messages,
+            Verdict::schema(),
+            StructuredOutputStrategy::Prompt,
+        );
+
+        let content = messages[0]["content"].as_str().unwrap();
+        assert!(content.starts_with("Base"));
+        assert!(content.contains("JSON Schema:"));
+        assert!(content.contains("approved"));
+    }
+}
@@ -0,0 +1,355 @@
+//! HTTP API that exposes a configured agent.
+//!
+//! - `POST /v1/chat/completions`: OpenAI-compatible chat completions (optionally streamed)
+//! - `POST /runs`: run a prompt and return the full `RunResult`, or stream steps as SSE with
+//!   `"stream": true` (`step` events, then a `result` or `error` event)
+
+use crate::{
+    core::{Agent, AgentMemory, AgentStep},
+    error::{AgentError, Result},
+    types::result::RunResult,
+};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    convert::Infallible,
+    fmt,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+
+/// Body of `POST /runs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRequest {
+    pub prompt: String,
+    /// Stream steps as server-sent events instead of returning one JSON document
+    #[serde(default)]
+    pub stream: bool,
+}
+
+/// Body of `POST /v1/chat/completions` (fields the agent does not use are ignored)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub messages: Vec<Value>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub stream: bool,
+}
+
+/// Serves one agent over HTTP
+#[derive(Debug, Clone)]
+pub struct AgentServer {
+    agent: Arc<Agent>,
+    next_id: Arc<AtomicU64>,
+    step_observer: Option<StepObserver>,
+    run_observer: Option<RunObserver>,
+}
+
+/// Sees every step of every run as it is recorded
+#[derive(Clone)]
+struct StepObserver(Arc<dyn Fn(&AgentStep) + Send + Sync>);
+
+impl fmt::Debug for StepObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StepObserver")
+    }
+}
+
+/// A finished run, or the error it ended with
+type RunOutcome<'a> = std::result::Result<&'a RunResult, &'a AgentError>;
+
+type RunObserverFn = dyn Fn(RunOutcome<'_>, &[AgentStep]) + Send + Sync;
+
+/// Sees every finished run, with the steps it got through
+#[derive(Clone)]
+struct RunObserver(Arc<RunObserverFn>);
+
+impl fmt::Debug for RunObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RunObserver")
+    }
+}
+
+impl AgentServer {
+    /// Serve `agent`
+    pub fn new(agent: Agent) -> Self {
+        Self::from_shared(Arc::new(agent))
+    }
+
+    /// Serve an agent that is also used elsewhere
+    pub fn from_shared(agent: Arc<Agent>) -> Self {
+        Self {
+            agent,
+            next_id: Arc::new(AtomicU64::new(1)),
+            step_observer: None,
+            run_observer: None,
+        }
+    }
+
+    /// Call `observer` with each step of every run as it is recorded, redacted like the logs
+    pub fn with_step_observer<F>(mut self, observer: F) -> Self
+    where
+        F: Fn(&AgentStep) + Send + Sync + 'static,
+    {
+        self.step_observer = Some(StepObserver(Arc::new(observer)));
+        self
+    }
+
+    /// Call `observer` when a run finishes, with its result or error and the steps it recorded
+    /// (redacted like the logs), e.g. to keep a trace of every run
+    pub fn with_run_observer<F>(mut self, observer: F) -> Self
+    where
+        F: Fn(RunOutcome<'_>, &[AgentStep]) + Send + Sync + 'static,
+    {
+        self.run_observer = Some(RunObserver(Arc::new(observer)));
+        self
+    }
+
+    /// Router with all endpoints, for embedding into a larger application
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/v1/chat/completions", post(chat_completions))
+            .route("/runs", post(runs))
+            .with_state(self)
+    }
+
+    /// Serve on `addr` until the process exits
+    pub async fn serve(self, addr: SocketAddr) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|err| AgentError::Config(format!("failed to bind {}: {}", addr, err)))?;
+        axum::serve(listener, self.router())
+            .await
+            .map_err(|err| AgentError::Unknown(format!("HTTP server error: {}", err)))
+    }
+
+    /// Run `memory`, which ends with the new task, sending each step to `forward` and the
+    /// observers as it is recorded
+    async fn execute(
+        &self,
+        mut memory: AgentMemory,
+        forward: Option<UnboundedSender<AgentStep>>,
+    ) -> Result<RunResult> {
+        if forward.is_none() && self.step_observer.is_none() && self.run_observer.is_none() {
+            return self.agent.run_with_memory(&mut memory).await;
+        }
+
+        let (sender, mut receiver) = unbounded_channel::<AgentStep>();
+        memory.set_redactor(self.agent.redactor().cloned());
+        // The task step is already recorded, so forward it by hand
+        if let Some(task) = memory.last_step() {
+            let _ = sender.send(memory.redacted_step(task));
+        }
+        memory.set_step_sender(Some(sender));
+
+        let step_observer = self.step_observer.clone();
+        let keep_steps = self.run_observer.is_some();
+        let relay = tokio::spawn(async move {
+            let mut steps = Vec::new();
+            while let Some(step) = receiver.recv().await {
+                if let Some(observer) = &step_observer {
+                    (observer.0)(&step);
+                }
+                if keep_steps {
+                    steps.push(step.clone());
+                }
+                if let Some(forward) = &forward {
+                    let _ = forward.send(step);
+                }
+            }
+            steps
+        });
+
+        let outcome = self.agent.run_with_memory(&mut memory).await;
+        // Dropping the last sender lets the relay drain every remaining step
+        drop(memory);
+        let steps = relay.await.unwrap_or_default();
+        if let Some(observer) = &self.run_observer {
+            (observer.0)(outcome.as_ref(), &steps);
+        }
+        outcome
+    }
+
+    fn completion_id(&self) -> String {
+        format!("chatcmpl-{}", self.next_id.fetch_add(1, Ordering::SeqCst))
+    }
+
+    /// Build the conversation for a chat completion request.
+    ///
+    /// The agent's system prompt always applies; a client system message is appended to it.
+    fn conversation(&self, messages: Vec<Value>) -> Result<AgentMemory> {
+        let mut memory = AgentMemory::from(messages);
+        let system_prompt = match memory.system_prompt() {
+            Some(client) => format!("{}\n\n{}", self.agent.system_prompt(), client),
+            None => self.agent.system_prompt(),
+        };
+        memory.set_system_prompt(Some(system_prompt));
+        memory.set_replay_reasoning(self.agent.replay_reasoning());
+
+        if !matches!(memory.last_step(), Some(AgentStep::Task { .. })) {
+            return Err(AgentError::Validation(
+                "The last message must be a user message".to_string(),
+            ));
+        }
+        Ok(memory)
+    }
+}
+
+async fn chat_completions(
+    State(server): State<AgentServer>,
+    Json(request): Json<ChatCompletionRequest>,
+) -> Response {
+    let memory = match server.conversation(request.messages) {
+        Ok(memory) => memory,
+        Err(error) => return error_response(StatusCode::BAD_REQUEST, &error),
+    };
+    let id = server.completion_id();
+    let model = request
+        .model
+        .unwrap_or_else(|| server.agent.model().to_string());
+
+    if !request.stream {
+        return match server.execute(memory, None).await {
+            Ok(result) => Json(completion_body(&id, &model, &result)).into_response(),
+            Err(error) => error_response(status_for(&error), &error),
+        };
+    }
+
+    sse_response(move |events| async move {
+        let chunk = |delta: Value, finish_reason: Value| {
+            json!({
+                "id": id,
+                "object": "chat.completion.chunk",
+                "created": unix_time(),
+                "model": model,
+                "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }]
+            })
+        };
+
+        let _ = events.send(data_event(&chunk(
+            json!({ "role": "assistant" }),
+            Value::Null,
+        )));
+        match server.execute(memory, None).await {
+            Ok(result) => {
+                let _ = events.send(data_event(&chunk(
+                    json!({ "content": result.output }),
+                    Value::Null,
+                )));
+                let _ = events.send(data_event(&chunk(json!({}), json!("stop"))));
+            }
+            Err(error) => {
+                let _ = events.send(data_event(&error.to_error_payload()));
+            }
+        }
+        let _ = events.send(Event::default().data("[DONE]"));
+    })
+}
+
+async fn runs(State(server): State<AgentServer>, Json(request): Json<RunRequest>) -> Response {
+    let memory = server.agent.task_memory(&request.prompt, Vec::new());
+    if !request.stream {
+        return match server.execute(memory, None).await {
+            Ok(result) => Json(result).into_response(),
+            Err(error) => error_response(status_for(&error), &error),
+        };
+    }
+
+    sse_response(move |events| async move {
+        let (step_sender, mut steps) = unbounded_channel::<AgentStep>();
+        let step_events = events.clone();
+        let forward = tokio::spawn(async move {
+            while let Some(step) = steps.recv().await {
+                let _ = step_events.send(named_event("step", &step));
+            }
+        });
+
+        let outcome = server.execute(memory, Some(step_sender)).await;
+        // The step sender is dropped with the run, so this drains every remaining step
+        let _ = forward.await;
+
+        let _ = events.send(match outcome {
+            Ok(result) => named_event("result", &result),
+            Err(error) => named_event("error", &error.to_error_payload()),
+        });
+    })
+}
+
+/// Run `produce` in the background and stream the events it sends
+fn sse_response<F, Fut>(produce: F) -> Response
+where
+    F: FnOnce(UnboundedSender<Event>) -> Fut,
+    Fut: std::future::Future<Output = ()> + Send + 'static,
+{
+    let (events, receiver) = unbounded_channel();
+    tokio::spawn(produce(events));
+
+    let stream = UnboundedReceiverStream::new(receiver).map(Ok::<_, Infallible>);
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn data_event(payload: &Value) -> Event {
+    Event::default().data(payload.to_string())
+}
+
+fn named_event<T: Serialize>(name: &str, payload: &T) -> Event {
+    Event::default()
+        .event(name)
+        .data(serde_json::to_string(payload).unwrap_or_default())
+}
+
+fn completion_body(id: &str, model: &str, result: &RunResult) -> Value {
+    let mut body = json!({
+        "id": id,
+        "object": "chat.completion",
+        "created": unix_time(),
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": result.output },
+            "finish_reason": "stop"
+        }]
+    });
+    if let Some(tokens) = &result.tokens {
+        body["usage"] = json!(tokens);
+    }
+    body
+}
+
+fn status_for(error: &AgentError) -> StatusCode {
+    match error {
+        AgentError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        AgentError::RateLimit { .. } => StatusCode::TOO_MANY_REQUESTS,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn error_response(status: StatusCode, error: &AgentError) -> Response {
+    (status, Json(error.to_error_payload())).into_response()
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
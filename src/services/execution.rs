@@ -1,31 +1,58 @@
 use super::response_handler::{
-    handle_final_answer_messages, handle_final_answer_steps, handle_structured_response_messages,
-    handle_structured_response_steps, ErrorSink, FinalAnswerContext, FinalAnswerStepsContext,
-    HandlerOutcome, StructuredResponseContext, StructuredResponseStepsContext,
+    handle_final_answer_messages, handle_final_answer_steps, handle_plain_answer,
+    handle_structured_content_messages, handle_structured_content_steps,
+    handle_structured_response_messages, handle_structured_response_steps, ErrorSink,
+    FinalAnswerContext, FinalAnswerStepsContext, HandlerOutcome, StructuredContentContext,
+    StructuredContentStepsContext, StructuredResponseContext, StructuredResponseStepsContext,
 };
 use crate::{
-    core::{agent::Agent, memory::AgentMemory, steps::AgentStep},
+    core::{
+        agent::Agent,
+        conversation::SchemaContext,
+        memory::{detect_tool_error, AgentMemory},
+        steps::{attach_reasoning, AgentStep},
+        team::{HandoffTarget, LoopOutcome, Team},
+    },
     error::{AgentError, Result},
-    schemas::validation::{
-        final_answer_tool_definition, inject_schema_instructions,
-        structured_response_tool_definition, structured_response_tool_name,
+    schemas::{
+        validation::{
+            final_answer_tool_definition, inject_schema_instructions,
+            structured_response_tool_definition, structured_response_tool_name,
+        },
+        SchemaHandle, StructuredOutputStrategy,
     },
     services::{
         openai_client::ChatCompletionRequest,
+        planning::{generate_planning_prompt, get_tool_names},
+        reasoning::extract_reasoning,
         tool_call_utils::{
             extract_arguments_str, extract_function_info, extract_tool_call_id,
             parse_function_arguments,
         },
     },
-    types::result::{RunResult, TokenUsage},
+    tools::{
+        context::{CancellationToken, RunContext, ToolContext},
+        result::ToolResponse,
+        retrieval::{search_tools_definition, search_tools_observation, SEARCH_TOOLS_NAME},
+        ToolChoice, ToolSelection,
+    },
+    types::{
+        content::{split_message_content, tool_attachments_message, ContentPart},
+        result::{accumulate_usage, RunResult, TokenUsage},
+    },
 };
 use serde_json::{json, Value};
 use std::time::Instant;
-use tokio::time::timeout;
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::timeout,
+};
 
 /// ErrorSink implementation for AgentMemory (run_with_steps)
 struct MemorySink<'a> {
     memory: &'a mut AgentMemory,
+    /// Plain reply being handled, recorded before a reminder so the model sees what it redoes
+    reply: Option<&'a str>,
 }
 
 impl<'a> ErrorSink for MemorySink<'a> {
@@ -34,6 +61,9 @@ impl<'a> ErrorSink for MemorySink<'a> {
             tool_call_id: tool_call_id.to_string(),
             result: error_message,
             is_error: true,
+            attachments: Vec::new(),
+            structured: None,
+            artifacts: Vec::new(),
         });
     }
 
@@ -42,6 +72,21 @@ impl<'a> ErrorSink for MemorySink<'a> {
             tool_call_id: tool_call_id.to_string(),
             result,
             is_error,
+            attachments: Vec::new(),
+            structured: None,
+            artifacts: Vec::new(),
+        });
+    }
+
+    fn report_reminder(&mut self, label: &str, message: String) {
+        if let Some(content) = self.reply.take().filter(|content| !content.is_empty()) {
+            self.memory.add_step(AgentStep::Reply {
+                content: content.to_string(),
+            });
+        }
+        self.memory.add_step(AgentStep::Reminder {
+            label: label.to_string(),
+            message,
         });
     }
 }
@@ -67,68 +112,415 @@ impl<'a> ErrorSink for MessagesSink<'a> {
             "content": result
         }));
     }
+
+    fn report_reminder(&mut self, _label: &str, message: String) {
+        self.messages.push(json!({
+            "role": "system",
+            "content": message
+        }));
+    }
 }
 
 impl Agent {
+    /// Schema whose payload is expected as plain assistant content rather than a tool call
+    fn content_schema(&self) -> Option<&SchemaHandle> {
+        match self.structured_output() {
+            StructuredOutputStrategy::Tool => None,
+            StructuredOutputStrategy::Native | StructuredOutputStrategy::Prompt => {
+                self.completion_schema()
+            }
+        }
+    }
+
+    /// Build the chat completion request body for a single turn offering the selected tools
+    fn build_request_body(
+        &self,
+        mut messages: Vec<Value>,
+        selection: &ToolSelection,
+    ) -> Result<Value> {
+        let mut tools = self.function_factory().get_selected_openai_tools(selection);
+        if self
+            .tool_retrieval()
+            .is_some_and(|retrieval| retrieval.search_tool())
+        {
+            tools.push(search_tools_definition());
+        }
+        let mut response_format = None;
+
+        match self.completion_schema() {
+            Some(schema) => {
+                inject_schema_instructions(&mut messages, schema, self.structured_output());
+
+                match self.structured_output() {
+                    StructuredOutputStrategy::Tool => {
+                        tools.push(structured_response_tool_definition(schema));
+                    }
+                    StructuredOutputStrategy::Native => {
+                        let mut schema_context = SchemaContext::default();
+                        schema_context.set_handle(schema.clone());
+                        response_format = schema_context.response_format()?;
+                    }
+                    StructuredOutputStrategy::Prompt => {}
+                }
+            }
+            None => tools.push(final_answer_tool_definition()),
+        }
+
+        let mut chat_request = ChatCompletionRequest::new(self.model().to_owned(), messages)
+            .with_max_tokens(self.max_tokens());
+
+        if !tools.is_empty() {
+            chat_request = chat_request
+                .with_tools(tools)
+                .with_tool_choice(json!("auto"));
+        }
+
+        if let Some(response_format) = response_format {
+            chat_request = chat_request.with_response_format(response_format);
+        }
+
+        Ok(chat_request.into_value())
+    }
+
+    /// Copy reasoning from a provider reply onto the message kept in history, when enabled
+    fn replay_reasoning_into(&self, message: &mut Value, assistant_message: &Value) {
+        if !self.replay_reasoning() {
+            return;
+        }
+
+        if let Some(reasoning) = extract_reasoning(assistant_message) {
+            attach_reasoning(message, &reasoning.content, &reasoning.details);
+        }
+    }
+
+    /// Tools retrieved for `task` among `candidates`, or `None` when retrieval is off
+    async fn retrieve_tools(
+        &self,
+        task: &str,
+        candidates: &ToolSelection,
+    ) -> Result<Option<Vec<String>>> {
+        match self.tool_retrieval() {
+            Some(retrieval) => Ok(Some(
+                retrieval
+                    .retrieve(self.function_factory(), task, candidates)
+                    .await?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether a call to `function_name` is the `search_tools` meta-tool
+    fn is_search_tools_call(&self, function_name: &str) -> bool {
+        function_name == SEARCH_TOOLS_NAME
+            && self
+                .tool_retrieval()
+                .is_some_and(|retrieval| retrieval.search_tool())
+    }
+
+    /// Answer a `search_tools` call, adding the tools it finds to `retrieved`
+    async fn search_tools(
+        &self,
+        arguments: &Value,
+        candidates: &ToolSelection,
+        retrieved: &mut Vec<String>,
+    ) -> Result<Value> {
+        let query = arguments
+            .get("query")
+            .and_then(|query| query.as_str())
+            .ok_or_else(|| {
+                AgentError::InvalidFunctionCall(format!(
+                    "`{}` requires a `query` string",
+                    SEARCH_TOOLS_NAME
+                ))
+            })?;
+        let found = match self.tool_retrieval() {
+            Some(retrieval) => {
+                retrieval
+                    .retrieve(self.function_factory(), query, candidates)
+                    .await?
+            }
+            None => Vec::new(),
+        };
+        for name in &found {
+            if !retrieved.contains(name) {
+                retrieved.push(name.clone());
+            }
+        }
+        Ok(search_tools_observation(self.function_factory(), &found))
+    }
+
+    /// Run a tool the model called, refusing tools outside the iteration's selection
+    async fn execute_tool(
+        &self,
+        selection: &ToolSelection,
+        function_name: &str,
+        arguments: Value,
+        context: ToolContext,
+    ) -> Result<ToolResponse> {
+        if !self
+            .function_factory()
+            .is_selected(function_name, selection)
+        {
+            return Err(AgentError::ToolNotFound(function_name.to_string()));
+        }
+        self.function_factory()
+            .execute_function_with_context(function_name, arguments, context)
+            .await
+    }
+
+    /// Ask the model for a plan for the next iteration without letting it call tools
+    async fn generate_plan(
+        &self,
+        mut messages: Vec<Value>,
+        task: &str,
+        iteration: usize,
+        selection: &ToolSelection,
+    ) -> Result<Option<String>> {
+        let mut tool_names = get_tool_names(self.function_factory());
+        tool_names.retain(|name| self.function_factory().is_selected(name, selection));
+        messages.push(json!({
+            "role": "user",
+            "content": generate_planning_prompt(task, &tool_names, iteration)
+        }));
+
+        let mut request_body = self.build_request_body(messages, selection)?;
+        if request_body.get("tools").is_some() {
+            request_body["tool_choice"] = json!("none");
+        }
+        if let Some(body) = request_body.as_object_mut() {
+            body.remove("response_format");
+        }
+
+        let response = timeout(self.timeout(), self.make_raw_request(&request_body))
+            .await
+            .map_err(|_| AgentError::Timeout("OpenAI API call timed out".to_string()))??;
+
+        let plan = first_assistant_message(&response)?
+            .get("content")
+            .and_then(|value| value.as_str())
+            .unwrap_or("")
+            .trim()
+            .to_string();
+
+        Ok(Some(plan).filter(|plan| !plan.is_empty()))
+    }
+
+    /// Shared state for the tool calls of a new run, which may continue the caller's run id
+    pub(crate) fn run_context(
+        &self,
+        cancellation: CancellationToken,
+        run_id: Option<&str>,
+    ) -> RunContext {
+        let run = RunContext::new(cancellation, self.user_data().clone())
+            .with_redactor(self.redactor().cloned());
+        match run_id {
+            Some(run_id) => run.with_run_id(run_id),
+            None => run,
+        }
+    }
+
+    /// Send a chat completion request, giving up early if the run is cancelled
+    async fn request_completion(&self, run: &RunContext, request_body: &Value) -> Result<Value> {
+        check_cancelled(run)?;
+        tokio::select! {
+            response = timeout(self.timeout(), self.make_raw_request(request_body)) => {
+                response.map_err(|_| AgentError::Timeout("OpenAI API call timed out".to_string()))?
+            }
+            _ = run.cancellation().cancelled() => Err(cancelled_error(run)),
+        }
+    }
+
     pub async fn run_with_steps(&self, prompt: &str) -> Result<RunResult> {
+        self.run_with_attachments(prompt, Vec::new()).await
+    }
+
+    /// Run a task that includes images or files alongside the prompt text
+    pub async fn run_with_attachments(
+        &self,
+        prompt: &str,
+        attachments: Vec<ContentPart>,
+    ) -> Result<RunResult> {
+        let mut memory = self.task_memory(prompt, attachments);
+        self.run_with_memory(&mut memory).await
+    }
+
+    /// Run a task that stops early once `cancellation` is cancelled.
+    ///
+    /// The run checks the token before each model request and tool call and fails with
+    /// [`AgentError::Cancelled`]; tools see it through
+    /// [`ToolContext::cancellation`](crate::ToolContext::cancellation). Other runs get a token of
+    /// their own, so a cancelled token never affects later runs.
+    pub async fn run_with_cancellation(
+        &self,
+        prompt: &str,
+        cancellation: CancellationToken,
+    ) -> Result<RunResult> {
+        let mut memory = self.task_memory(prompt, Vec::new());
+        self.run_cancellable(&mut memory, cancellation).await
+    }
+
+    /// Run a task while sending each step to `sender` as soon as it is recorded
+    pub async fn run_with_step_sender(
+        &self,
+        prompt: &str,
+        sender: UnboundedSender<AgentStep>,
+    ) -> Result<RunResult> {
+        let mut memory = self.task_memory(prompt, Vec::new());
+        // The task step is recorded before the sender is attached, so forward it by hand
+        if let Some(task) = memory.last_step() {
+            let _ = sender.send(memory.redacted_step(task));
+        }
+        memory.set_step_sender(Some(sender));
+        self.run_with_memory(&mut memory).await
+    }
+
+    /// Continue the conversation in `memory`, which must end with a new task.
+    ///
+    /// On success the run's steps (including the final answer) are kept in `memory`, so the
+    /// next task can be added and run in the same conversation. Memory without a system prompt
+    /// gets this agent's.
+    pub async fn run_with_memory(&self, memory: &mut AgentMemory) -> Result<RunResult> {
+        self.run_cancellable(memory, CancellationToken::new()).await
+    }
+
+    async fn run_cancellable(
+        &self,
+        memory: &mut AgentMemory,
+        cancellation: CancellationToken,
+    ) -> Result<RunResult> {
+        let mut working = memory.clone();
+        if working.system_prompt().is_none() {
+            working.set_system_prompt(Some(self.system_prompt()));
+        }
+
+        let run = self.run_context(cancellation, None);
+        match self.run_memory(working, &[], run).await? {
+            LoopOutcome::Finished(result) => {
+                memory.replace_steps(result.steps.clone());
+                Ok(result)
+            }
+            LoopOutcome::Handoff { target, .. } => {
+                Err(AgentError::ToolNotFound(Team::handoff_tool_name(&target)))
+            }
+        }
+    }
+
+    /// Run a task delegated by the tool call in `parent`, sharing its cancellation and run id.
+    ///
+    /// Always returns the run, so it can be nested into the parent's trace. A run that fails
+    /// comes back with the error, the steps it got through (as streamed, so redacted), and the
+    /// tokens and iterations it used.
+    pub(crate) async fn run_delegated(
+        &self,
+        task: &str,
+        parent: &ToolContext,
+    ) -> (RunResult, Option<AgentError>) {
+        let (sender, mut receiver) = unbounded_channel();
+        let mut memory = self.task_memory(task, Vec::new());
+        if let Some(task) = memory.last_step() {
+            let _ = sender.send(memory.redacted_step(task));
+        }
+        memory.set_step_sender(Some(sender));
+
+        // A context made outside of a run has no id to continue
+        let run_id = Some(parent.run_id()).filter(|run_id| !run_id.is_empty());
+        let run = self.run_context(parent.cancellation().clone(), run_id);
         let start_time = Instant::now();
-        let mut memory = AgentMemory::with_default_system();
+        let error = match self.run_memory(memory, &[], run.clone()).await {
+            Ok(LoopOutcome::Finished(result)) => return (result, None),
+            Ok(LoopOutcome::Handoff { target, .. }) => {
+                AgentError::ToolNotFound(Team::handoff_tool_name(&target))
+            }
+            Err(error) => error,
+        };
+
+        let mut steps = Vec::new();
+        while let Ok(step) = receiver.try_recv() {
+            steps.push(step);
+        }
+        let (tokens, iterations) = run.spent();
+        let mut partial = RunResult::new(
+            String::new(),
+            None,
+            None,
+            steps,
+            tokens,
+            start_time.elapsed(),
+            iterations,
+        );
+        partial.run_id = run.run_id().to_string();
+        (partial, Some(error))
+    }
 
+    pub(crate) fn task_memory(&self, prompt: &str, attachments: Vec<ContentPart>) -> AgentMemory {
+        let mut memory = AgentMemory::new(Some(self.system_prompt()));
+        memory.set_replay_reasoning(self.replay_reasoning());
+        memory.set_redactor(self.redactor().cloned());
         memory.add_step(AgentStep::Task {
             content: prompt.to_string(),
+            attachments,
         });
+        memory
+    }
+
+    /// Continue the conversation in `memory` until a final answer or a handoff to a team member
+    pub(crate) async fn run_memory(
+        &self,
+        mut memory: AgentMemory,
+        handoffs: &[HandoffTarget],
+        run: RunContext,
+    ) -> Result<LoopOutcome> {
+        self.check_structured_output()?;
+        memory.set_redactor(self.redactor().cloned());
+        let start_time = Instant::now();
+        let prompt = latest_task(&memory);
+        let prompt = prompt.as_str();
+
+        self.guardrails().check_input(prompt).await?;
+        let mut retrieved = self.retrieve_tools(prompt, memory.tool_selection()).await?;
 
         let mut iteration = 0;
+        let mut iteration_start = 0;
         let mut has_final_answer = false;
         let mut final_answer_value: Option<String> = None;
+        let mut forced_called = false;
+        let mut total_usage: Option<TokenUsage> = None;
 
         while iteration < self.max_iterations() {
             iteration += 1;
-
-            let mut messages = memory.as_messages();
-            if let Some(schema) = self.completion_schema() {
-                inject_schema_instructions(&mut messages, schema);
-            }
-
-            let mut tools = self.function_factory().get_openai_tools();
-            if let Some(schema) = self.completion_schema() {
-                tools.push(structured_response_tool_definition(schema));
-            } else {
-                tools.push(final_answer_tool_definition());
-            }
-
-            let mut chat_request =
-                ChatCompletionRequest::new(self.model().to_owned(), messages.clone())
-                    .with_max_tokens(self.max_tokens());
-
-            if !tools.is_empty() {
-                chat_request = chat_request
-                    .with_tools(tools)
-                    .with_tool_choice(json!("auto"));
+            run.record_iteration();
+            let selection = narrow_to_retrieved(
+                self.tool_selection(iteration)
+                    .and(memory.tool_selection().clone()),
+                &retrieved,
+            );
+
+            if let Some(planning) = self.planning() {
+                let previous_had_error = memory.steps()[iteration_start..].iter().any(|step| {
+                    matches!(
+                        step,
+                        AgentStep::Observation { is_error: true, .. } | AgentStep::Reminder { .. }
+                    )
+                });
+                if planning.should_plan(iteration, previous_had_error) {
+                    if let Some(plan) = self
+                        .generate_plan(memory.as_messages(), prompt, iteration, &selection)
+                        .await?
+                    {
+                        memory.add_step(AgentStep::Planning { plan });
+                    }
+                }
             }
+            iteration_start = memory.step_count();
 
-            let request_body = chat_request.into_value();
+            let mut request_body = self.build_request_body(memory.as_messages(), &selection)?;
+            add_handoff_tools(&mut request_body, handoffs);
+            let tool_choice = self.tool_choice(iteration, forced_called);
+            apply_tool_choice(&mut request_body, &tool_choice)?;
 
-            let response = timeout(self.timeout(), self.make_raw_request(&request_body))
-                .await
-                .map_err(|_| AgentError::Timeout("OpenAI API call timed out".to_string()))??;
+            let response = self.request_completion(&run, &request_body).await?;
 
-            let choices = response
-                .get("choices")
-                .and_then(|value| value.as_array())
-                .ok_or_else(|| {
-                    AgentError::Unknown(
-                        "Missing 'choices' array in completion response".to_string(),
-                    )
-                })?;
-
-            let first_choice = choices.first().ok_or_else(|| {
-                AgentError::Unknown("Completion response contained no choices".to_string())
-            })?;
-
-            let assistant_message = first_choice.get("message").cloned().ok_or_else(|| {
-                AgentError::Unknown("Completion response missing assistant message".to_string())
-            })?;
+            let assistant_message = first_assistant_message(&response)?;
 
             let token_usage = response.get("usage").and_then(|usage| {
                 Some(TokenUsage {
@@ -137,9 +529,24 @@ impl Agent {
                     total_tokens: usage.get("total_tokens")?.as_u64()? as u32,
                 })
             });
+            if let Some(usage) = &token_usage {
+                accumulate_usage(&mut total_usage, usage);
+                run.record_usage(usage);
+            }
+
+            if let Some(reasoning) = extract_reasoning(&assistant_message) {
+                memory.add_step(AgentStep::Reasoning {
+                    content: reasoning.content,
+                    details: reasoning.details,
+                });
+            }
 
-            if let Some(tool_calls) = assistant_message.get("tool_calls") {
+            if let Some(tool_calls) = assistant_message
+                .get("tool_calls")
+                .filter(|calls| calls.as_array().is_some_and(|calls| !calls.is_empty()))
+            {
                 if let Some(tool_calls_array) = tool_calls.as_array() {
+                    forced_called |= calls_function(tool_calls_array, self.forced_function());
                     let turn_has_final_answer = tool_calls_array.iter().any(|tool_call| {
                         tool_call
                             .get("function")
@@ -159,10 +566,15 @@ impl Agent {
                             .to_error_payload()
                             .to_string(),
                             is_error: true,
+                            attachments: Vec::new(),
+                            structured: None,
+                            artifacts: Vec::new(),
                         });
                         continue;
                     }
 
+                    let mut pending_handoff: Option<(String, Option<String>)> = None;
+
                     for tool_call in tool_calls_array {
                         let tool_call_id = extract_tool_call_id(tool_call);
 
@@ -173,6 +585,9 @@ impl Agent {
                                     tool_call_id: tool_call_id.to_string(),
                                     result: "Tool call missing function".to_string(),
                                     is_error: true,
+                                    attachments: Vec::new(),
+                                    structured: None,
+                                    artifacts: Vec::new(),
                                 });
                                 continue;
                             }
@@ -185,6 +600,9 @@ impl Agent {
                                     tool_call_id: tool_call_id.to_string(),
                                     result: "Tool call missing function name".to_string(),
                                     is_error: true,
+                                    attachments: Vec::new(),
+                                    structured: None,
+                                    artifacts: Vec::new(),
                                 });
                                 continue;
                             }
@@ -200,6 +618,7 @@ impl Agent {
                                     let steps = memory.steps().to_vec();
                                     let mut sink = MemorySink {
                                         memory: &mut memory,
+                                        reply: None,
                                     };
                                     let ctx = FinalAnswerStepsContext {
                                         base: FinalAnswerContext {
@@ -208,16 +627,22 @@ impl Agent {
                                             completion_schema: self.completion_schema(),
                                             has_final_answer: &mut has_final_answer,
                                             final_answer_value: &mut final_answer_value,
+                                            guardrails: self.guardrails(),
+                                            redactor: self
+                                                .redactor()
+                                                .map(|redactor| redactor.as_ref()),
                                         },
                                         steps: &steps,
-                                        token_usage: token_usage.clone(),
+                                        token_usage: total_usage.clone(),
                                         start_duration: start_time.elapsed(),
                                         iteration,
                                     };
 
-                                    match handle_final_answer_steps(ctx, &mut sink)? {
+                                    match handle_final_answer_steps(ctx, &mut sink).await? {
                                         HandlerOutcome::Continue => continue,
-                                        HandlerOutcome::ReturnResult(result) => return Ok(result),
+                                        HandlerOutcome::ReturnResult(result) => {
+                                            return Ok(finish_run(&mut memory, &run, result))
+                                        }
                                         HandlerOutcome::ReturnAnswer(_) => unreachable!(),
                                     }
                                 }
@@ -234,6 +659,9 @@ impl Agent {
                                                 tool_call_id: tool_call_id.to_string(),
                                                 result: payload.to_string(),
                                                 is_error: true,
+                                                attachments: Vec::new(),
+                                                structured: None,
+                                                artifacts: Vec::new(),
                                             });
                                             continue;
                                         }
@@ -242,6 +670,7 @@ impl Agent {
                                     let steps = memory.steps().to_vec();
                                     let mut sink = MemorySink {
                                         memory: &mut memory,
+                                        reply: None,
                                     };
                                     let ctx = StructuredResponseStepsContext {
                                         base: StructuredResponseContext {
@@ -249,37 +678,137 @@ impl Agent {
                                             arguments_json,
                                             schema: &schema,
                                             final_answer_value: final_answer_value.clone(),
+                                            guardrails: self.guardrails(),
+                                            redactor: self
+                                                .redactor()
+                                                .map(|redactor| redactor.as_ref()),
                                         },
                                         steps: &steps,
-                                        token_usage: token_usage.clone(),
+                                        token_usage: total_usage.clone(),
                                         start_duration: start_time.elapsed(),
                                         iteration,
                                     };
 
-                                    match handle_structured_response_steps(ctx, &mut sink)? {
+                                    match handle_structured_response_steps(ctx, &mut sink).await? {
                                         HandlerOutcome::Continue => continue,
-                                        HandlerOutcome::ReturnResult(result) => return Ok(result),
+                                        HandlerOutcome::ReturnResult(result) => {
+                                            return Ok(finish_run(&mut memory, &run, result))
+                                        }
                                         HandlerOutcome::ReturnAnswer(_) => unreachable!(),
                                     }
                                 }
 
+                                if let Some(retrieved) = retrieved
+                                    .as_mut()
+                                    .filter(|_| self.is_search_tools_call(&function_name))
+                                {
+                                    memory.add_step(AgentStep::Action {
+                                        tool_name: function_name.to_string(),
+                                        tool_call_id: tool_call_id.to_string(),
+                                        arguments: arguments_json.clone(),
+                                    });
+                                    let (result, is_error) = match self
+                                        .search_tools(
+                                            &arguments_json,
+                                            memory.tool_selection(),
+                                            retrieved,
+                                        )
+                                        .await
+                                    {
+                                        Ok(found) => (found.to_string(), false),
+                                        Err(e) => (e.to_error_payload().to_string(), true),
+                                    };
+                                    memory.add_step(AgentStep::Observation {
+                                        tool_call_id: tool_call_id.to_string(),
+                                        result,
+                                        is_error,
+                                        attachments: Vec::new(),
+                                        structured: None,
+                                        artifacts: Vec::new(),
+                                    });
+                                    continue;
+                                }
+
+                                if let Some(target) = handoffs
+                                    .iter()
+                                    .find(|target| target.tool_name == function_name)
+                                {
+                                    let reason = arguments_json
+                                        .get("reason")
+                                        .and_then(|reason| reason.as_str())
+                                        .map(str::to_string);
+                                    memory.add_step(AgentStep::Action {
+                                        tool_name: function_name.to_string(),
+                                        tool_call_id: tool_call_id.to_string(),
+                                        arguments: arguments_json,
+                                    });
+                                    memory.add_step(AgentStep::Observation {
+                                        tool_call_id: tool_call_id.to_string(),
+                                        result: json!({ "transferred_to": target.name })
+                                            .to_string(),
+                                        is_error: false,
+                                        attachments: Vec::new(),
+                                        structured: None,
+                                        artifacts: Vec::new(),
+                                    });
+                                    pending_handoff = Some((target.name.clone(), reason));
+                                    continue;
+                                }
+
                                 // Regular tool execution
+                                check_cancelled(&run)?;
                                 memory.add_step(AgentStep::Action {
                                     tool_name: function_name.to_string(),
                                     tool_call_id: tool_call_id.to_string(),
                                     arguments: arguments_json.clone(),
                                 });
 
-                                match self
-                                    .function_factory()
-                                    .execute_function(&function_name, arguments_json)
-                                    .await
-                                {
-                                    Ok(result) => {
+                                let context = ToolContext::for_call(
+                                    &run,
+                                    tool_call_id,
+                                    iteration,
+                                    memory.steps(),
+                                    memory.step_sender().cloned(),
+                                );
+                                let outcome = self
+                                    .execute_tool(
+                                        &selection,
+                                        &function_name,
+                                        arguments_json,
+                                        context.clone(),
+                                    )
+                                    .await;
+                                memory.record_sent_steps(context.take_progress());
+                                // Sub-agent runs are nested whether or not the call succeeded
+                                for nested in context.take_delegations() {
+                                    if let Some(usage) = &nested.run.tokens {
+                                        accumulate_usage(&mut total_usage, usage);
+                                        run.record_usage(usage);
+                                    }
+                                    memory.add_step(AgentStep::Delegation {
+                                        tool_call_id: tool_call_id.to_string(),
+                                        agent: nested.agent,
+                                        depth: nested.depth,
+                                        run: Box::new(nested.run),
+                                    });
+                                }
+
+                                match outcome {
+                                    Ok(output) => {
+                                        let (result, attachments, tool_result) =
+                                            output.into_observation();
+                                        let (structured, artifacts) = tool_result
+                                            .map(|tool_result| {
+                                                (tool_result.structured, tool_result.artifacts)
+                                            })
+                                            .unwrap_or_default();
                                         memory.add_step(AgentStep::Observation {
                                             tool_call_id: tool_call_id.to_string(),
-                                            result: result.to_string(),
+                                            result,
                                             is_error: false,
+                                            attachments,
+                                            structured,
+                                            artifacts,
                                         });
                                     }
                                     Err(e) => {
@@ -288,7 +817,16 @@ impl Agent {
                                             tool_call_id: tool_call_id.to_string(),
                                             result: error_payload.to_string(),
                                             is_error: true,
+                                            attachments: Vec::new(),
+                                            structured: None,
+                                            artifacts: Vec::new(),
                                         });
+                                        // A fatal error ends the run. The caller's memory is
+                                        // left as it was before the run, so this trace only
+                                        // survives in the steps already streamed to a sender
+                                        if e.is_fatal() {
+                                            return Err(e);
+                                        }
                                     }
                                 };
                             }
@@ -297,10 +835,24 @@ impl Agent {
                                     tool_call_id: tool_call_id.to_string(),
                                     result: error.to_error_payload().to_string(),
                                     is_error: true,
+                                    attachments: Vec::new(),
+                                    structured: None,
+                                    artifacts: Vec::new(),
                                 });
                             }
                         }
                     }
+
+                    // Hand over only after every tool call in the turn has an observation
+                    if let Some((target, reason)) = pending_handoff {
+                        return Ok(LoopOutcome::Handoff {
+                            memory,
+                            target,
+                            reason,
+                            tokens: total_usage,
+                            iterations: iteration,
+                        });
+                    }
                 }
             } else {
                 let answer = assistant_message
@@ -310,6 +862,62 @@ impl Agent {
                     .trim()
                     .to_string();
 
+                if let Some(schema) = self.content_schema() {
+                    let steps = memory.steps().to_vec();
+                    let mut sink = MemorySink {
+                        memory: &mut memory,
+                        reply: Some(&answer),
+                    };
+                    let ctx = StructuredContentStepsContext {
+                        base: StructuredContentContext {
+                            content: &answer,
+                            schema,
+                            strategy: self.structured_output(),
+                            guardrails: self.guardrails(),
+                            redactor: self.redactor().map(|redactor| redactor.as_ref()),
+                        },
+                        steps: &steps,
+                        token_usage: total_usage.clone(),
+                        start_duration: start_time.elapsed(),
+                        iteration,
+                    };
+
+                    match handle_structured_content_steps(ctx, &mut sink).await? {
+                        HandlerOutcome::Continue => continue,
+                        HandlerOutcome::ReturnResult(result) => {
+                            return Ok(finish_run(&mut memory, &run, result))
+                        }
+                        HandlerOutcome::ReturnAnswer(_) => unreachable!(),
+                    }
+                }
+
+                // With tools turned off, a plain reply is the final answer
+                if tool_choice == ToolChoice::None
+                    && self.completion_schema().is_none()
+                    && !answer.is_empty()
+                {
+                    let mut sink = MemorySink {
+                        memory: &mut memory,
+                        reply: Some(&answer),
+                    };
+                    match handle_plain_answer(&answer, self.guardrails(), &mut sink).await? {
+                        HandlerOutcome::Continue => continue,
+                        HandlerOutcome::ReturnAnswer(answer) => {
+                            let result = RunResult::new(
+                                answer,
+                                None,
+                                None,
+                                memory.steps().to_vec(),
+                                total_usage.clone(),
+                                start_time.elapsed(),
+                                iteration,
+                            );
+                            return Ok(finish_run(&mut memory, &run, result));
+                        }
+                        HandlerOutcome::ReturnResult(_) => unreachable!(),
+                    }
+                }
+
                 let message = if !has_final_answer {
                     if answer.is_empty() {
                         "Assistant must call the `final_answer` tool to conclude the task, but returned no content.".to_string()
@@ -338,11 +946,11 @@ impl Agent {
                     "final_answer"
                 };
 
-                memory.add_step(AgentStep::Observation {
-                    tool_call_id: tool_name.to_string(),
-                    result: message,
-                    is_error: true,
-                });
+                let mut sink = MemorySink {
+                    memory: &mut memory,
+                    reply: Some(&answer),
+                };
+                sink.report_reminder(tool_name, message);
 
                 continue;
             }
@@ -352,64 +960,66 @@ impl Agent {
     }
 
     pub async fn run_with_messages(&self, mut messages: Vec<Value>) -> Result<String> {
+        self.check_structured_output()?;
+        let run = self.run_context(CancellationToken::new(), None);
+        // The turn being answered; later user messages only forward tool attachments
+        let task = latest_user_text(&messages);
+        self.guardrails().check_input(&task).await?;
+        let mut retrieved = self.retrieve_tools(&task, &ToolSelection::all()).await?;
+
         let mut iteration = 0;
+        let mut iteration_start = 0;
         let mut has_final_answer = false;
         let mut final_answer_value: Option<String> = None;
+        let mut forced_called = false;
 
         while iteration < self.max_iterations() {
             iteration += 1;
-
-            if let Some(schema) = self.completion_schema() {
-                inject_schema_instructions(&mut messages, schema);
-            }
-
-            let mut tools = self.function_factory().get_openai_tools();
-            if let Some(schema) = self.completion_schema() {
-                tools.push(structured_response_tool_definition(schema));
-            } else {
-                tools.push(final_answer_tool_definition());
-            }
-
-            let mut chat_request =
-                ChatCompletionRequest::new(self.model().to_owned(), messages.clone())
-                    .with_max_tokens(self.max_tokens());
-
-            if !tools.is_empty() {
-                chat_request = chat_request
-                    .with_tools(tools)
-                    .with_tool_choice(json!("auto"));
+            let selection = narrow_to_retrieved(self.tool_selection(iteration), &retrieved);
+
+            if let Some(planning) = self.planning() {
+                let previous_had_error = messages[iteration_start..].iter().any(|message| {
+                    message.get("role").and_then(|role| role.as_str()) == Some("tool")
+                        && message
+                            .get("content")
+                            .and_then(|content| content.as_str())
+                            .is_some_and(detect_tool_error)
+                });
+                if planning.should_plan(iteration, previous_had_error) {
+                    if let Some(plan) = self
+                        .generate_plan(messages.clone(), &task, iteration, &selection)
+                        .await?
+                    {
+                        messages.push(json!({
+                            "role": "assistant",
+                            "content": plan
+                        }));
+                    }
+                }
             }
+            iteration_start = messages.len();
 
-            let request_body = chat_request.into_value();
+            let mut request_body = self.build_request_body(messages.clone(), &selection)?;
+            let tool_choice = self.tool_choice(iteration, forced_called);
+            apply_tool_choice(&mut request_body, &tool_choice)?;
 
-            let response = timeout(self.timeout(), self.make_raw_request(&request_body))
-                .await
-                .map_err(|_| AgentError::Timeout("OpenAI API call timed out".to_string()))??;
+            let response = self.request_completion(&run, &request_body).await?;
 
-            let choices = response
-                .get("choices")
-                .and_then(|value| value.as_array())
-                .ok_or_else(|| {
-                    AgentError::Unknown(
-                        "Missing 'choices' array in completion response".to_string(),
-                    )
-                })?;
-
-            let first_choice = choices.first().ok_or_else(|| {
-                AgentError::Unknown("Completion response contained no choices".to_string())
-            })?;
+            let assistant_message = first_assistant_message(&response)?;
 
-            let assistant_message = first_choice.get("message").cloned().ok_or_else(|| {
-                AgentError::Unknown("Completion response missing assistant message".to_string())
-            })?;
-
-            if let Some(tool_calls) = assistant_message.get("tool_calls") {
+            if let Some(tool_calls) = assistant_message
+                .get("tool_calls")
+                .filter(|calls| calls.as_array().is_some_and(|calls| !calls.is_empty()))
+            {
                 if let Some(tool_calls_array) = tool_calls.as_array() {
-                    messages.push(json!({
+                    forced_called |= calls_function(tool_calls_array, self.forced_function());
+                    let mut turn_message = json!({
                         "role": "assistant",
                         "content": assistant_message.get("content").unwrap_or(&json!("")),
                         "tool_calls": tool_calls
-                    }));
+                    });
+                    self.replay_reasoning_into(&mut turn_message, &assistant_message);
+                    messages.push(turn_message);
 
                     let turn_has_final_answer = tool_calls_array.iter().any(|tool_call| {
                         tool_call
@@ -433,6 +1043,7 @@ impl Agent {
                         continue;
                     }
 
+                    let mut attachment_messages = Vec::new();
                     for tool_call in tool_calls_array {
                         let tool_call_id = extract_tool_call_id(tool_call);
 
@@ -494,9 +1105,11 @@ impl Agent {
                                 completion_schema: self.completion_schema(),
                                 has_final_answer: &mut has_final_answer,
                                 final_answer_value: &mut final_answer_value,
+                                guardrails: self.guardrails(),
+                                redactor: self.redactor().map(|redactor| redactor.as_ref()),
                             };
 
-                            match handle_final_answer_messages(ctx, &mut sink)? {
+                            match handle_final_answer_messages(ctx, &mut sink).await? {
                                 HandlerOutcome::Continue => continue,
                                 HandlerOutcome::ReturnAnswer(answer) => return Ok(answer),
                                 HandlerOutcome::ReturnResult(_) => unreachable!(),
@@ -540,34 +1153,67 @@ impl Agent {
                                 arguments_json,
                                 schema: &schema,
                                 final_answer_value: final_answer_value.clone(),
+                                guardrails: self.guardrails(),
+                                redactor: self.redactor().map(|redactor| redactor.as_ref()),
                             };
 
-                            match handle_structured_response_messages(ctx, &mut sink)? {
+                            match handle_structured_response_messages(ctx, &mut sink).await? {
                                 HandlerOutcome::Continue => continue,
                                 HandlerOutcome::ReturnAnswer(answer) => return Ok(answer),
                                 HandlerOutcome::ReturnResult(_) => unreachable!(),
                             }
                         }
 
-                        // Regular tool execution
-                        let result = match parsed_arguments {
+                        if let Some(retrieved) = retrieved
+                            .as_mut()
+                            .filter(|_| self.is_search_tools_call(&function_name))
+                        {
+                            let result = match parsed_arguments {
+                                Ok(arguments_json) => self
+                                    .search_tools(&arguments_json, &ToolSelection::all(), retrieved)
+                                    .await
+                                    .unwrap_or_else(|e| e.to_error_payload()),
+                                Err(error) => error.to_error_payload(),
+                            };
+                            messages.push(json!({
+                                "role": "tool",
+                                "tool_call_id": tool_call_id,
+                                "content": result.to_string()
+                            }));
+                            continue;
+                        }
+
+                        // Regular tool execution; message-based runs keep no steps to share
+                        check_cancelled(&run)?;
+                        let context =
+                            ToolContext::for_call(&run, tool_call_id, iteration, &[], None);
+                        let output = match parsed_arguments {
                             Ok(arguments_json) => match self
-                                .function_factory()
-                                .execute_function(&function_name, arguments_json)
+                                .execute_tool(&selection, &function_name, arguments_json, context)
                                 .await
                             {
-                                Ok(result) => result,
-                                Err(e) => e.to_error_payload(),
+                                Ok(output) => output,
+                                Err(e) if e.is_fatal() => return Err(e),
+                                Err(e) => e.to_error_payload().into(),
                             },
-                            Err(error) => error.to_error_payload(),
+                            Err(error) => error.to_error_payload().into(),
                         };
 
+                        let (result, attachments, _) = output.into_observation();
+                        if !attachments.is_empty() {
+                            attachment_messages
+                                .push(tool_attachments_message(tool_call_id, &attachments));
+                        }
+
                         messages.push(json!({
                             "role": "tool",
                             "tool_call_id": tool_call_id,
-                            "content": result.to_string()
+                            "content": result
                         }));
                     }
+
+                    // Tool messages must directly follow the assistant turn, so images go last
+                    messages.extend(attachment_messages);
                 }
             } else {
                 let answer = assistant_message
@@ -577,6 +1223,53 @@ impl Agent {
                     .trim()
                     .to_string();
 
+                if let Some(schema) = self.content_schema() {
+                    let mut turn_message = json!({
+                        "role": "assistant",
+                        "content": answer
+                    });
+                    self.replay_reasoning_into(&mut turn_message, &assistant_message);
+                    messages.push(turn_message);
+
+                    let mut sink = MessagesSink {
+                        messages: &mut messages,
+                    };
+                    let ctx = StructuredContentContext {
+                        content: &answer,
+                        schema,
+                        strategy: self.structured_output(),
+                        guardrails: self.guardrails(),
+                        redactor: self.redactor().map(|redactor| redactor.as_ref()),
+                    };
+
+                    match handle_structured_content_messages(ctx, &mut sink).await? {
+                        HandlerOutcome::Continue => continue,
+                        HandlerOutcome::ReturnAnswer(answer) => return Ok(answer),
+                        HandlerOutcome::ReturnResult(_) => unreachable!(),
+                    }
+                }
+
+                if tool_choice == ToolChoice::None
+                    && self.completion_schema().is_none()
+                    && !answer.is_empty()
+                {
+                    let mut turn_message = json!({
+                        "role": "assistant",
+                        "content": answer
+                    });
+                    self.replay_reasoning_into(&mut turn_message, &assistant_message);
+                    messages.push(turn_message);
+
+                    let mut sink = MessagesSink {
+                        messages: &mut messages,
+                    };
+                    match handle_plain_answer(&answer, self.guardrails(), &mut sink).await? {
+                        HandlerOutcome::Continue => continue,
+                        HandlerOutcome::ReturnAnswer(answer) => return Ok(answer),
+                        HandlerOutcome::ReturnResult(_) => unreachable!(),
+                    }
+                }
+
                 let content = if self.completion_schema().is_some() {
                     if answer.is_empty() {
                         format!(
@@ -611,3 +1304,145 @@ impl Agent {
         Err(AgentError::MaxIterations(self.max_iterations()))
     }
 }
+
+/// Extract the first choice's assistant message from a completion response
+fn first_assistant_message(response: &Value) -> Result<Value> {
+    let choices = response
+        .get("choices")
+        .and_then(|value| value.as_array())
+        .ok_or_else(|| {
+            AgentError::Unknown("Missing 'choices' array in completion response".to_string())
+        })?;
+
+    let first_choice = choices.first().ok_or_else(|| {
+        AgentError::Unknown("Completion response contained no choices".to_string())
+    })?;
+
+    first_choice.get("message").cloned().ok_or_else(|| {
+        AgentError::Unknown("Completion response missing assistant message".to_string())
+    })
+}
+
+/// Text of the most recent user message, the turn a message-based run answers
+fn latest_user_text(messages: &[Value]) -> String {
+    messages
+        .iter()
+        .rev()
+        .find(|message| message.get("role").and_then(|role| role.as_str()) == Some("user"))
+        .and_then(|message| message.get("content"))
+        .and_then(split_message_content)
+        .map(|(text, _)| text)
+        .unwrap_or_default()
+}
+
+/// Fail with [`AgentError::Cancelled`] once the run's cancellation token has been cancelled
+fn check_cancelled(run: &RunContext) -> Result<()> {
+    if run.cancellation().is_cancelled() {
+        return Err(cancelled_error(run));
+    }
+    Ok(())
+}
+
+fn cancelled_error(run: &RunContext) -> AgentError {
+    AgentError::Cancelled(format!("run {} was cancelled", run.run_id()))
+}
+
+/// Record the final answer in memory and return the run with the complete trace
+fn finish_run(memory: &mut AgentMemory, run: &RunContext, mut result: RunResult) -> LoopOutcome {
+    let final_step = match result.steps.last() {
+        Some(step @ AgentStep::FinalAnswer { .. }) => step.clone(),
+        _ => AgentStep::FinalAnswer {
+            answer: result.output.clone(),
+            structured: result.structured.clone(),
+        },
+    };
+    memory.add_step(final_step);
+    result.steps = memory.steps().to_vec();
+    result.run_id = run.run_id().to_string();
+    result.redactor = run.redactor().cloned();
+    LoopOutcome::Finished(result)
+}
+
+/// Text of the most recent task in memory, used as the task description for planning
+fn latest_task(memory: &AgentMemory) -> String {
+    memory
+        .steps()
+        .iter()
+        .rev()
+        .find_map(|step| match step {
+            AgentStep::Task { content, .. } => Some(content.clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Narrow `selection` to the retrieved tools, if retrieval is on
+fn narrow_to_retrieved(selection: ToolSelection, retrieved: &Option<Vec<String>>) -> ToolSelection {
+    match retrieved {
+        Some(names) => selection.and(ToolSelection::only(names.iter().cloned())),
+        None => selection,
+    }
+}
+
+/// Whether a turn's tool calls include `function`
+fn calls_function(tool_calls: &[Value], function: Option<String>) -> bool {
+    function.is_some_and(|function| {
+        tool_calls
+            .iter()
+            .any(|tool_call| tool_call["function"]["name"].as_str() == Some(function.as_str()))
+    })
+}
+
+/// Set the request's `tool_choice`, checking that a forced tool is actually offered
+fn apply_tool_choice(request_body: &mut Value, tool_choice: &ToolChoice) -> Result<()> {
+    let offered: Vec<&str> = request_body
+        .get("tools")
+        .and_then(|tools| tools.as_array())
+        .map(|tools| {
+            tools
+                .iter()
+                .filter_map(|tool| tool["function"]["name"].as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if offered.is_empty() {
+        return match tool_choice {
+            ToolChoice::Auto | ToolChoice::None => Ok(()),
+            _ => Err(AgentError::Config(format!(
+                "Tool choice {:?} requires a tool, but none are offered",
+                tool_choice
+            ))),
+        };
+    }
+    if let Some(function) = tool_choice.forced_function() {
+        if !offered.contains(&function.as_str()) {
+            return Err(AgentError::Config(format!(
+                "Tool choice forces '{}', which is not offered on this iteration",
+                function
+            )));
+        }
+    }
+
+    request_body["tool_choice"] = tool_choice.to_openai();
+    Ok(())
+}
+
+/// Offer `transfer_to_<name>` tools for the other members of a team
+fn add_handoff_tools(request_body: &mut Value, handoffs: &[HandoffTarget]) {
+    if handoffs.is_empty() {
+        return;
+    }
+
+    let definitions = handoffs.iter().map(HandoffTarget::tool_definition);
+    match request_body
+        .get_mut("tools")
+        .and_then(|tools| tools.as_array_mut())
+    {
+        Some(tools) => tools.extend(definitions),
+        None => {
+            request_body["tools"] = Value::Array(definitions.collect());
+            request_body["tool_choice"] = json!("auto");
+        }
+    }
+}
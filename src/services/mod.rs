@@ -1,5 +1,6 @@
 pub(crate) mod execution;
 pub(crate) mod openai_client;
 pub(crate) mod planning;
+pub(crate) mod reasoning;
 pub(crate) mod response_handler;
 pub(crate) mod tool_call_utils;
@@ -163,7 +163,6 @@ impl ChatCompletionRequest {
         self
     }
 
-    #[allow(dead_code)]
     pub fn with_response_format(mut self, response_format: Value) -> Self {
         self.response_format = Some(response_format);
         self
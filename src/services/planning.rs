@@ -1,5 +1,57 @@
 use crate::tools::FunctionFactory;
 
+/// Opt-in planning behaviour for the agent loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanningConfig {
+    /// Re-plan every `interval` iterations (after the initial plan)
+    pub interval: Option<usize>,
+    /// Re-plan after an iteration that produced a tool error
+    pub replan_on_error: bool,
+}
+
+impl PlanningConfig {
+    /// Plan once before the first iteration and again after tool errors
+    pub fn new() -> Self {
+        Self {
+            interval: None,
+            replan_on_error: true,
+        }
+    }
+
+    /// Re-plan every `interval` iterations
+    pub fn with_interval(mut self, interval: usize) -> Self {
+        self.interval = Some(interval).filter(|interval| *interval > 0);
+        self
+    }
+
+    /// Enable or disable re-planning after tool errors
+    pub fn with_replan_on_error(mut self, replan_on_error: bool) -> Self {
+        self.replan_on_error = replan_on_error;
+        self
+    }
+
+    /// Whether a plan should be generated before the given (1-based) iteration
+    pub fn should_plan(&self, iteration: usize, previous_had_error: bool) -> bool {
+        if iteration == 1 {
+            return true;
+        }
+
+        if self.replan_on_error && previous_had_error {
+            return true;
+        }
+
+        self.interval
+            .map(|interval| (iteration - 1).is_multiple_of(interval))
+            .unwrap_or(false)
+    }
+}
+
+impl Default for PlanningConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Generate a planning prompt for the agent before tool execution
 pub fn generate_planning_prompt(
     task: &str,
@@ -118,6 +170,20 @@ mod tests {
         assert!(!is_planning_response("Done"));
     }
 
+    #[test]
+    fn test_planning_config_schedule() {
+        let config = PlanningConfig::new().with_interval(3);
+        assert!(config.should_plan(1, false));
+        assert!(!config.should_plan(2, false));
+        assert!(!config.should_plan(3, false));
+        assert!(config.should_plan(4, false));
+        assert!(config.should_plan(2, true));
+
+        let config = PlanningConfig::new().with_replan_on_error(false);
+        assert!(!config.should_plan(2, true));
+        assert!(!config.should_plan(7, false));
+    }
+
     #[test]
     fn test_tool_planning_prompt_empty() {
         let factory = FunctionFactory::new();
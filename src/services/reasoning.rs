@@ -0,0 +1,76 @@
+use serde_json::Value;
+
+/// Reasoning text and raw provider blocks extracted from an assistant message
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ExtractedReasoning {
+    pub content: String,
+    pub details: Vec<Value>,
+}
+
+/// Extract reasoning from an assistant message.
+///
+/// Reads `reasoning` (OpenRouter) or `reasoning_content` (DeepSeek and others) for the text and
+/// keeps `reasoning_details` verbatim so encrypted blocks can be sent back unchanged.
+pub(crate) fn extract_reasoning(message: &Value) -> Option<ExtractedReasoning> {
+    let details = message
+        .get("reasoning_details")
+        .and_then(|value| value.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let content = ["reasoning", "reasoning_content"]
+        .iter()
+        .filter_map(|key| message.get(*key).and_then(|value| value.as_str()))
+        .map(str::trim)
+        .find(|text| !text.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| details_text(&details));
+
+    if content.is_empty() && details.is_empty() {
+        return None;
+    }
+
+    Some(ExtractedReasoning { content, details })
+}
+
+/// Fall back to the readable parts of `reasoning_details` when no plain text field is present
+fn details_text(details: &[Value]) -> String {
+    details
+        .iter()
+        .filter_map(|detail| {
+            detail
+                .get("text")
+                .or_else(|| detail.get("summary"))
+                .and_then(|value| value.as_str())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_reasoning_variants() {
+        let openrouter = json!({"content": null, "reasoning": "Check the units first."});
+        assert_eq!(
+            extract_reasoning(&openrouter).unwrap().content,
+            "Check the units first."
+        );
+
+        let deepseek = json!({"content": "42", "reasoning_content": "6 * 7 = 42"});
+        assert_eq!(extract_reasoning(&deepseek).unwrap().content, "6 * 7 = 42");
+
+        let encrypted = json!({
+            "content": "",
+            "reasoning_details": [{"type": "reasoning.encrypted", "data": "opaque"}]
+        });
+        let extracted = extract_reasoning(&encrypted).unwrap();
+        assert!(extracted.content.is_empty());
+        assert_eq!(extracted.details.len(), 1);
+
+        assert!(extract_reasoning(&json!({"content": "plain"})).is_none());
+    }
+}
@@ -1,11 +1,12 @@
 use crate::{
-    core::steps::AgentStep,
-    error::AgentError,
+    core::{guardrails::Guardrails, redaction::Redactor, steps::AgentStep},
+    error::{AgentError, GuardrailViolation},
     schemas::{
         validation::{
-            validate_structured_payload, FinalAnswerArguments, StructuredResponseArguments,
+            parse_structured_content, validate_structured_payload, FinalAnswerArguments,
+            StructuredResponseArguments,
         },
-        SchemaHandle,
+        SchemaHandle, StructuredOutputStrategy,
     },
     types::result::{RunResult, TokenUsage},
 };
@@ -14,9 +15,11 @@ use std::time::Duration;
 use tracing::debug;
 
 /// Trait to abstract over memory.add_step vs messages.push
-pub(super) trait ErrorSink {
+pub(super) trait ErrorSink: Send {
     fn report_error(&mut self, tool_call_id: &str, error_message: String);
     fn report_observation(&mut self, tool_call_id: &str, result: String, is_error: bool);
+    /// Report a correction for a plain assistant reply that did not complete the task
+    fn report_reminder(&mut self, label: &str, message: String);
 }
 
 /// Handler outcome indicating what the execution loop should do next
@@ -37,6 +40,8 @@ pub(super) struct FinalAnswerContext<'a> {
     pub completion_schema: Option<&'a SchemaHandle>,
     pub has_final_answer: &'a mut bool,
     pub final_answer_value: &'a mut Option<String>,
+    pub guardrails: &'a Guardrails,
+    pub redactor: Option<&'a Redactor>,
 }
 
 /// Context needed for run_with_steps final_answer handler
@@ -49,7 +54,7 @@ pub(super) struct FinalAnswerStepsContext<'a> {
 }
 
 /// Handle final_answer tool call for run_with_steps
-pub(super) fn handle_final_answer_steps(
+pub(super) async fn handle_final_answer_steps(
     ctx: FinalAnswerStepsContext<'_>,
     sink: &mut dyn ErrorSink,
 ) -> Result<HandlerOutcome, AgentError> {
@@ -98,21 +103,12 @@ pub(super) fn handle_final_answer_steps(
     let structured_opt = final_args.structured.clone();
     if let Some(schema) = ctx.base.completion_schema {
         if let Some(structured_val) = structured_opt.as_ref() {
-            if !structured_val.is_object() {
-                let err = AgentError::Validation(format!(
-                    "`final_answer.structured` must be a JSON object that matches the `{}` schema",
-                    schema.schema_name()
-                ));
-                sink.report_error(ctx.base.tool_call_id, err.to_error_payload().to_string());
-                return Ok(HandlerOutcome::Continue);
-            }
-
             if let Err(err) = validate_structured_payload(schema, structured_val) {
                 debug!(
                     target: "tinyagent::schema",
                     schema = schema.schema_name(),
                     error = %err,
-                    payload = %structured_val
+                    payload = %loggable(ctx.base.redactor, structured_val)
                 );
                 sink.report_error(ctx.base.tool_call_id, err.to_error_payload().to_string());
                 return Ok(HandlerOutcome::Continue);
@@ -120,6 +116,20 @@ pub(super) fn handle_final_answer_steps(
         }
     }
 
+    // Only an answer that ends the run is checked; one still waiting for its payload is
+    // checked together with it in `handle_structured_response_steps`
+    if structured_opt.is_some() || ctx.base.completion_schema.is_none() {
+        let checked = ctx
+            .base
+            .guardrails
+            .check_output(answer, structured_opt.as_ref())
+            .await?;
+        if let Some(violation) = checked {
+            report_violation(ctx.base.tool_call_id, violation, sink);
+            return Ok(HandlerOutcome::Continue);
+        }
+    }
+
     let answer_string = answer.to_string();
     *ctx.base.has_final_answer = true;
     *ctx.base.final_answer_value = Some(answer_string.clone());
@@ -162,7 +172,7 @@ pub(super) fn handle_final_answer_steps(
 }
 
 /// Handle final_answer tool call for run_with_messages
-pub(super) fn handle_final_answer_messages(
+pub(super) async fn handle_final_answer_messages(
     ctx: FinalAnswerContext<'_>,
     sink: &mut dyn ErrorSink,
 ) -> Result<HandlerOutcome, AgentError> {
@@ -207,21 +217,12 @@ pub(super) fn handle_final_answer_messages(
     let structured_opt = final_args.structured.clone();
     if let Some(schema) = ctx.completion_schema {
         if let Some(structured_val) = structured_opt.as_ref() {
-            if !structured_val.is_object() {
-                let err = AgentError::Validation(format!(
-                    "`final_answer.structured` must be a JSON object that matches the `{}` schema",
-                    schema.schema_name()
-                ));
-                sink.report_error(ctx.tool_call_id, err.to_error_payload().to_string());
-                return Ok(HandlerOutcome::Continue);
-            }
-
             if let Err(err) = validate_structured_payload(schema, structured_val) {
                 debug!(
                     target: "tinyagent::schema",
                     schema = schema.schema_name(),
                     error = %err,
-                    payload = %structured_val
+                    payload = %loggable(ctx.redactor, structured_val)
                 );
                 sink.report_error(ctx.tool_call_id, err.to_error_payload().to_string());
                 return Ok(HandlerOutcome::Continue);
@@ -229,6 +230,17 @@ pub(super) fn handle_final_answer_messages(
         }
     }
 
+    if structured_opt.is_some() || ctx.completion_schema.is_none() {
+        let checked = ctx
+            .guardrails
+            .check_output(answer, structured_opt.as_ref())
+            .await?;
+        if let Some(violation) = checked {
+            report_violation(ctx.tool_call_id, violation, sink);
+            return Ok(HandlerOutcome::Continue);
+        }
+    }
+
     let answer_string = answer.to_string();
     *ctx.has_final_answer = true;
     *ctx.final_answer_value = Some(answer_string.clone());
@@ -257,6 +269,8 @@ pub(super) struct StructuredResponseContext<'a> {
     pub arguments_json: Value,
     pub schema: &'a SchemaHandle,
     pub final_answer_value: Option<String>,
+    pub guardrails: &'a Guardrails,
+    pub redactor: Option<&'a Redactor>,
 }
 
 /// Context for run_with_steps structured_response handler
@@ -269,7 +283,7 @@ pub(super) struct StructuredResponseStepsContext<'a> {
 }
 
 /// Handle structured_response tool call for run_with_steps
-pub(super) fn handle_structured_response_steps(
+pub(super) async fn handle_structured_response_steps(
     ctx: StructuredResponseStepsContext<'_>,
     sink: &mut dyn ErrorSink,
 ) -> Result<HandlerOutcome, AgentError> {
@@ -290,25 +304,12 @@ pub(super) fn handle_structured_response_steps(
         }
     };
 
-    if !args.structured.is_object() {
-        sink.report_error(
-            ctx.base.tool_call_id,
-            AgentError::Validation(format!(
-                "`structured_response.structured` must be a JSON object that matches the `{}` schema",
-                ctx.base.schema.schema_name()
-            ))
-            .to_error_payload()
-            .to_string(),
-        );
-        return Ok(HandlerOutcome::Continue);
-    }
-
     if let Err(err) = validate_structured_payload(ctx.base.schema, &args.structured) {
         debug!(
             target: "tinyagent::schema",
             schema = ctx.base.schema.schema_name(),
             error = %err,
-            payload = %args.structured
+            payload = %loggable(ctx.base.redactor, &args.structured)
         );
         sink.report_error(ctx.base.tool_call_id, err.to_error_payload().to_string());
         return Ok(HandlerOutcome::Continue);
@@ -319,6 +320,16 @@ pub(super) fn handle_structured_response_steps(
         .final_answer_value
         .unwrap_or_else(|| "Task completed with structured response".to_string());
 
+    let checked = ctx
+        .base
+        .guardrails
+        .check_output(&answer_string, Some(&args.structured))
+        .await?;
+    if let Some(violation) = checked {
+        report_violation(ctx.base.tool_call_id, violation, sink);
+        return Ok(HandlerOutcome::Continue);
+    }
+
     Ok(HandlerOutcome::ReturnResult(RunResult::new(
         answer_string,
         Some(args.structured),
@@ -331,7 +342,7 @@ pub(super) fn handle_structured_response_steps(
 }
 
 /// Handle structured_response tool call for run_with_messages
-pub(super) fn handle_structured_response_messages(
+pub(super) async fn handle_structured_response_messages(
     ctx: StructuredResponseContext<'_>,
     sink: &mut dyn ErrorSink,
 ) -> Result<HandlerOutcome, AgentError> {
@@ -351,25 +362,12 @@ pub(super) fn handle_structured_response_messages(
         }
     };
 
-    if !args.structured.is_object() {
-        sink.report_error(
-            ctx.tool_call_id,
-            AgentError::Validation(format!(
-                "`structured_response.structured` must be a JSON object that matches the `{}` schema",
-                ctx.schema.schema_name()
-            ))
-            .to_error_payload()
-            .to_string(),
-        );
-        return Ok(HandlerOutcome::Continue);
-    }
-
     if let Err(err) = validate_structured_payload(ctx.schema, &args.structured) {
         debug!(
             target: "tinyagent::schema",
             schema = ctx.schema.schema_name(),
             error = %err,
-            payload = %args.structured
+            payload = %loggable(ctx.redactor, &args.structured)
         );
         sink.report_error(ctx.tool_call_id, err.to_error_payload().to_string());
         return Ok(HandlerOutcome::Continue);
@@ -379,6 +377,15 @@ pub(super) fn handle_structured_response_messages(
         .final_answer_value
         .unwrap_or_else(|| "Task completed with structured response".to_string());
 
+    let checked = ctx
+        .guardrails
+        .check_output(&answer_string, Some(&args.structured))
+        .await?;
+    if let Some(violation) = checked {
+        report_violation(ctx.tool_call_id, violation, sink);
+        return Ok(HandlerOutcome::Continue);
+    }
+
     sink.report_observation(
         ctx.tool_call_id,
         serde_json::json!({ "status": "accepted" }).to_string(),
@@ -387,3 +394,157 @@ pub(super) fn handle_structured_response_messages(
 
     Ok(HandlerOutcome::ReturnAnswer(answer_string))
 }
+
+/// Context needed for structured replies delivered as plain content (native / prompt strategies)
+pub(super) struct StructuredContentContext<'a> {
+    pub content: &'a str,
+    pub schema: &'a SchemaHandle,
+    pub strategy: StructuredOutputStrategy,
+    pub guardrails: &'a Guardrails,
+    pub redactor: Option<&'a Redactor>,
+}
+
+/// Context for run_with_steps structured content handler
+pub(super) struct StructuredContentStepsContext<'a> {
+    pub base: StructuredContentContext<'a>,
+    pub steps: &'a [AgentStep],
+    pub token_usage: Option<TokenUsage>,
+    pub start_duration: Duration,
+    pub iteration: usize,
+}
+
+/// Handle a structured reply delivered as assistant content for run_with_steps
+pub(super) async fn handle_structured_content_steps(
+    ctx: StructuredContentStepsContext<'_>,
+    sink: &mut dyn ErrorSink,
+) -> Result<HandlerOutcome, AgentError> {
+    let structured =
+        match parse_structured_content(ctx.base.schema, ctx.base.strategy, ctx.base.content) {
+            Ok(value) => value,
+            Err(err) => {
+                report_structured_content_error(&ctx.base, err, sink);
+                return Ok(HandlerOutcome::Continue);
+            }
+        };
+
+    let answer_string = ctx.base.content.trim().to_string();
+    let checked = ctx
+        .base
+        .guardrails
+        .check_output(&answer_string, Some(&structured))
+        .await?;
+    if let Some(violation) = checked {
+        report_violation_reminder(violation, sink);
+        return Ok(HandlerOutcome::Continue);
+    }
+
+    let mut steps = ctx.steps.to_vec();
+    steps.push(AgentStep::FinalAnswer {
+        answer: answer_string.clone(),
+        structured: Some(structured.clone()),
+    });
+
+    Ok(HandlerOutcome::ReturnResult(RunResult::new(
+        answer_string,
+        Some(structured),
+        Some(ctx.base.schema.clone()),
+        steps,
+        ctx.token_usage,
+        ctx.start_duration,
+        ctx.iteration,
+    )))
+}
+
+/// Handle a structured reply delivered as assistant content for run_with_messages
+pub(super) async fn handle_structured_content_messages(
+    ctx: StructuredContentContext<'_>,
+    sink: &mut dyn ErrorSink,
+) -> Result<HandlerOutcome, AgentError> {
+    let structured = match parse_structured_content(ctx.schema, ctx.strategy, ctx.content) {
+        Ok(value) => value,
+        Err(err) => {
+            report_structured_content_error(&ctx, err, sink);
+            return Ok(HandlerOutcome::Continue);
+        }
+    };
+
+    let answer_string = ctx.content.trim().to_string();
+    let checked = ctx
+        .guardrails
+        .check_output(&answer_string, Some(&structured))
+        .await?;
+    if let Some(violation) = checked {
+        report_violation_reminder(violation, sink);
+        return Ok(HandlerOutcome::Continue);
+    }
+    Ok(HandlerOutcome::ReturnAnswer(answer_string))
+}
+
+/// Handle a plain reply, which is the final answer when the iteration's tool choice is
+/// [`ToolChoice::None`](crate::ToolChoice::None)
+pub(super) async fn handle_plain_answer(
+    answer: &str,
+    guardrails: &Guardrails,
+    sink: &mut dyn ErrorSink,
+) -> Result<HandlerOutcome, AgentError> {
+    if let Some(violation) = guardrails.check_output(answer, None).await? {
+        report_violation_reminder(violation, sink);
+        return Ok(HandlerOutcome::Continue);
+    }
+    Ok(HandlerOutcome::ReturnAnswer(answer.to_string()))
+}
+
+fn report_structured_content_error(
+    ctx: &StructuredContentContext<'_>,
+    err: AgentError,
+    sink: &mut dyn ErrorSink,
+) {
+    debug!(
+        target: "tinyagent::schema",
+        schema = ctx.schema.schema_name(),
+        error = %err,
+        payload = %ctx
+            .redactor
+            .map_or_else(|| ctx.content.to_string(), |redactor| redactor.redact_text(ctx.content))
+    );
+    sink.report_reminder(
+        ctx.schema.schema_name(),
+        format!(
+            "Reply with only JSON that matches the `{}` schema. {}",
+            ctx.schema.schema_name(),
+            err.to_error_payload()
+        ),
+    );
+}
+
+/// `payload` as it may appear in logs
+fn loggable(redactor: Option<&Redactor>, payload: &Value) -> Value {
+    match redactor {
+        Some(redactor) => redactor.redact_value(payload),
+        None => payload.clone(),
+    }
+}
+
+/// Send an output guardrail violation back to the model as the result of its call
+fn report_violation(tool_call_id: &str, violation: GuardrailViolation, sink: &mut dyn ErrorSink) {
+    debug!(target: "tinyagent::guardrails", %violation);
+    sink.report_error(
+        tool_call_id,
+        AgentError::Guardrail(violation)
+            .to_error_payload()
+            .to_string(),
+    );
+}
+
+/// Send an output guardrail violation on a plain reply back to the model as a reminder
+fn report_violation_reminder(violation: GuardrailViolation, sink: &mut dyn ErrorSink) {
+    debug!(target: "tinyagent::guardrails", %violation);
+    let label = violation.guardrail.clone();
+    sink.report_reminder(
+        &label,
+        format!(
+            "Your answer was rejected; answer again without the problem. {}",
+            AgentError::Guardrail(violation).to_error_payload()
+        ),
+    );
+}
@@ -0,0 +1,389 @@
+use super::sub_agent::SubAgentRun;
+use crate::{
+    core::{redaction::Redactor, steps::AgentStep},
+    types::result::{accumulate_usage, TokenUsage},
+};
+use serde_json::Value;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{mpsc::UnboundedSender, Notify};
+use tracing::info;
+
+/// What a tool can see of the run that called it.
+///
+/// Passed to [`Tool::execute_with_context`](super::Tool::execute_with_context). Tools called
+/// outside of a run (tests, the MCP server) get [`ToolContext::default`], which has empty ids,
+/// no prior steps and a cancellation token nobody cancels.
+#[derive(Debug, Clone, Default)]
+pub struct ToolContext {
+    run_id: Arc<str>,
+    tool_call_id: String,
+    iteration: usize,
+    steps: Arc<[AgentStep]>,
+    cancellation: CancellationToken,
+    user_data: UserData,
+    progress: ProgressSink,
+    redactor: Option<Arc<Redactor>>,
+    delegations: Arc<Mutex<Vec<SubAgentRun>>>,
+}
+
+impl ToolContext {
+    /// Context for a call outside of an agent run, e.g. to test a tool
+    pub fn new(run_id: impl Into<String>, tool_call_id: impl Into<String>) -> Self {
+        Self {
+            run_id: Arc::from(run_id.into()),
+            tool_call_id: tool_call_id.into(),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn for_call(
+        run: &RunContext,
+        tool_call_id: &str,
+        iteration: usize,
+        steps: &[AgentStep],
+        step_sender: Option<UnboundedSender<AgentStep>>,
+    ) -> Self {
+        Self {
+            run_id: run.run_id.clone(),
+            tool_call_id: tool_call_id.to_string(),
+            iteration,
+            steps: Arc::from(steps),
+            cancellation: run.cancellation.clone(),
+            user_data: run.user_data.clone(),
+            progress: ProgressSink {
+                sender: step_sender,
+                recorded: Arc::default(),
+            },
+            redactor: run.redactor.clone(),
+            delegations: Arc::default(),
+        }
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn with_user_data(mut self, user_data: UserData) -> Self {
+        self.user_data = user_data;
+        self
+    }
+
+    /// Id shared by every tool call of the same run, for correlating logs
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Id of the tool call being executed
+    pub fn tool_call_id(&self) -> &str {
+        &self.tool_call_id
+    }
+
+    /// Iteration of the agent loop the call was made in, starting at 1
+    pub fn iteration(&self) -> usize {
+        self.iteration
+    }
+
+    /// Steps recorded before this call, up to and including its own action
+    pub fn steps(&self) -> &[AgentStep] {
+        &self.steps
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// Value of type `T` registered with [`Agent::with_user_data`](crate::Agent::with_user_data)
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.user_data.get::<T>()
+    }
+
+    /// Report progress to the caller as an [`AgentStep::Progress`].
+    ///
+    /// The step is sent to the run's step sender right away and added to the trace once the
+    /// tool returns; the model never sees it.
+    pub fn progress(&self, message: impl Into<String>, data: Option<Value>) {
+        let step = AgentStep::Progress {
+            tool_call_id: self.tool_call_id.clone(),
+            message: message.into(),
+            data,
+        };
+        let shown = match &self.redactor {
+            Some(redactor) => redactor.redact_step(&step, &self.steps),
+            None => step.clone(),
+        };
+        info!(target: "tinyagent::steps", run_id = %self.run_id, "{}", shown.describe());
+        if let Some(sender) = &self.progress.sender {
+            let _ = sender.send(shown);
+        }
+        if let Ok(mut recorded) = self.progress.recorded.lock() {
+            recorded.push(step);
+        }
+    }
+
+    /// Progress steps reported so far, already sent to the step sender
+    pub(crate) fn take_progress(&self) -> Vec<AgentStep> {
+        self.progress
+            .recorded
+            .lock()
+            .map(|mut recorded| std::mem::take(&mut *recorded))
+            .unwrap_or_default()
+    }
+
+    /// Hand the run of a sub-agent this call delegated to back to the calling loop
+    pub(crate) fn record_delegation(&self, delegation: SubAgentRun) {
+        if let Ok(mut delegations) = self.delegations.lock() {
+            delegations.push(delegation);
+        }
+    }
+
+    /// Sub-agent runs recorded so far, to be nested into the caller's trace
+    pub(crate) fn take_delegations(&self) -> Vec<SubAgentRun> {
+        self.delegations
+            .lock()
+            .map(|mut delegations| std::mem::take(&mut *delegations))
+            .unwrap_or_default()
+    }
+}
+
+/// Where progress reported by a tool goes
+#[derive(Debug, Clone, Default)]
+struct ProgressSink {
+    sender: Option<UnboundedSender<AgentStep>>,
+    recorded: Arc<Mutex<Vec<AgentStep>>>,
+}
+
+/// Per-run state shared by the contexts of every tool call in the run
+#[derive(Debug, Clone)]
+pub(crate) struct RunContext {
+    run_id: Arc<str>,
+    cancellation: CancellationToken,
+    user_data: UserData,
+    redactor: Option<Arc<Redactor>>,
+    spent: Arc<Mutex<Spent>>,
+}
+
+/// Tokens and iterations a run has used so far, kept for runs that end in an error
+#[derive(Debug, Default)]
+struct Spent {
+    tokens: Option<TokenUsage>,
+    iterations: usize,
+}
+
+impl RunContext {
+    pub(crate) fn new(cancellation: CancellationToken, user_data: UserData) -> Self {
+        Self {
+            run_id: Arc::from(new_run_id()),
+            cancellation,
+            user_data,
+            redactor: None,
+            spent: Arc::default(),
+        }
+    }
+
+    /// Continue the run id of the run that started this one, so their logs correlate
+    pub(crate) fn with_run_id(mut self, run_id: &str) -> Self {
+        self.run_id = Arc::from(run_id);
+        self
+    }
+
+    /// Mask secrets in the progress tools report during the run
+    pub(crate) fn with_redactor(mut self, redactor: Option<Arc<Redactor>>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    pub(crate) fn redactor(&self) -> Option<&Arc<Redactor>> {
+        self.redactor.as_ref()
+    }
+
+    pub(crate) fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub(crate) fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    pub(crate) fn record_iteration(&self) {
+        if let Ok(mut spent) = self.spent.lock() {
+            spent.iterations += 1;
+        }
+    }
+
+    pub(crate) fn record_usage(&self, usage: &TokenUsage) {
+        if let Ok(mut spent) = self.spent.lock() {
+            accumulate_usage(&mut spent.tokens, usage);
+        }
+    }
+
+    /// Tokens and iterations recorded so far
+    pub(crate) fn spent(&self) -> (Option<TokenUsage>, usize) {
+        self.spent
+            .lock()
+            .map(|spent| (spent.tokens.clone(), spent.iterations))
+            .unwrap_or_default()
+    }
+}
+
+/// Unique enough id for a run: time of day in milliseconds plus a process-wide counter
+fn new_run_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    format!(
+        "run_{:x}_{:x}",
+        millis,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Signal asking a run and its tools to stop early.
+///
+/// Clones share the same state, so keep one and hand another to
+/// [`Agent::run_with_cancellation`](crate::Agent::run_with_cancellation). Once cancelled a token
+/// stays cancelled; give each run a new one.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationState>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the token is cancelled, e.g. in a `tokio::select!` next to long-running work
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking so a cancel in between is not missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Values made available to every tool, looked up by type
+#[derive(Clone, Default)]
+pub struct UserData {
+    values: Arc<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl UserData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `value`, replacing any earlier value of the same type
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        Arc::make_mut(&mut self.values).insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl std::fmt::Debug for UserData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserData")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct ApiKey(&'static str);
+
+    #[test]
+    fn test_user_data_is_looked_up_by_type() {
+        let mut user_data = UserData::new();
+        user_data.insert(ApiKey("first"));
+        user_data.insert(ApiKey("second"));
+        user_data.insert(42u32);
+
+        let context = ToolContext::default().with_user_data(user_data);
+        assert_eq!(context.get::<ApiKey>(), Some(&ApiKey("second")));
+        assert_eq!(context.get::<u32>(), Some(&42));
+        assert!(context.get::<String>().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_wakes_waiters() {
+        let token = CancellationToken::new();
+        let waiter = tokio::spawn({
+            let token = token.clone();
+            async move { token.cancelled().await }
+        });
+
+        tokio::task::yield_now().await;
+        token.cancel();
+        waiter.await.unwrap();
+        assert!(token.is_cancelled());
+        // Already cancelled: returns immediately
+        token.cancelled().await;
+    }
+
+    #[test]
+    fn test_progress_is_sent_and_recorded() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let run = RunContext::new(CancellationToken::new(), UserData::new());
+        let context = ToolContext::for_call(&run, "call_1", 2, &[], Some(sender));
+
+        context.progress("halfway", Some(serde_json::json!({ "percent": 50 })));
+
+        assert!(matches!(
+            receiver.try_recv().unwrap(),
+            AgentStep::Progress { ref tool_call_id, .. } if tool_call_id == "call_1"
+        ));
+        assert_eq!(context.take_progress().len(), 1);
+        assert!(context.take_progress().is_empty());
+        assert!(context.run_id().starts_with("run_"));
+    }
+}
@@ -1,6 +1,13 @@
-use super::{tool::ToolRegistry, Tool};
-use crate::{AgentError, Result};
+use super::{
+    tool::{wire_name, ToolRegistry},
+    NamespacedTool, Tool, ToolContext, ToolResponse, ToolSelection,
+};
+use crate::{
+    mcp::{McpClient, McpServerConfig, McpTool},
+    AgentError, Result,
+};
 use serde_json::Value;
+use std::sync::Arc;
 
 /// Factory for creating and managing function/tool execution
 #[derive(Debug)]
@@ -16,19 +23,109 @@ impl FunctionFactory {
         }
     }
 
-    /// Register a tool with the factory
-    pub fn register_tool<T: Tool + 'static>(&mut self, tool: T) {
-        self.registry.register(tool);
+    /// Register a tool with the factory, failing if its name is already taken
+    pub fn register_tool<T: Tool + 'static>(&mut self, tool: T) -> Result<()> {
+        self.registry.register(tool)
+    }
+
+    /// Swap in a new implementation for a registered tool, returning the old one
+    pub fn replace_tool<T: Tool + 'static>(&mut self, tool: T) -> Result<Box<dyn Tool>> {
+        self.registry.replace(tool)
+    }
+
+    /// Remove a tool, returning it if it was registered
+    pub fn unregister_tool(&mut self, name: &str) -> Option<Box<dyn Tool>> {
+        self.registry.unregister(name)
+    }
+
+    /// Registered names of all tools
+    pub fn tool_names(&self) -> Vec<&str> {
+        self.registry.names()
+    }
+
+    /// Registered names and tools, in registration order
+    pub fn tools(&self) -> impl Iterator<Item = (&str, &dyn Tool)> {
+        self.registry.iter()
+    }
+
+    /// Get a tool by registered or function name
+    pub fn tool(&self, name: &str) -> Option<&dyn Tool> {
+        self.registry.get(name)
+    }
+
+    /// Register every tool exposed by a connected MCP server under `namespace` (e.g. `files`
+    /// gives `files.read_file`), returning their registered names.
+    ///
+    /// Fails with a `Config` error, registering nothing, if any name is already taken.
+    pub async fn register_mcp_client(
+        &mut self,
+        namespace: &str,
+        client: Arc<McpClient>,
+    ) -> Result<Vec<String>> {
+        let tools: Vec<NamespacedTool> = client
+            .list_tools()
+            .await?
+            .into_iter()
+            .map(|info| NamespacedTool::new(namespace, McpTool::new(client.clone(), info)))
+            .collect();
+
+        let mut names: Vec<String> = Vec::with_capacity(tools.len());
+        for tool in &tools {
+            let name = tool.name();
+            if let Some(existing) = self.registry.resolve(&wire_name(name)) {
+                return Err(AgentError::Config(format!(
+                    "MCP tool '{}' collides with the registered tool '{}'",
+                    name, existing
+                )));
+            }
+            if names
+                .iter()
+                .any(|other| wire_name(other) == wire_name(name))
+            {
+                return Err(AgentError::Config(format!(
+                    "MCP server '{}' lists the tool '{}' more than once",
+                    namespace, name
+                )));
+            }
+            names.push(name.to_string());
+        }
+
+        for tool in tools {
+            self.registry.register(tool)?;
+        }
+        Ok(names)
+    }
+
+    /// Connect to an MCP server and register all of its tools under `namespace`
+    pub async fn register_mcp_server(
+        &mut self,
+        namespace: &str,
+        config: &McpServerConfig,
+    ) -> Result<Vec<String>> {
+        let client = McpClient::connect(config).await?;
+        self.register_mcp_client(namespace, Arc::new(client)).await
     }
 
     /// Execute a function call by name
     pub async fn execute_function(&self, function_name: &str, parameters: Value) -> Result<Value> {
+        self.execute_function_with_context(function_name, parameters, ToolContext::default())
+            .await
+            .map(ToolResponse::into_value)
+    }
+
+    /// Execute a function call by name, passing `context` to the tool
+    pub async fn execute_function_with_context(
+        &self,
+        function_name: &str,
+        parameters: Value,
+        context: ToolContext,
+    ) -> Result<ToolResponse> {
         let tool = self
             .registry
             .get(function_name)
             .ok_or_else(|| AgentError::ToolNotFound(function_name.to_string()))?;
 
-        tool.execute(parameters).await
+        tool.execute_with_context(parameters, context).await
     }
 
     /// Get all available tools for OpenAI function calling
@@ -36,10 +133,23 @@ impl FunctionFactory {
         self.registry.to_openai_tools()
     }
 
+    /// Tools in `selection`, for OpenAI function calling
+    pub fn get_selected_openai_tools(&self, selection: &ToolSelection) -> Vec<Value> {
+        self.registry
+            .to_openai_tools_matching(|name| selection.allows(name))
+    }
+
     /// Check if a function exists
     pub fn has_function(&self, name: &str) -> bool {
         self.registry.get(name).is_some()
     }
+
+    /// Whether `name` (a registered or function name) is a tool in `selection`
+    pub fn is_selected(&self, name: &str, selection: &ToolSelection) -> bool {
+        self.registry
+            .resolve(name)
+            .is_some_and(|name| selection.allows(name))
+    }
 }
 
 impl Default for FunctionFactory {
@@ -1,13 +1,26 @@
 //! Tools module containing tool abstractions and built-in tools
 
 pub mod calculator;
+pub mod context;
 pub mod function_factory;
 pub mod jina;
+pub mod namespace;
+pub mod result;
+pub mod retrieval;
+pub mod selection;
+pub mod sub_agent;
 pub mod tool;
 pub mod weather;
 
 pub use calculator::CalculatorTool;
+pub use context::{CancellationToken, ToolContext, UserData};
 pub use function_factory::FunctionFactory;
 pub use jina::JinaReaderTool;
-pub use tool::{Tool, ToolRegistry};
+pub use namespace::NamespacedTool;
+pub use result::{Artifact, IntoToolResponse, ToolResponse, ToolResult};
+pub use retrieval::{Bm25Retriever, ToolDocument, ToolRetrieval, ToolRetriever};
+pub use selection::{ToolChoice, ToolSelection};
+pub use sub_agent::SubAgentTool;
+pub use tinyagent_macros::tool_attribute as tool;
+pub use tool::{tool_output, Tool, ToolRegistry};
 pub use weather::WeatherTool;
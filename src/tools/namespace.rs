@@ -0,0 +1,56 @@
+use super::{Tool, ToolContext, ToolResponse};
+use serde_json::Value;
+use std::{future::Future, pin::Pin};
+
+/// A tool registered under a namespace, e.g. `fetch` as `web.fetch`.
+///
+/// Namespaces keep tools from different sources (such as two MCP servers) from colliding and
+/// let a [`ToolSelection`](super::ToolSelection) pick them as a group (`web.*`).
+#[derive(Debug)]
+pub struct NamespacedTool {
+    name: String,
+    tool: Box<dyn Tool>,
+}
+
+impl NamespacedTool {
+    pub fn new<T: Tool + 'static>(namespace: &str, tool: T) -> Self {
+        Self {
+            name: format!("{}.{}", namespace, tool.name()),
+            tool: Box::new(tool),
+        }
+    }
+
+    /// The wrapped tool
+    pub fn inner(&self) -> &dyn Tool {
+        self.tool.as_ref()
+    }
+}
+
+impl Tool for NamespacedTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        self.tool.description()
+    }
+
+    fn parameters_schema(&self) -> Value {
+        self.tool.parameters_schema()
+    }
+
+    fn execute(
+        &self,
+        parameters: Value,
+    ) -> Pin<Box<dyn Future<Output = Result<Value, crate::AgentError>> + Send + '_>> {
+        self.tool.execute(parameters)
+    }
+
+    fn execute_with_context(
+        &self,
+        parameters: Value,
+        context: ToolContext,
+    ) -> Pin<Box<dyn Future<Output = Result<ToolResponse, crate::AgentError>> + Send + '_>> {
+        self.tool.execute_with_context(parameters, context)
+    }
+}
@@ -0,0 +1,213 @@
+use crate::types::content::{split_tool_attachments, ContentPart};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Output of a tool that separates what the model reads from what the caller keeps.
+///
+/// Only `content` (plus a one-line note per artifact) is sent to the model. `structured` and
+/// `artifacts` are kept on the [`AgentStep::Observation`](crate::AgentStep::Observation) of
+/// the call, so large or binary output never fills the context.
+///
+/// Tools return it from [`Tool::execute_with_context`](super::Tool::execute_with_context)
+/// (`#[tool]` functions simply return it) as a [`ToolResponse`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ToolResult {
+    /// Text shown to the model as the observation
+    pub content: String,
+    /// Data kept for the caller
+    pub structured: Option<Value>,
+    /// Files and images kept for the caller
+    pub artifacts: Vec<Artifact>,
+}
+
+impl ToolResult {
+    /// Result that only shows `content` to the model
+    pub fn text(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_structured(mut self, structured: Value) -> Self {
+        self.structured = Some(structured);
+        self
+    }
+
+    pub fn with_artifact(mut self, artifact: Artifact) -> Self {
+        self.artifacts.push(artifact);
+        self
+    }
+
+    /// Observation text sent to the model: the content and a note per artifact
+    pub fn model_content(&self) -> String {
+        if self.artifacts.is_empty() {
+            return self.content.clone();
+        }
+
+        let artifacts = self
+            .artifacts
+            .iter()
+            .map(Artifact::describe)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "{}\n[Artifacts kept outside the conversation: {}]",
+            self.content, artifacts
+        )
+    }
+}
+
+/// What a tool call produced: plain JSON, or a [`ToolResult`] that keeps data and artifacts
+/// away from the model
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolResponse {
+    /// JSON sent to the model as text; an `attachments` key of content parts is forwarded
+    /// separately (see [`with_attachments`](crate::types::content::with_attachments))
+    Value(Value),
+    Result(ToolResult),
+}
+
+impl ToolResponse {
+    /// The output as JSON, e.g. for callers of [`Tool::execute`](super::Tool::execute)
+    pub fn into_value(self) -> Value {
+        match self {
+            ToolResponse::Value(value) => value,
+            ToolResponse::Result(result) => {
+                let mut value = json!({ "content": result.content });
+                if let Some(structured) = result.structured {
+                    value["structured"] = structured;
+                }
+                if !result.artifacts.is_empty() {
+                    value["artifacts"] = serde_json::to_value(result.artifacts).unwrap_or_default();
+                }
+                value
+            }
+        }
+    }
+
+    /// Observation text for the model, attachments to forward to it, and the [`ToolResult`]
+    /// whose data and artifacts the caller keeps
+    pub(crate) fn into_observation(self) -> (String, Vec<ContentPart>, Option<ToolResult>) {
+        match self {
+            ToolResponse::Value(value) => {
+                let (value, attachments) = split_tool_attachments(value);
+                (value.to_string(), attachments, None)
+            }
+            ToolResponse::Result(result) => (result.model_content(), Vec::new(), Some(result)),
+        }
+    }
+}
+
+impl From<Value> for ToolResponse {
+    fn from(value: Value) -> Self {
+        ToolResponse::Value(value)
+    }
+}
+
+impl From<ToolResult> for ToolResponse {
+    fn from(result: ToolResult) -> Self {
+        ToolResponse::Result(result)
+    }
+}
+
+/// Return values of `#[tool]` functions: a [`ToolResult`] as it is, anything else serialized
+pub trait IntoToolResponse {
+    fn into_tool_response(self) -> crate::Result<ToolResponse>;
+}
+
+impl IntoToolResponse for ToolResult {
+    fn into_tool_response(self) -> crate::Result<ToolResponse> {
+        Ok(ToolResponse::Result(self))
+    }
+}
+
+impl<T: Serialize> IntoToolResponse for T {
+    fn into_tool_response(self) -> crate::Result<ToolResponse> {
+        Ok(ToolResponse::Value(serde_json::to_value(self)?))
+    }
+}
+
+/// File produced by a tool, kept for the caller instead of being sent to the model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub name: String,
+    pub media_type: String,
+    /// Base64-encoded contents
+    pub data: String,
+}
+
+impl Artifact {
+    /// Create an artifact from raw bytes
+    pub fn from_bytes(
+        name: impl Into<String>,
+        media_type: impl Into<String>,
+        bytes: &[u8],
+    ) -> Self {
+        Self {
+            name: name.into(),
+            media_type: media_type.into(),
+            data: STANDARD.encode(bytes),
+        }
+    }
+
+    /// Decoded contents
+    pub fn bytes(&self) -> crate::Result<Vec<u8>> {
+        STANDARD.decode(&self.data).map_err(|err| {
+            crate::AgentError::Validation(format!(
+                "Artifact '{}' is not valid base64: {}",
+                self.name, err
+            ))
+        })
+    }
+
+    /// Name, media type and size, e.g. `report.pdf (application/pdf, 1024 bytes)`
+    pub fn describe(&self) -> String {
+        // Four base64 characters encode three bytes
+        let size = self.data.trim_end_matches('=').len() * 3 / 4;
+        format!("{} ({}, {} bytes)", self.name, self.media_type, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_tool_result_output_keeps_data_away_from_the_model() {
+        let result = ToolResult::text("3 rows")
+            .with_structured(json!({ "rows": [1, 2, 3] }))
+            .with_artifact(Artifact::from_bytes(
+                "rows.csv",
+                "text/csv",
+                b"a\n1\n2\n3\n",
+            ));
+
+        let (observation, attachments, unpacked) =
+            ToolResponse::from(result.clone()).into_observation();
+        assert_eq!(unpacked, Some(result));
+        assert!(attachments.is_empty());
+        assert_eq!(
+            observation,
+            "3 rows\n[Artifacts kept outside the conversation: rows.csv (text/csv, 8 bytes)]"
+        );
+    }
+
+    #[test]
+    fn test_json_output_is_sent_as_text() {
+        // A key named like the old envelope is just data
+        let plain = json!({ "tool_result": { "content": "x" } });
+        let (observation, _, unpacked) = ToolResponse::from(plain.clone()).into_observation();
+        assert_eq!(observation, plain.to_string());
+        assert!(unpacked.is_none());
+    }
+
+    #[test]
+    fn test_artifact_bytes_decode() {
+        let artifact = Artifact::from_bytes("chart.png", "image/png", &[1, 2, 3, 4]);
+        assert_eq!(artifact.bytes().unwrap(), vec![1, 2, 3, 4]);
+        assert_eq!(artifact.describe(), "chart.png (image/png, 4 bytes)");
+    }
+}
@@ -0,0 +1,330 @@
+use super::{tool::wire_name, FunctionFactory, Tool, ToolSelection};
+use crate::{AgentError, Result};
+use serde_json::{json, Value};
+use std::{collections::HashMap, fmt, future::Future, pin::Pin, sync::Arc};
+
+/// Name of the meta-tool the model calls to find tools that were not offered
+pub const SEARCH_TOOLS_NAME: &str = "search_tools";
+
+/// What a [`ToolRetriever`] sees of a registered tool
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolDocument {
+    /// Registered name, e.g. `web.fetch`
+    pub name: String,
+    pub description: String,
+    /// Top-level parameters as `(name, description)` pairs
+    pub parameters: Vec<(String, String)>,
+}
+
+impl ToolDocument {
+    pub fn from_tool(name: &str, tool: &dyn Tool) -> Self {
+        let schema = tool.parameters_schema();
+        let parameters = schema
+            .get("properties")
+            .and_then(|properties| properties.as_object())
+            .map(|properties| {
+                properties
+                    .iter()
+                    .map(|(name, property)| {
+                        let description = property
+                            .get("description")
+                            .and_then(|description| description.as_str())
+                            .unwrap_or_default();
+                        (name.clone(), description.to_string())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            name: name.to_string(),
+            description: tool.description().to_string(),
+            parameters,
+        }
+    }
+
+    /// Name, description and parameter docs as one searchable text
+    pub fn text(&self) -> String {
+        let mut text = format!("{} {}", self.name, self.description);
+        for (name, description) in &self.parameters {
+            text.push(' ');
+            text.push_str(name);
+            text.push(' ');
+            text.push_str(description);
+        }
+        text
+    }
+}
+
+/// Scores tools against a query; implement this to rank with embeddings instead of keywords
+pub trait ToolRetriever: Send + Sync + fmt::Debug {
+    /// One score per document, higher is more relevant; tools scoring 0 or less are never offered
+    fn score<'a>(
+        &'a self,
+        query: &'a str,
+        documents: &'a [ToolDocument],
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<f64>>> + Send + 'a>>;
+}
+
+/// Okapi BM25 keyword ranking over [`ToolDocument::text`]
+#[derive(Debug, Clone)]
+pub struct Bm25Retriever {
+    k1: f64,
+    b: f64,
+}
+
+impl Bm25Retriever {
+    pub fn new() -> Self {
+        Self { k1: 1.2, b: 0.75 }
+    }
+
+    /// Tune term-frequency saturation (`k1`) and length normalization (`b`)
+    pub fn with_parameters(mut self, k1: f64, b: f64) -> Self {
+        self.k1 = k1;
+        self.b = b;
+        self
+    }
+
+    fn scores(&self, query: &str, documents: &[ToolDocument]) -> Vec<f64> {
+        let documents: Vec<Vec<String>> = documents
+            .iter()
+            .map(|document| tokenize(&document.text()))
+            .collect();
+        if documents.is_empty() {
+            return Vec::new();
+        }
+
+        let count = documents.len() as f64;
+        let average_length = documents.iter().map(Vec::len).sum::<usize>() as f64 / count;
+        let mut query_terms = tokenize(query);
+        query_terms.sort();
+        query_terms.dedup();
+
+        let document_frequency: HashMap<&str, f64> = query_terms
+            .iter()
+            .map(|term| {
+                let frequency = documents
+                    .iter()
+                    .filter(|tokens| tokens.contains(term))
+                    .count();
+                (term.as_str(), frequency as f64)
+            })
+            .collect();
+
+        documents
+            .iter()
+            .map(|tokens| {
+                let length = tokens.len() as f64;
+                query_terms
+                    .iter()
+                    .map(|term| {
+                        let frequency = tokens.iter().filter(|token| *token == term).count() as f64;
+                        if frequency == 0.0 {
+                            return 0.0;
+                        }
+                        let containing = document_frequency[term.as_str()];
+                        let idf = ((count - containing + 0.5) / (containing + 0.5) + 1.0).ln();
+                        let norm = 1.0 - self.b + self.b * length / average_length.max(1.0);
+                        idf * frequency * (self.k1 + 1.0) / (frequency + self.k1 * norm)
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+impl Default for Bm25Retriever {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolRetriever for Bm25Retriever {
+    fn score<'a>(
+        &'a self,
+        query: &'a str,
+        documents: &'a [ToolDocument],
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<f64>>> + Send + 'a>> {
+        Box::pin(async move { Ok(self.scores(query, documents)) })
+    }
+}
+
+/// Lowercase words, splitting `snake_case`, dotted and `camelCase` names
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut previous_lowercase = false;
+
+    for character in text.chars() {
+        if !character.is_alphanumeric() {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            previous_lowercase = false;
+            continue;
+        }
+        if character.is_uppercase() && previous_lowercase {
+            tokens.push(std::mem::take(&mut current));
+        }
+        previous_lowercase = character.is_lowercase();
+        current.extend(character.to_lowercase());
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// Offer only the tools most relevant to the task instead of the whole catalog.
+///
+/// Before the first request the task is ranked against every registered tool (by keyword with
+/// [`Bm25Retriever`] unless another [`ToolRetriever`] is set) and the top `top_k` are offered.
+/// Unless disabled, the model can also call `search_tools` to find more, which become callable
+/// on its next turn.
+#[derive(Clone)]
+pub struct ToolRetrieval {
+    top_k: usize,
+    retriever: Arc<dyn ToolRetriever>,
+    search_tool: bool,
+}
+
+impl ToolRetrieval {
+    pub fn new(top_k: usize) -> Self {
+        Self {
+            top_k,
+            retriever: Arc::new(Bm25Retriever::new()),
+            search_tool: true,
+        }
+    }
+
+    pub fn with_retriever(mut self, retriever: impl ToolRetriever + 'static) -> Self {
+        self.retriever = Arc::new(retriever);
+        self
+    }
+
+    /// Offer the `search_tools` meta-tool (on by default)
+    pub fn with_search_tool(mut self, search_tool: bool) -> Self {
+        self.search_tool = search_tool;
+        self
+    }
+
+    pub fn top_k(&self) -> usize {
+        self.top_k
+    }
+
+    pub fn search_tool(&self) -> bool {
+        self.search_tool
+    }
+
+    /// Registered names of the `top_k` tools in `candidates` most relevant to `query`
+    pub async fn retrieve(
+        &self,
+        factory: &FunctionFactory,
+        query: &str,
+        candidates: &ToolSelection,
+    ) -> Result<Vec<String>> {
+        let documents: Vec<ToolDocument> = factory
+            .tools()
+            .filter(|(name, _)| candidates.allows(name))
+            .map(|(name, tool)| ToolDocument::from_tool(name, tool))
+            .collect();
+        let scores = self.retriever.score(query, &documents).await?;
+        if scores.len() != documents.len() {
+            return Err(AgentError::Validation(format!(
+                "Tool retriever returned {} scores for {} tools",
+                scores.len(),
+                documents.len()
+            )));
+        }
+
+        let mut ranked: Vec<(f64, ToolDocument)> = scores
+            .into_iter()
+            .zip(documents)
+            .filter(|(score, _)| *score > 0.0)
+            .collect();
+        // Stable, so equally relevant tools keep their registration order
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        Ok(ranked
+            .into_iter()
+            .take(self.top_k)
+            .map(|(_, document)| document.name)
+            .collect())
+    }
+}
+
+impl fmt::Debug for ToolRetrieval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ToolRetrieval")
+            .field("top_k", &self.top_k)
+            .field("retriever", &self.retriever)
+            .field("search_tool", &self.search_tool)
+            .finish()
+    }
+}
+
+/// Tool definition for the `search_tools` meta-tool
+pub(crate) fn search_tools_definition() -> Value {
+    json!({
+        "type": "function",
+        "function": {
+            "name": SEARCH_TOOLS_NAME,
+            "description": "Search for more tools when none of the offered tools fit. Matching tools can be called from your next turn.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "What the tool should do, e.g. \"convert currencies\""
+                    }
+                },
+                "required": ["query"]
+            }
+        }
+    })
+}
+
+/// Observation for a `search_tools` call: the tools found, by the name the model calls
+pub(crate) fn search_tools_observation(factory: &FunctionFactory, names: &[String]) -> Value {
+    let tools: Vec<Value> = names
+        .iter()
+        .filter_map(|name| {
+            let tool = factory.tool(name)?;
+            Some(json!({ "name": wire_name(name), "description": tool.description() }))
+        })
+        .collect();
+    json!({ "tools": tools })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(name: &str, description: &str) -> ToolDocument {
+        ToolDocument {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_tokenize_splits_identifiers() {
+        assert_eq!(
+            tokenize("web.fetchPage get_weather, IDs"),
+            ["web", "fetch", "page", "get", "weather", "ids"]
+        );
+    }
+
+    #[test]
+    fn test_bm25_ranks_matching_tools_first() {
+        let documents = [
+            document("calculator", "Perform arithmetic on two numbers"),
+            document("weather", "Get the current weather for a city"),
+            document("web.fetch", "Fetch a web page and return its text"),
+        ];
+        let scores = Bm25Retriever::new().scores("what is the weather in Oslo", &documents);
+
+        assert_eq!(scores[0], 0.0);
+        assert!(scores[1] > scores[2]);
+    }
+}
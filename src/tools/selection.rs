@@ -0,0 +1,137 @@
+use super::tool::wire_name;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Which registered tools are offered to the model.
+///
+/// Entries are registered tool names (`math.calc`) or whole namespaces (`web.*`). Selections
+/// combined with [`ToolSelection::and`] only allow tools every part allows.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolSelection {
+    allowlists: Vec<Vec<String>>,
+}
+
+impl ToolSelection {
+    /// Every registered tool
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Only the listed tools and namespaces
+    pub fn only<I, S>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowlists: vec![entries.into_iter().map(Into::into).collect()],
+        }
+    }
+
+    /// Tools allowed by both selections
+    pub fn and(mut self, other: ToolSelection) -> Self {
+        self.allowlists.extend(other.allowlists);
+        self
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.allowlists.is_empty()
+    }
+
+    /// Whether the tool registered as `name` is selected
+    pub fn allows(&self, name: &str) -> bool {
+        self.allowlists
+            .iter()
+            .all(|entries| entries.iter().any(|entry| entry_matches(entry, name)))
+    }
+}
+
+/// Whether (and which) tool the model must call, sent as the request's `tool_choice`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides
+    #[default]
+    Auto,
+    /// The model must call one of the offered tools
+    Required,
+    /// The model must answer without calling a tool
+    None,
+    /// The model must call this tool (registered name, or `final_answer`)
+    Tool(String),
+}
+
+impl ToolChoice {
+    /// Force a call to the tool registered as `name`
+    pub fn tool(name: impl Into<String>) -> Self {
+        Self::Tool(name.into())
+    }
+
+    /// Function name the model must call, if any
+    pub(crate) fn forced_function(&self) -> Option<String> {
+        match self {
+            Self::Tool(name) => Some(wire_name(name)),
+            _ => None,
+        }
+    }
+
+    /// The `tool_choice` value of a chat completion request
+    pub(crate) fn to_openai(&self) -> Value {
+        match self {
+            Self::Auto => json!("auto"),
+            Self::Required => json!("required"),
+            Self::None => json!("none"),
+            Self::Tool(name) => json!({
+                "type": "function",
+                "function": { "name": wire_name(name) }
+            }),
+        }
+    }
+}
+
+fn entry_matches(entry: &str, name: &str) -> bool {
+    match entry.strip_suffix(".*") {
+        Some(namespace) => name
+            .strip_prefix(namespace)
+            .is_some_and(|rest| rest.starts_with('.')),
+        None => entry == name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_matches_names_and_namespaces() {
+        let selection = ToolSelection::only(["web.*", "math.calc"]);
+        assert!(selection.allows("web.fetch"));
+        assert!(selection.allows("web.search.news"));
+        assert!(selection.allows("math.calc"));
+        assert!(!selection.allows("math.plot"));
+        assert!(!selection.allows("webhook.send"));
+        assert!(ToolSelection::all().allows("anything"));
+    }
+
+    #[test]
+    fn test_tool_choice_uses_function_names() {
+        assert_eq!(ToolChoice::Required.to_openai(), json!("required"));
+        assert_eq!(
+            ToolChoice::tool("web.fetch").to_openai(),
+            json!({ "type": "function", "function": { "name": "web__fetch" } })
+        );
+    }
+
+    #[test]
+    fn test_combined_selections_intersect() {
+        let selection = ToolSelection::only(["web.*", "calculator"]).and(ToolSelection::only([
+            "web.fetch",
+            "calculator",
+            "weather",
+        ]));
+        assert!(selection.allows("web.fetch"));
+        assert!(selection.allows("calculator"));
+        assert!(!selection.allows("web.search"));
+        assert!(!selection.allows("weather"));
+    }
+}
@@ -0,0 +1,146 @@
+use super::{result::ToolResponse, Tool, ToolContext};
+use crate::{core::Agent, types::result::RunResult, AgentError};
+use serde_json::{json, Value};
+use std::{pin::Pin, sync::Arc};
+
+/// Default number of nested delegation levels allowed below the root agent
+pub const DEFAULT_MAX_DEPTH: usize = 3;
+
+tokio::task_local! {
+    static DELEGATION_DEPTH: usize;
+}
+
+/// Wraps an [`Agent`] as a tool so a parent agent can delegate a task to it.
+///
+/// The sub-agent keeps its own tools, model and completion schema. The parent sees the
+/// structured payload (or `{"answer": ...}`) as the observation, while the full child run is
+/// nested into the parent's trace as an [`AgentStep::Delegation`](crate::AgentStep::Delegation)
+/// and its token usage is added to the parent's total. A child that fails is nested too, with
+/// the steps it got through. The child shares the parent run's cancellation token and run id.
+#[derive(Debug, Clone)]
+pub struct SubAgentTool {
+    name: String,
+    description: String,
+    agent: Arc<Agent>,
+    max_depth: usize,
+}
+
+impl SubAgentTool {
+    /// Expose `agent` to a parent agent as the tool `name`
+    pub fn new(name: impl Into<String>, description: impl Into<String>, agent: Agent) -> Self {
+        Self::from_shared(name, description, Arc::new(agent))
+    }
+
+    /// Expose an agent that is also used elsewhere
+    pub fn from_shared(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        agent: Arc<Agent>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            agent,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Refuse to run when this many sub-agents are already active above this one
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Delegation depth of the sub-agent currently running on this task (0 at the root)
+    pub fn current_depth() -> usize {
+        DELEGATION_DEPTH.try_with(|depth| *depth).unwrap_or(0)
+    }
+}
+
+impl Tool for SubAgentTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "task": {
+                    "type": "string",
+                    "description": "Self-contained description of the task to delegate"
+                }
+            },
+            "required": ["task"]
+        })
+    }
+
+    fn execute(
+        &self,
+        parameters: Value,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<Value, AgentError>> + Send + '_>> {
+        Box::pin(async move {
+            self.execute_with_context(parameters, ToolContext::default())
+                .await
+                .map(ToolResponse::into_value)
+        })
+    }
+
+    fn execute_with_context(
+        &self,
+        parameters: Value,
+        context: ToolContext,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<ToolResponse, AgentError>> + Send + '_>>
+    {
+        Box::pin(async move {
+            let task = parameters
+                .get("task")
+                .and_then(|task| task.as_str())
+                .ok_or_else(|| {
+                    AgentError::InvalidFunctionCall(format!(
+                        "Tool '{}' requires a `task` string",
+                        self.name
+                    ))
+                })?
+                .to_string();
+
+            let depth = Self::current_depth() + 1;
+            if depth > self.max_depth {
+                return Err(AgentError::ToolExecution(format!(
+                    "Sub-agent '{}' exceeds the maximum delegation depth of {}",
+                    self.name, self.max_depth
+                )));
+            }
+
+            let (run, error) = DELEGATION_DEPTH
+                .scope(depth, self.agent.run_delegated(&task, &context))
+                .await;
+            let observation = match &run.structured {
+                Some(structured) if structured.is_object() => structured.clone(),
+                _ => json!({ "answer": run.output }),
+            };
+            // Nested whether or not the sub-agent finished, so its steps and usage are kept
+            context.record_delegation(SubAgentRun {
+                agent: self.name.clone(),
+                depth,
+                run,
+            });
+            match error {
+                Some(error) => Err(error),
+                None => Ok(ToolResponse::Value(observation)),
+            }
+        })
+    }
+}
+
+/// Run of a sub-agent, handed to the calling loop through its [`ToolContext`]
+#[derive(Debug)]
+pub(crate) struct SubAgentRun {
+    pub agent: String,
+    pub depth: usize,
+    pub run: RunResult,
+}
@@ -1,12 +1,16 @@
-use std::collections::HashMap;
+use super::{
+    result::{IntoToolResponse, ToolResponse},
+    ToolContext,
+};
+use crate::AgentError;
 
 /// A tool that can be executed by the agent
 pub trait Tool: Send + Sync + std::fmt::Debug {
     /// The name of the tool (used in function calls)
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
 
     /// A description of what the tool does
-    fn description(&self) -> &'static str;
+    fn description(&self) -> &str;
 
     /// JSON Schema for the tool's parameters
     fn parameters_schema(&self) -> serde_json::Value;
@@ -22,12 +26,71 @@ pub trait Tool: Send + Sync + std::fmt::Debug {
                 + '_,
         >,
     >;
+
+    /// Execute the tool with access to the run that called it.
+    ///
+    /// The agent loop always calls this; the default ignores the context and calls
+    /// [`Tool::execute`]. Tools that need the context, or return a
+    /// [`ToolResult`](super::ToolResult), override this and implement `execute` with
+    /// `self.execute_with_context(parameters, ToolContext::default())` and
+    /// [`ToolResponse::into_value`].
+    fn execute_with_context(
+        &self,
+        parameters: serde_json::Value,
+        _context: ToolContext,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<ToolResponse, crate::AgentError>> + Send + '_>,
+    > {
+        Box::pin(async move { self.execute(parameters).await.map(ToolResponse::from) })
+    }
 }
 
-/// Registry for available tools
+/// Convert the result of a `#[tool]` function into a tool output.
+///
+/// A [`ToolResult`](super::ToolResult) is kept as it is and any other value is serialized. A
+/// [`ToolError`](crate::ToolError) or [`AgentError`](crate::AgentError) is passed on as it
+/// is, so its kind decides how the run reacts; any other error becomes a tool execution error.
+pub fn tool_output<T, E>(result: Result<T, E>) -> Result<ToolResponse, crate::AgentError>
+where
+    T: IntoToolResponse,
+    E: std::fmt::Display + 'static,
+{
+    result.map_err(into_agent_error)?.into_tool_response()
+}
+
+fn into_agent_error<E: std::fmt::Display + 'static>(err: E) -> crate::AgentError {
+    // `Option` lets the error be moved out through `&mut dyn Any`
+    let mut slot = Some(err);
+    let any: &mut dyn std::any::Any = &mut slot;
+    if let Some(error) = any
+        .downcast_mut::<Option<crate::ToolError>>()
+        .and_then(Option::take)
+    {
+        return crate::AgentError::ToolFailed(error);
+    }
+    if let Some(error) = any
+        .downcast_mut::<Option<crate::AgentError>>()
+        .and_then(Option::take)
+    {
+        return error;
+    }
+    match slot {
+        Some(err) => crate::AgentError::ToolExecution(err.to_string()),
+        None => crate::AgentError::Unknown("tool error was already taken".to_string()),
+    }
+}
+
+/// Registry for available tools.
+///
+/// Tools are keyed by their name, which may be qualified with a namespace (`web.fetch`, see
+/// [`NamespacedTool`](super::NamespacedTool)). Function names sent to the model cannot contain
+/// dots, so they use `__` instead (`web__fetch`); lookups accept either form.
+///
+/// Tools are kept in registration order, so the tools sent to the model are the same on every
+/// run (which keeps provider prompt caches warm and recorded requests reproducible).
 #[derive(Debug, Default)]
 pub struct ToolRegistry {
-    tools: HashMap<String, Box<dyn Tool>>,
+    tools: Vec<(String, Box<dyn Tool>)>,
 }
 
 impl ToolRegistry {
@@ -36,30 +99,86 @@ impl ToolRegistry {
         Self::default()
     }
 
-    /// Register a tool
-    pub fn register<T: Tool + 'static>(&mut self, tool: T) {
-        self.tools.insert(tool.name().to_string(), Box::new(tool));
+    /// Register a tool, failing with a `Config` error if its name is already taken.
+    ///
+    /// A tool whose function name matches another tool's (`web__fetch` next to `web.fetch`)
+    /// is refused too, since the model could not tell the two apart. Use
+    /// [`ToolRegistry::replace`] to swap out a registered tool.
+    pub fn register<T: Tool + 'static>(&mut self, tool: T) -> crate::Result<()> {
+        let name = tool.name().to_string();
+        if let Some(existing) = self.colliding(&name) {
+            return Err(AgentError::Config(format!(
+                "Tool '{}' collides with the registered tool '{}'",
+                name, existing
+            )));
+        }
+        self.tools.push((name, Box::new(tool)));
+        Ok(())
     }
 
-    /// Get a tool by name
+    /// Swap in a new implementation for a registered tool, returning the old one
+    pub fn replace<T: Tool + 'static>(&mut self, tool: T) -> crate::Result<Box<dyn Tool>> {
+        let name = tool.name().to_string();
+        let index = self
+            .exact_position(&name)
+            .ok_or_else(|| AgentError::ToolNotFound(name.clone()))?;
+        let (_, replaced) = std::mem::replace(&mut self.tools[index], (name, Box::new(tool)));
+        Ok(replaced)
+    }
+
+    /// Remove a tool by registered or function name, returning it if it was registered
+    pub fn unregister(&mut self, name: &str) -> Option<Box<dyn Tool>> {
+        let index = self.position(name)?;
+        Some(self.tools.remove(index).1)
+    }
+
+    /// Registered name of the tool called `name`, which may also be its function name
+    pub fn resolve(&self, name: &str) -> Option<&str> {
+        self.position(name)
+            .map(|index| self.tools[index].0.as_str())
+    }
+
+    /// Get a tool by registered or function name
     pub fn get(&self, name: &str) -> Option<&dyn Tool> {
-        self.tools.get(name).map(|tool| tool.as_ref())
+        self.position(name)
+            .map(|index| self.tools[index].1.as_ref())
     }
 
-    /// Get all registered tools
+    /// Get all registered tools, in registration order
     pub fn list(&self) -> Vec<&dyn Tool> {
-        self.tools.values().map(|tool| tool.as_ref()).collect()
+        self.tools.iter().map(|(_, tool)| tool.as_ref()).collect()
+    }
+
+    /// Registered names and tools, in registration order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &dyn Tool)> {
+        self.tools
+            .iter()
+            .map(|(name, tool)| (name.as_str(), tool.as_ref()))
+    }
+
+    /// Registered names of all tools, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        self.tools.iter().map(|(name, _)| name.as_str()).collect()
     }
 
     /// Generate tool schemas for OpenAI function calling
     pub fn to_openai_tools(&self) -> Vec<serde_json::Value> {
+        self.to_openai_tools_matching(|_| true)
+    }
+
+    /// Generate tool schemas for the tools whose registered name passes `filter`
+    pub fn to_openai_tools_matching(
+        &self,
+        filter: impl Fn(&str) -> bool,
+    ) -> Vec<serde_json::Value> {
         self.tools
-            .values()
-            .map(|tool| {
+            .iter()
+            .filter(|(name, _)| filter(name))
+            .map(|(name, tool)| {
                 serde_json::json!({
                     "type": "function",
                     "function": {
-                        "name": tool.name(),
+                        "name": wire_name(name),
                         "description": tool.description(),
                         "parameters": tool.parameters_schema()
                     }
@@ -67,4 +186,36 @@ impl ToolRegistry {
             })
             .collect()
     }
+
+    /// Index of the tool registered under exactly `name`
+    fn exact_position(&self, name: &str) -> Option<usize> {
+        self.tools
+            .iter()
+            .position(|(registered, _)| registered == name)
+    }
+
+    /// Registered name of a tool that `name` would share a function name with
+    fn colliding(&self, name: &str) -> Option<&str> {
+        let wire = wire_name(name);
+        self.tools
+            .iter()
+            .find(|(registered, _)| wire_name(registered) == wire)
+            .map(|(registered, _)| registered.as_str())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.tools
+            .iter()
+            .position(|(registered, _)| registered == name)
+            .or_else(|| {
+                self.tools
+                    .iter()
+                    .position(|(registered, _)| wire_name(registered) == name)
+            })
+    }
+}
+
+/// Function name sent to the model for a registered tool name
+pub(crate) fn wire_name(name: &str) -> String {
+    name.replace('.', "__")
 }
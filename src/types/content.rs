@@ -0,0 +1,291 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Key a tool result uses to hand back images or files alongside its JSON output
+pub const ATTACHMENTS_KEY: &str = "attachments";
+
+/// A non-text (or additional text) part of a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    /// Plain text
+    Text { text: String },
+    /// Image referenced by URL
+    ImageUrl {
+        url: String,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+    /// Inline base64-encoded image
+    Image { media_type: String, data: String },
+    /// Inline base64-encoded file (e.g. a PDF)
+    File {
+        filename: String,
+        media_type: String,
+        data: String,
+    },
+}
+
+impl ContentPart {
+    /// Create a text part
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    /// Create an image part that references a URL
+    pub fn image_url(url: impl Into<String>) -> Self {
+        ContentPart::ImageUrl {
+            url: url.into(),
+            detail: None,
+        }
+    }
+
+    /// Create an image part from base64 data that is already encoded
+    pub fn image_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        ContentPart::Image {
+            media_type: media_type.into(),
+            data: data.into(),
+        }
+    }
+
+    /// Create an image part from raw bytes
+    pub fn image_bytes(media_type: impl Into<String>, bytes: &[u8]) -> Self {
+        Self::image_base64(media_type, STANDARD.encode(bytes))
+    }
+
+    /// Create a file attachment from raw bytes
+    pub fn file_bytes(
+        filename: impl Into<String>,
+        media_type: impl Into<String>,
+        bytes: &[u8],
+    ) -> Self {
+        ContentPart::File {
+            filename: filename.into(),
+            media_type: media_type.into(),
+            data: STANDARD.encode(bytes),
+        }
+    }
+
+    /// Set the detail level for URL images (`low`, `high`, `auto`)
+    pub fn with_detail(mut self, level: impl Into<String>) -> Self {
+        if let ContentPart::ImageUrl { detail, .. } = &mut self {
+            *detail = Some(level.into());
+        }
+        self
+    }
+
+    /// Serialize this part as an OpenAI chat completions content part (also used by OpenRouter
+    /// and most compatible APIs)
+    pub fn to_openai_value(&self) -> Value {
+        match self {
+            ContentPart::Text { text } => json!({ "type": "text", "text": text }),
+            ContentPart::ImageUrl { url, detail } => {
+                let mut image_url = json!({ "url": url });
+                if let Some(detail) = detail {
+                    image_url["detail"] = json!(detail);
+                }
+                json!({ "type": "image_url", "image_url": image_url })
+            }
+            ContentPart::Image { media_type, data } => json!({
+                "type": "image_url",
+                "image_url": { "url": format!("data:{};base64,{}", media_type, data) }
+            }),
+            ContentPart::File {
+                filename,
+                media_type,
+                data,
+            } => json!({
+                "type": "file",
+                "file": {
+                    "filename": filename,
+                    "file_data": format!("data:{};base64,{}", media_type, data)
+                }
+            }),
+        }
+    }
+
+    /// Parse an OpenAI-format content part back into a `ContentPart`
+    pub fn from_openai_value(value: &Value) -> Option<Self> {
+        match value.get("type")?.as_str()? {
+            "text" => Some(Self::text(value.get("text")?.as_str()?)),
+            "image_url" => {
+                let image_url = value.get("image_url")?;
+                let url = image_url.get("url")?.as_str()?;
+                if let Some((media_type, data)) = parse_data_url(url) {
+                    return Some(Self::image_base64(media_type, data));
+                }
+                let detail = image_url
+                    .get("detail")
+                    .and_then(|d| d.as_str())
+                    .map(str::to_string);
+                Some(ContentPart::ImageUrl {
+                    url: url.to_string(),
+                    detail,
+                })
+            }
+            "file" => {
+                let file = value.get("file")?;
+                let (media_type, data) = parse_data_url(file.get("file_data")?.as_str()?)?;
+                Some(ContentPart::File {
+                    filename: file
+                        .get("filename")
+                        .and_then(|f| f.as_str())
+                        .unwrap_or("file")
+                        .to_string(),
+                    media_type: media_type.to_string(),
+                    data: data.to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Short human-readable summary that never includes inline data
+    pub fn describe(&self) -> String {
+        match self {
+            ContentPart::Text { text } => format!("text ({} chars)", text.chars().count()),
+            ContentPart::ImageUrl { url, .. } => format!("image {}", url),
+            ContentPart::Image { media_type, data } => {
+                format!("image {} ({} bytes base64)", media_type, data.len())
+            }
+            ContentPart::File {
+                filename,
+                media_type,
+                ..
+            } => format!("file {} ({})", filename, media_type),
+        }
+    }
+}
+
+fn parse_data_url(url: &str) -> Option<(&str, &str)> {
+    let rest = url.strip_prefix("data:")?;
+    let (media_type, data) = rest.split_once(";base64,")?;
+    Some((media_type, data))
+}
+
+/// Build message content from text plus optional parts.
+///
+/// Returns a plain string when there are no parts so text-only requests stay unchanged.
+pub fn message_content(text: &str, parts: &[ContentPart]) -> Value {
+    if parts.is_empty() {
+        return Value::String(text.to_string());
+    }
+
+    let mut content = Vec::with_capacity(parts.len() + 1);
+    if !text.is_empty() {
+        content.push(ContentPart::text(text).to_openai_value());
+    }
+    content.extend(parts.iter().map(ContentPart::to_openai_value));
+    Value::Array(content)
+}
+
+/// Split message content (string or array of parts) into its text and non-text parts
+pub(crate) fn split_message_content(content: &Value) -> Option<(String, Vec<ContentPart>)> {
+    match content {
+        Value::String(text) => Some((text.clone(), Vec::new())),
+        Value::Array(items) => {
+            let mut texts = Vec::new();
+            let mut parts = Vec::new();
+            for part in items.iter().filter_map(ContentPart::from_openai_value) {
+                match part {
+                    ContentPart::Text { text } => texts.push(text),
+                    other => parts.push(other),
+                }
+            }
+            Some((texts.join("\n"), parts))
+        }
+        _ => None,
+    }
+}
+
+/// Remove attachments from a tool result so only the JSON text is sent in the tool message.
+///
+/// The `attachments` key is only treated as content parts when every entry parses as one.
+pub fn split_tool_attachments(mut result: Value) -> (Value, Vec<ContentPart>) {
+    let Some(object) = result.as_object_mut() else {
+        return (result, Vec::new());
+    };
+
+    let parts = match object.get(ATTACHMENTS_KEY) {
+        Some(raw) => match serde_json::from_value::<Vec<ContentPart>>(raw.clone()) {
+            Ok(parts) => parts,
+            Err(_) => return (result, Vec::new()),
+        },
+        None => return (result, Vec::new()),
+    };
+
+    object.remove(ATTACHMENTS_KEY);
+    (result, parts)
+}
+
+/// Attach content parts to a tool result so they are forwarded to the model
+pub fn with_attachments(mut result: Value, parts: Vec<ContentPart>) -> Value {
+    if parts.is_empty() {
+        return result;
+    }
+
+    let attachments = serde_json::to_value(parts).unwrap_or_default();
+    match result.as_object_mut() {
+        Some(object) => {
+            object.insert(ATTACHMENTS_KEY.to_string(), attachments);
+            result
+        }
+        None => json!({ "result": result, ATTACHMENTS_KEY: attachments }),
+    }
+}
+
+/// User message that forwards tool attachments, since tool messages can only carry text
+pub(crate) fn tool_attachments_message(tool_call_id: &str, parts: &[ContentPart]) -> Value {
+    json!({
+        "role": "user",
+        "content": message_content(
+            &format!("Attachments returned by tool call {}:", tool_call_id),
+            parts,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_bytes_serialize_as_data_url() {
+        let part = ContentPart::image_bytes("image/png", &[1, 2, 3]);
+        let value = part.to_openai_value();
+        assert_eq!(value["type"], "image_url");
+        assert_eq!(value["image_url"]["url"], "data:image/png;base64,AQID");
+
+        assert_eq!(ContentPart::from_openai_value(&value), Some(part));
+    }
+
+    #[test]
+    fn test_message_content_stays_text_without_parts() {
+        assert_eq!(message_content("hello", &[]), json!("hello"));
+
+        let content = message_content(
+            "describe",
+            &[ContentPart::image_url("https://example.com/a.png").with_detail("low")],
+        );
+        assert_eq!(content[0]["text"], "describe");
+        assert_eq!(content[1]["image_url"]["detail"], "low");
+    }
+
+    #[test]
+    fn test_split_tool_attachments() {
+        let result = with_attachments(
+            json!({"status": "captured"}),
+            vec![ContentPart::image_base64("image/png", "AQID")],
+        );
+        let (stripped, parts) = split_tool_attachments(result);
+        assert_eq!(stripped, json!({"status": "captured"}));
+        assert_eq!(parts.len(), 1);
+
+        let unrelated = json!({"attachments": ["invoice.pdf"]});
+        let (kept, parts) = split_tool_attachments(unrelated.clone());
+        assert_eq!(kept, unrelated);
+        assert!(parts.is_empty());
+    }
+}
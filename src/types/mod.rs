@@ -1,6 +1,8 @@
+pub mod content;
 pub mod response;
 pub mod result;
 pub mod vacation_types;
 
+pub use content::ContentPart;
 pub use response::{deserialize_structured_response, StructuredPayload};
 pub use result::{RunResult, TokenUsage};
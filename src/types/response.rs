@@ -65,6 +65,13 @@ where
 }
 
 fn ensure_schema_matches<T: 'static>(schema: &SchemaHandle) -> Result<()> {
+    if schema.is_runtime() {
+        return Err(AgentError::Validation(format!(
+            "schema `{}` was defined at runtime and has no Rust type; read the payload as JSON instead",
+            schema.schema_name(),
+        )));
+    }
+
     let expected = TypeId::of::<T>();
     if schema.type_id() != expected {
         return Err(AgentError::Validation(format!(
@@ -1,15 +1,19 @@
 use super::response::deserialize_structured_response;
 use crate::{
-    core::steps::AgentStep,
+    core::{redaction::Redactor, steps::AgentStep},
     error::{AgentError, Result as AgentResult},
     schemas::{CompletionSchema, SchemaHandle},
+    tools::result::Artifact,
 };
-use serde::{Deserialize, Serialize};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 use serde_json::Value;
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
-/// Result of an agent execution run
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Result of an agent execution run.
+///
+/// The fields hold the real values; with a [`Redactor`] configured on the agent, serializing
+/// the run, [`replay`](Self::replay) and [`explain`](Self::explain) mask secrets.
+#[derive(Debug, Clone, Deserialize)]
 pub struct RunResult {
     /// Final output from the agent
     pub output: String,
@@ -21,12 +25,43 @@ pub struct RunResult {
     pub schema: Option<SchemaHandle>,
     /// All reasoning steps taken during execution
     pub steps: Vec<AgentStep>,
-    /// Total tokens used (if available from API)
+    /// Total tokens used across all iterations and sub-agents (if available from API)
     pub tokens: Option<TokenUsage>,
     /// Total execution duration
     pub duration: Duration,
     /// Number of iterations used
     pub iterations: usize,
+    /// Id of the run, also given to every tool call through its `ToolContext`
+    #[serde(default)]
+    pub run_id: String,
+    /// Masks secrets whenever the run is shown or serialized
+    #[serde(skip)]
+    pub(crate) redactor: Option<Arc<Redactor>>,
+}
+
+impl Serialize for RunResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Some(redactor) = &self.redactor {
+            return redactor.redact_run(self).serialize(serializer);
+        }
+
+        let mut state = serializer.serialize_struct("RunResult", 7)?;
+        state.serialize_field("output", &self.output)?;
+        match &self.structured {
+            Some(structured) => state.serialize_field("structured", structured)?,
+            None => state.skip_field("structured")?,
+        }
+        state.serialize_field("steps", &self.steps)?;
+        state.serialize_field("tokens", &self.tokens)?;
+        state.serialize_field("duration", &self.duration)?;
+        state.serialize_field("iterations", &self.iterations)?;
+        if self.run_id.is_empty() {
+            state.skip_field("run_id")?;
+        } else {
+            state.serialize_field("run_id", &self.run_id)?;
+        }
+        state.end()
+    }
 }
 
 /// Token usage information from the API
@@ -37,6 +72,23 @@ pub struct TokenUsage {
     pub total_tokens: u32,
 }
 
+impl TokenUsage {
+    /// Add another usage report to this one
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+/// Fold `usage` into a running total that starts out empty
+pub(crate) fn accumulate_usage(total: &mut Option<TokenUsage>, usage: &TokenUsage) {
+    match total {
+        Some(total) => total.add(usage),
+        None => *total = Some(usage.clone()),
+    }
+}
+
 impl RunResult {
     /// Create a new RunResult
     pub fn new(
@@ -56,11 +108,25 @@ impl RunResult {
             tokens,
             duration,
             iterations,
+            run_id: String::new(),
+            redactor: None,
+        }
+    }
+
+    /// Copy of the run with secrets masked, as it is shown and serialized
+    pub fn redacted(&self) -> RunResult {
+        match &self.redactor {
+            Some(redactor) => redactor.redact_run(self),
+            None => self.clone(),
         }
     }
 
     /// Generate a human-readable replay of the execution
     pub fn replay(&self) -> String {
+        if self.redactor.is_some() {
+            return self.redacted().replay();
+        }
+
         let mut lines = Vec::new();
 
         lines.push("=== Agent Execution Trace ===".to_string());
@@ -96,6 +162,10 @@ impl RunResult {
 
     /// Generate a detailed explanation with full step data
     pub fn explain(&self) -> String {
+        if self.redactor.is_some() {
+            return self.redacted().explain();
+        }
+
         let mut lines = Vec::new();
 
         lines.push("=== Agent Execution Explanation ===".to_string());
@@ -116,12 +186,24 @@ impl RunResult {
             lines.push(format!("\n{}. {}", idx + 1, step.describe()));
 
             match step {
-                AgentStep::Task { content } => {
+                AgentStep::Task {
+                    content,
+                    attachments,
+                } => {
                     lines.push(format!("   Content: {}", content));
+                    for attachment in attachments {
+                        lines.push(format!("   Attachment: {}", attachment.describe()));
+                    }
                 }
                 AgentStep::Planning { plan } => {
                     lines.push(format!("   Plan: {}", plan));
                 }
+                AgentStep::Reasoning { content, details } => {
+                    lines.push(format!("   Reasoning: {}", content));
+                    if !details.is_empty() {
+                        lines.push(format!("   Reasoning blocks: {}", details.len()));
+                    }
+                }
                 AgentStep::Action {
                     tool_name,
                     tool_call_id,
@@ -135,10 +217,55 @@ impl RunResult {
                     tool_call_id,
                     result,
                     is_error,
+                    attachments,
+                    structured,
+                    artifacts,
                 } => {
                     lines.push(format!("   Call ID: {}", tool_call_id));
                     lines.push(format!("   Error: {}", is_error));
                     lines.push(format!("   Result: {}", result));
+                    for attachment in attachments {
+                        lines.push(format!("   Attachment: {}", attachment.describe()));
+                    }
+                    if let Some(structured) = structured {
+                        lines.push(format!("   Structured: {}", structured));
+                    }
+                    for artifact in artifacts {
+                        lines.push(format!("   Artifact: {}", artifact.describe()));
+                    }
+                }
+                AgentStep::Progress {
+                    tool_call_id,
+                    message,
+                    data,
+                } => {
+                    lines.push(format!("   Call ID: {}", tool_call_id));
+                    lines.push(format!("   Message: {}", message));
+                    if let Some(data) = data {
+                        lines.push(format!("   Data: {}", data));
+                    }
+                }
+                AgentStep::Delegation {
+                    tool_call_id, run, ..
+                } => {
+                    lines.push(format!("   Call ID: {}", tool_call_id));
+                    for line in run.explain().lines() {
+                        lines.push(format!("   | {}", line));
+                    }
+                }
+                AgentStep::Reply { content } => {
+                    lines.push(format!("   Content: {}", content));
+                }
+                AgentStep::Reminder { label, message } => {
+                    lines.push(format!("   About: {}", label));
+                    lines.push(format!("   Message: {}", message));
+                }
+                AgentStep::Handoff { from, to, reason } => {
+                    lines.push(format!("   From: {}", from));
+                    lines.push(format!("   To: {}", to));
+                    if let Some(reason) = reason {
+                        lines.push(format!("   Reason: {}", reason));
+                    }
                 }
                 AgentStep::FinalAnswer { answer, .. } => {
                     lines.push(format!("   Answer: {}", answer));
@@ -215,7 +342,43 @@ impl RunResult {
             .any(|s| matches!(s, AgentStep::FinalAnswer { .. }))
     }
 
-    /// Get all error observations
+    /// Runs of sub-agents delegated to directly from this run
+    pub fn sub_runs(&self) -> Vec<&RunResult> {
+        self.steps
+            .iter()
+            .filter_map(|s| match s {
+                AgentStep::Delegation { run, .. } => Some(run.as_ref()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Team members that handled this run, in order (empty outside of a team)
+    pub fn agents(&self) -> Vec<&str> {
+        let mut agents = Vec::new();
+        for step in &self.steps {
+            if let AgentStep::Handoff { from, to, .. } = step {
+                if agents.is_empty() {
+                    agents.push(from.as_str());
+                }
+                agents.push(to.as_str());
+            }
+        }
+        agents
+    }
+
+    /// Files kept from tool results, in the order the tools returned them
+    pub fn artifacts(&self) -> Vec<&Artifact> {
+        self.steps
+            .iter()
+            .flat_map(|step| match step {
+                AgentStep::Observation { artifacts, .. } => artifacts.as_slice(),
+                _ => &[],
+            })
+            .collect()
+    }
+
+    /// Get all error observations and the reminders sent after rejected replies
     pub fn errors(&self) -> Vec<&str> {
         self.steps
             .iter()
@@ -223,6 +386,7 @@ impl RunResult {
                 AgentStep::Observation {
                     result, is_error, ..
                 } if *is_error => Some(result.as_str()),
+                AgentStep::Reminder { message, .. } => Some(message.as_str()),
                 _ => None,
             })
             .collect()
@@ -243,6 +407,7 @@ mod tests {
         let steps = vec![
             AgentStep::Task {
                 content: "Test task".to_string(),
+                attachments: Vec::new(),
             },
             AgentStep::FinalAnswer {
                 answer: "Test answer".to_string(),
@@ -298,6 +463,7 @@ mod tests {
         let steps = vec![
             AgentStep::Task {
                 content: "Test".to_string(),
+                attachments: Vec::new(),
             },
             AgentStep::FinalAnswer {
                 answer: "Done".to_string(),
@@ -333,11 +499,17 @@ mod tests {
                 tool_call_id: "1".to_string(),
                 result: "Error occurred".to_string(),
                 is_error: true,
+                attachments: Vec::new(),
+                structured: None,
+                artifacts: Vec::new(),
             },
             AgentStep::Observation {
                 tool_call_id: "2".to_string(),
                 result: "Success".to_string(),
                 is_error: false,
+                attachments: Vec::new(),
+                structured: None,
+                artifacts: Vec::new(),
             },
         ];
 
@@ -0,0 +1,38 @@
+//! Helpers shared by the integration tests
+// Each test crate compiles this module on its own and uses only some of it
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+
+/// Chat completion response calling a single tool
+pub fn tool_call_body(id: &str, name: &str, arguments: Value) -> String {
+    tool_call_response(id, name, arguments).to_string()
+}
+
+/// Chat completion response calling a single tool, reporting `tokens` prompt and completion
+/// tokens each
+pub fn tool_call_body_with_usage(id: &str, name: &str, arguments: Value, tokens: u32) -> String {
+    let mut response = tool_call_response(id, name, arguments);
+    response["usage"] = json!({
+        "prompt_tokens": tokens,
+        "completion_tokens": tokens,
+        "total_tokens": tokens * 2
+    });
+    response.to_string()
+}
+
+fn tool_call_response(id: &str, name: &str, arguments: Value) -> Value {
+    json!({
+        "choices": [{
+            "message": {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": id,
+                    "type": "function",
+                    "function": { "name": name, "arguments": arguments.to_string() }
+                }]
+            }
+        }]
+    })
+}
@@ -0,0 +1,113 @@
+use mockito::Matcher;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tiny_agent_rs::{completion_schema, Agent, CompletionSchema, FunctionFactory};
+
+/// Outcome of an expense review
+#[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[completion_schema]
+enum Decision {
+    /// The expense is reimbursed in full
+    Approve { amount: u32 },
+    /// The expense is refused
+    Reject { reason: String },
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct Invoice {
+    number: String,
+}
+
+/// One page of results
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[completion_schema]
+struct Page<T> {
+    items: Vec<T>,
+    /// Cursor for the next page, if any
+    next: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[completion_schema(name = "InvoiceEnvelope")]
+struct Envelope(Invoice);
+
+#[test]
+fn test_enum_variant_docs_become_one_of_descriptions() {
+    let schema = Decision::schema().schema_json();
+    assert_eq!(schema["description"], "Outcome of an expense review");
+
+    let variants = schema["oneOf"].as_array().unwrap();
+    assert_eq!(variants.len(), 2);
+    assert_eq!(
+        variants[0]["description"],
+        "The expense is reimbursed in full"
+    );
+    assert_eq!(variants[1]["description"], "The expense is refused");
+}
+
+#[test]
+fn test_generic_instantiations_get_their_own_schema() {
+    let invoices = Page::<Invoice>::schema();
+    let names = Page::<String>::schema();
+
+    assert_eq!(invoices.schema_name(), "Page_for_Invoice");
+    assert_eq!(names.schema_name(), "Page_for_String");
+    assert_eq!(
+        invoices.schema_json()["properties"]["next"]["description"],
+        "Cursor for the next page, if any"
+    );
+    assert!(std::ptr::eq(invoices, Page::<Invoice>::schema()));
+}
+
+#[test]
+fn test_tuple_struct_uses_inner_schema() {
+    let schema = Envelope::schema();
+    assert_eq!(schema.schema_name(), "InvoiceEnvelope");
+    assert!(schema.schema_json()["properties"]["number"].is_object());
+}
+
+#[tokio::test]
+async fn test_enum_schema_round_trips_through_structured_response_tool() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex(r#""oneOf""#.to_string()))
+        .with_body(
+            json!({
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {
+                                "name": "structured_response",
+                                "arguments": json!({
+                                    "structured": { "Reject": { "reason": "no receipt" } }
+                                })
+                                .to_string()
+                            }
+                        }]
+                    }
+                }]
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_completion_schema::<Decision>();
+
+    let result = agent.run_with_steps("Review the taxi fare").await.unwrap();
+    mock.assert_async().await;
+    assert_eq!(
+        result.deserialize_structured::<Decision>().unwrap(),
+        Decision::Reject {
+            reason: "no receipt".to_string()
+        }
+    );
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use common::tool_call_body;
+use mockito::Matcher;
+use serde_json::json;
+use tiny_agent_rs::{
+    agent::guardrails::{BlockedTopics, FnGuardrail, PiiGuardrail},
+    Agent, AgentError, FunctionFactory, GuardrailAction, GuardrailStage,
+};
+
+#[tokio::test]
+async fn test_input_guardrail_stops_the_run_before_the_model_is_called() {
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url("http://127.0.0.1:9")
+        .with_input_guardrail(BlockedTopics::new(["stock tips"]));
+
+    let error = agent
+        .run_with_steps("Give me some stock tips")
+        .await
+        .unwrap_err();
+    assert_eq!(error.error_code(), "GUARDRAIL_VIOLATION");
+    assert!(matches!(
+        error,
+        AgentError::Guardrail(ref violation)
+            if violation.stage == GuardrailStage::Input && violation.guardrail == "blocked_topics"
+    ));
+}
+
+#[tokio::test]
+async fn test_correcting_guardrail_sends_the_violation_back_to_the_model() {
+    let mut server = mockito::Server::new_async().await;
+    let leaky = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_1",
+            "final_answer",
+            json!({ "answer": "Write to jane@example.com" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let corrected = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex("GUARDRAIL_VIOLATION".to_string()))
+        .with_body(tool_call_body(
+            "call_2",
+            "final_answer",
+            json!({ "answer": "Use the contact form" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_output_guardrail(PiiGuardrail::new(), GuardrailAction::Correct);
+
+    let result = agent
+        .run_with_steps("How do I reach support?")
+        .await
+        .unwrap();
+    leaky.assert_async().await;
+    corrected.assert_async().await;
+
+    assert_eq!(result.output, "Use the contact form");
+    assert_eq!(result.errors().len(), 1);
+    assert!(result.errors()[0].contains("an email address"));
+}
+
+#[tokio::test]
+async fn test_aborting_async_guardrail_fails_the_run() {
+    let mut server = mockito::Server::new_async().await;
+    let answer = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_1",
+            "final_answer",
+            json!({ "answer": "Guaranteed returns of 40%" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    // Stands in for a call to a moderation service
+    let moderation = FnGuardrail::new("moderation", |content| async move {
+        tokio::task::yield_now().await;
+        if content.text.contains("Guaranteed") {
+            Err("makes a financial promise".to_string())
+        } else {
+            Ok(())
+        }
+    });
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_output_guardrail(moderation, GuardrailAction::Abort);
+
+    let error = agent
+        .run_with_steps("How should I invest?")
+        .await
+        .unwrap_err();
+    answer.assert_async().await;
+
+    assert!(matches!(
+        error,
+        AgentError::Guardrail(ref violation)
+            if violation.stage == GuardrailStage::Output
+                && violation.message == "makes a financial promise"
+    ));
+}
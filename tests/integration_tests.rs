@@ -46,8 +46,8 @@ async fn test_weather_tool() {
 #[tokio::test]
 async fn test_function_factory() {
     let mut factory = FunctionFactory::new();
-    factory.register_tool(CalculatorTool::new());
-    factory.register_tool(WeatherTool::new());
+    factory.register_tool(CalculatorTool::new()).unwrap();
+    factory.register_tool(WeatherTool::new()).unwrap();
 
     // Test tool registration
     assert!(factory.has_function("calculator"));
@@ -107,7 +107,7 @@ async fn test_smolagents_style_execution() {
     };
 
     let mut factory = FunctionFactory::new();
-    factory.register_tool(CalculatorTool::new());
+    factory.register_tool(CalculatorTool::new()).unwrap();
 
     let agent = Agent::new(api_key, factory).with_max_iterations(5);
 
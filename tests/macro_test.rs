@@ -67,3 +67,148 @@ async fn test_macro_tool_invalid_params() {
     let result = tool.execute(params).await;
     assert!(result.is_err());
 }
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct LookupParams {
+    key: String,
+}
+
+struct Catalog {
+    entries: std::collections::HashMap<String, u32>,
+}
+
+tiny_agent_rs::tool!(
+    name = "catalog_lookup",
+    description = "Look up a key in the catalog",
+    params = LookupParams,
+    state = Catalog,
+    |catalog: &Catalog, params: LookupParams| async move {
+        match catalog.entries.get(&params.key) {
+            Some(value) => Ok(json!({ "value": value })),
+            None => Err(format!("no entry for {}", params.key)),
+        }
+    }
+);
+
+#[tokio::test]
+async fn test_macro_tool_with_state() {
+    let tool = CatalogLookup::new(Catalog {
+        entries: [("apples".to_string(), 3)].into_iter().collect(),
+    });
+
+    assert_eq!(tool.name(), "catalog_lookup");
+
+    let result = tool.execute(json!({ "key": "apples" })).await.unwrap();
+    assert_eq!(result["value"], 3);
+
+    let error = tool.execute(json!({ "key": "pears" })).await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Tool execution error: no entry for pears"
+    );
+}
+
+/// Convert a temperature between Celsius and Fahrenheit
+#[tiny_agent_rs::tools::tool]
+async fn convert_temperature(
+    /// Temperature to convert
+    value: f64,
+    /// Unit of `value`: "C" or "F"
+    unit: String,
+) -> Result<serde_json::Value, String> {
+    match unit.as_str() {
+        "C" => Ok(json!({ "fahrenheit": value * 9.0 / 5.0 + 32.0 })),
+        "F" => Ok(json!({ "celsius": (value - 32.0) * 5.0 / 9.0 })),
+        other => Err(format!("unknown unit {other}")),
+    }
+}
+
+struct Greeter {
+    greeting: String,
+}
+
+/// Greet someone using the configured greeting
+#[tiny_agent_rs::tools::tool(name = "greet")]
+async fn greet_person(#[state] greeter: &Greeter, name: String) -> Result<String, std::fmt::Error> {
+    Ok(format!("{}, {}!", greeter.greeting, name))
+}
+
+#[tokio::test]
+async fn test_attribute_macro_tool() {
+    let tool = ConvertTemperature::new();
+
+    assert_eq!(tool.name(), "convert_temperature");
+    assert_eq!(
+        tool.description(),
+        "Convert a temperature between Celsius and Fahrenheit"
+    );
+
+    let schema = tool.parameters_schema();
+    assert_eq!(
+        schema["properties"]["value"]["description"],
+        "Temperature to convert"
+    );
+    assert_eq!(schema["required"], json!(["unit", "value"]));
+
+    let result = tool
+        .execute(json!({ "value": 100.0, "unit": "C" }))
+        .await
+        .unwrap();
+    assert_eq!(result["fahrenheit"], 212.0);
+
+    let error = tool
+        .execute(json!({ "value": 1.0, "unit": "K" }))
+        .await
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Tool execution error: unknown unit K");
+
+    // The function itself stays callable
+    assert!(convert_temperature(0.0, "C".to_string()).await.is_ok());
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Slot {
+    day: String,
+    hour: u8,
+}
+
+/// Book a meeting slot
+#[tiny_agent_rs::tools::tool]
+async fn book(slot: Slot) -> Result<String, String> {
+    Ok(format!("{} at {}", slot.day, slot.hour))
+}
+
+#[tokio::test]
+async fn test_attribute_macro_schema_keeps_nested_definitions() {
+    let schema = Book::new().parameters_schema();
+
+    // The parameters schema itself, with the definitions its `$ref`s point at beside it
+    assert!(schema.get("$schema").is_none());
+    assert_eq!(schema["type"], "object");
+    assert_eq!(schema["properties"]["slot"]["$ref"], "#/definitions/Slot");
+    assert_eq!(
+        schema["definitions"]["Slot"]["required"],
+        json!(["day", "hour"])
+    );
+
+    let result = Book::new()
+        .execute(json!({ "slot": { "day": "Monday", "hour": 9 } }))
+        .await
+        .unwrap();
+    assert_eq!(result, "Monday at 9");
+}
+
+#[tokio::test]
+async fn test_attribute_macro_tool_with_state() {
+    let tool = Greet::new(Greeter {
+        greeting: "Hello".to_string(),
+    });
+
+    assert_eq!(tool.name(), "greet");
+    let schema = tool.parameters_schema();
+    assert_eq!(schema["required"], json!(["name"]));
+    assert!(schema["properties"].get("greeter").is_none());
+
+    let result = tool.execute(json!({ "name": "Ana" })).await.unwrap();
+    assert_eq!(result, "Hello, Ana!");
+}
@@ -0,0 +1,272 @@
+use mockito::Matcher;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tiny_agent_rs::{AgentError, FunctionFactory, McpClient, McpServerConfig};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// Minimal MCP server speaking newline-delimited JSON-RPC over an in-process stream
+async fn run_stub_server(io: tokio::io::DuplexStream) {
+    let (reader, mut writer) = tokio::io::split(io);
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let request: Value = serde_json::from_str(&line).unwrap();
+        let Some(id) = request.get("id").cloned() else {
+            continue;
+        };
+
+        let result = match request["method"].as_str().unwrap() {
+            "initialize" => json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "stub", "version": "0.1.0" }
+            }),
+            "tools/list" if request["params"].get("cursor").is_none() => json!({
+                "tools": [{
+                    "name": "echo",
+                    "description": "Echo the input text",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": { "text": { "type": "string" } },
+                        "required": ["text"]
+                    }
+                }],
+                "nextCursor": "page-2"
+            }),
+            "tools/list" => json!({
+                "tools": [{ "name": "fail" }]
+            }),
+            "tools/call" => {
+                // Exercise the client's handling of interleaved notifications and pings
+                let ping = json!({"jsonrpc": "2.0", "id": "srv-1", "method": "ping"});
+                let notice =
+                    json!({"jsonrpc": "2.0", "method": "notifications/message", "params": {}});
+                for message in [notice, ping] {
+                    writer
+                        .write_all(format!("{}\n", message).as_bytes())
+                        .await
+                        .unwrap();
+                }
+                let pong = lines.next_line().await.unwrap().unwrap();
+                assert_eq!(serde_json::from_str::<Value>(&pong).unwrap()["id"], "srv-1");
+
+                match request["params"]["name"].as_str().unwrap() {
+                    "echo" => json!({
+                        "content": [{
+                            "type": "text",
+                            "text": request["params"]["arguments"]["text"]
+                        }]
+                    }),
+                    _ => json!({
+                        "content": [{ "type": "text", "text": "boom" }],
+                        "isError": true
+                    }),
+                }
+            }
+            _ => Value::Null,
+        };
+
+        let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+        writer
+            .write_all(format!("{}\n", response).as_bytes())
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_stdio_stream_tools_are_registered() {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(run_stub_server(server_io));
+
+    let (reader, writer) = tokio::io::split(client_io);
+    let client = McpClient::connect_streams(reader, writer).await.unwrap();
+    assert_eq!(client.server_info()["name"], "stub");
+    assert_eq!(client.protocol_version(), "2025-03-26");
+
+    let client = Arc::new(client);
+    let mut factory = FunctionFactory::new();
+    let names = factory
+        .register_mcp_client("stub", client.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        names,
+        vec!["stub.echo".to_string(), "stub.fail".to_string()]
+    );
+
+    let tools = factory.get_openai_tools();
+    let echo = tools
+        .iter()
+        .find(|tool| tool["function"]["name"] == "stub__echo")
+        .unwrap();
+    assert_eq!(echo["function"]["parameters"]["required"], json!(["text"]));
+
+    let output = factory
+        .execute_function("stub__echo", json!({"text": "hello"}))
+        .await
+        .unwrap();
+    assert_eq!(output, json!("hello"));
+
+    let error = factory
+        .execute_function("stub.fail", json!({}))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AgentError::ToolExecution(_)));
+
+    // Registering the same server under the same name again collides instead of replacing
+    let error = factory
+        .register_mcp_client("stub", client)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AgentError::Config(ref message) if message.contains("stub.echo")));
+    assert_eq!(factory.tool_names(), ["stub.echo", "stub.fail"]);
+}
+
+#[tokio::test]
+async fn test_http_server_tools_are_registered() {
+    let mut server = mockito::Server::new_async().await;
+
+    // The server settles on an older revision, which every later request then announces
+    let initialize = server
+        .mock("POST", "/mcp")
+        .match_header("mcp-protocol-version", Matcher::Missing)
+        .match_body(Matcher::PartialJson(json!({"method": "initialize"})))
+        .with_header("content-type", "application/json")
+        .with_header("mcp-session-id", "session-123")
+        .with_body(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "serverInfo": { "name": "http-stub" }
+                }
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let initialized = server
+        .mock("POST", "/mcp")
+        .match_header("mcp-session-id", "session-123")
+        .match_header("mcp-protocol-version", "2024-11-05")
+        .match_body(Matcher::PartialJson(
+            json!({"method": "notifications/initialized"}),
+        ))
+        .with_status(202)
+        .create_async()
+        .await;
+
+    let list = server
+        .mock("POST", "/mcp")
+        .match_header("mcp-session-id", "session-123")
+        .match_header("mcp-protocol-version", "2024-11-05")
+        .match_body(Matcher::PartialJson(json!({"method": "tools/list"})))
+        .with_header("content-type", "text/event-stream")
+        .with_body(format!(
+            "event: message\ndata: {}\n\n",
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "result": {
+                    "tools": [{
+                        "name": "lookup_order",
+                        "description": "Find an order by id",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": { "id": { "type": "string" } }
+                        }
+                    }]
+                }
+            })
+        ))
+        .create_async()
+        .await;
+
+    let call = server
+        .mock("POST", "/mcp")
+        .match_header("mcp-protocol-version", "2024-11-05")
+        .match_body(Matcher::PartialJson(json!({
+            "method": "tools/call",
+            "params": { "name": "lookup_order", "arguments": { "id": "A-1" } }
+        })))
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "result": {
+                    "content": [{ "type": "text", "text": "{\"status\":\"shipped\"}" }],
+                    "structuredContent": { "status": "shipped" }
+                }
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let mut factory = FunctionFactory::new();
+    let names = factory
+        .register_mcp_server(
+            "orders",
+            &McpServerConfig::Http {
+                url: format!("{}/mcp", server.url()),
+                headers: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(names, vec!["orders.lookup_order".to_string()]);
+
+    let output = factory
+        .execute_function("orders.lookup_order", json!({"id": "A-1"}))
+        .await
+        .unwrap();
+    assert_eq!(output, json!({"status": "shipped"}));
+
+    initialize.assert_async().await;
+    initialized.assert_async().await;
+    list.assert_async().await;
+    call.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_unsupported_protocol_version_fails_to_connect() {
+    let mut server = mockito::Server::new_async().await;
+    let initialize = server
+        .mock("POST", "/mcp")
+        .match_body(Matcher::PartialJson(json!({"method": "initialize"})))
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": { "protocolVersion": "2030-01-01", "capabilities": {} }
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+    let initialized = server
+        .mock("POST", "/mcp")
+        .match_body(Matcher::PartialJson(
+            json!({"method": "notifications/initialized"}),
+        ))
+        .expect(0)
+        .create_async()
+        .await;
+
+    let error = McpClient::connect(&McpServerConfig::Http {
+        url: format!("{}/mcp", server.url()),
+        headers: Default::default(),
+    })
+    .await
+    .unwrap_err();
+    assert!(matches!(error, AgentError::Mcp(ref message) if message.contains("2030-01-01")));
+
+    initialize.assert_async().await;
+    initialized.assert_async().await;
+}
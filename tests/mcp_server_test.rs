@@ -0,0 +1,80 @@
+use serde_json::json;
+use std::sync::Arc;
+use tiny_agent_rs::{tools::CalculatorTool, AgentError, FunctionFactory, McpClient, McpServer};
+
+fn calculator_server() -> McpServer {
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(CalculatorTool::new()).unwrap();
+    McpServer::new(factory).with_server_info("calc", "1.0.0")
+}
+
+#[tokio::test]
+async fn test_factory_round_trips_through_mcp_streams() {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    let server = calculator_server();
+    tokio::spawn(async move {
+        let (reader, writer) = tokio::io::split(server_io);
+        server.serve_streams(reader, writer).await.unwrap();
+    });
+
+    let (reader, writer) = tokio::io::split(client_io);
+    let client = McpClient::connect_streams(reader, writer).await.unwrap();
+    assert_eq!(client.server_info()["name"], "calc");
+
+    let mut imported = FunctionFactory::new();
+    let names = imported
+        .register_mcp_client("calc", Arc::new(client))
+        .await
+        .unwrap();
+    assert_eq!(names, vec!["calc.calculator".to_string()]);
+
+    let output = imported
+        .execute_function(
+            "calc.calculator",
+            json!({"operation": "multiply", "a": 6.0, "b": 7.0}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(output["result"], 42.0);
+
+    let error = imported
+        .execute_function(
+            "calc.calculator",
+            json!({"operation": "divide", "a": 1.0, "b": 0.0}),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AgentError::ToolExecution(_)));
+    assert!(error.to_string().contains("TOOL_EXECUTION_ERROR"));
+}
+
+#[cfg(feature = "server")]
+#[tokio::test]
+async fn test_factory_served_over_http() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, calculator_server().http_router())
+            .await
+            .unwrap();
+    });
+
+    let client = McpClient::connect_http(format!("http://{}/mcp", addr))
+        .await
+        .unwrap();
+    let tools = client.list_tools().await.unwrap();
+    assert_eq!(tools[0].name, "calculator");
+    assert_eq!(
+        tools[0].input_schema["required"],
+        json!(["operation", "a", "b"])
+    );
+
+    let result = client
+        .call_tool(
+            "calculator",
+            json!({"operation": "add", "a": 2.0, "b": 3.0}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["structuredContent"]["result"], 5.0);
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::tool_call_body;
+use mockito::Matcher;
+use serde_json::json;
+use tiny_agent_rs::{Agent, ContentPart, FunctionFactory};
+
+#[tokio::test]
+async fn test_task_image_is_sent_as_an_openai_content_part() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "messages": [
+                { "role": "system" },
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "What is in this picture?" },
+                        {
+                            "type": "image_url",
+                            "image_url": { "url": "data:image/png;base64,AQID" }
+                        }
+                    ]
+                }
+            ]
+        })))
+        .with_body(tool_call_body(
+            "call_1",
+            "final_answer",
+            json!({ "answer": "Three pixels." }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let agent =
+        Agent::new("test-key".to_string(), FunctionFactory::new()).with_base_url(server.url());
+    let result = agent
+        .run_with_attachments(
+            "What is in this picture?",
+            vec![ContentPart::image_bytes("image/png", &[1, 2, 3])],
+        )
+        .await
+        .unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(result.output, "Three pixels.");
+    assert_eq!(result.steps[0].attachments().len(), 1);
+}
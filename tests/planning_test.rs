@@ -0,0 +1,96 @@
+mod common;
+
+use common::tool_call_body;
+use mockito::Matcher;
+use serde_json::json;
+use tiny_agent_rs::{tools::CalculatorTool, Agent, AgentStep, FunctionFactory, PlanningConfig};
+
+fn content_body(content: &str) -> String {
+    json!({
+        "choices": [{
+            "message": { "role": "assistant", "content": content }
+        }]
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn test_planning_runs_before_first_iteration_and_after_errors() {
+    let mut server = mockito::Server::new_async().await;
+
+    let initial_plan = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex(r#""tool_choice":"none""#.to_string()),
+            Matcher::Regex("Think step-by-step".to_string()),
+        ]))
+        .with_body(content_body("Plan: divide with the calculator."))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let replan = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex(r#""tool_choice":"none""#.to_string()),
+            Matcher::Regex("Iteration 2".to_string()),
+        ]))
+        .with_body(content_body(
+            "Plan: report that division by zero is undefined.",
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let divide = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex(r#""tool_choice":"auto""#.to_string()),
+            Matcher::Regex("Plan: divide".to_string()),
+        ]))
+        .with_body(tool_call_body(
+            "call_1",
+            "calculator",
+            json!({"operation": "divide", "a": 1.0, "b": 0.0}),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let answer = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex(r#""tool_choice":"auto""#.to_string()),
+            Matcher::Regex("Plan: report".to_string()),
+        ]))
+        .with_body(tool_call_body(
+            "call_2",
+            "final_answer",
+            json!({"answer": "Division by zero is undefined."}),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(CalculatorTool::new()).unwrap();
+
+    let agent = Agent::new("test-key".to_string(), factory)
+        .with_base_url(server.url())
+        .with_planning(PlanningConfig::new());
+
+    let result = agent.run_with_steps("What is 1 / 0?").await.unwrap();
+    initial_plan.assert_async().await;
+    replan.assert_async().await;
+    divide.assert_async().await;
+    answer.assert_async().await;
+
+    let plans: Vec<_> = result
+        .steps
+        .iter()
+        .filter(|step| matches!(step, AgentStep::Planning { .. }))
+        .collect();
+    assert_eq!(plans.len(), 2);
+    assert!(matches!(result.steps[1], AgentStep::Planning { .. }));
+    assert_eq!(result.output, "Division by zero is undefined.");
+}
@@ -0,0 +1,81 @@
+use mockito::Matcher;
+use serde_json::json;
+use tiny_agent_rs::{tools::CalculatorTool, Agent, AgentStep, FunctionFactory};
+
+#[tokio::test]
+async fn test_reasoning_captured_and_replayed() {
+    let mut server = mockito::Server::new_async().await;
+
+    let first = server
+        .mock("POST", "/chat/completions")
+        .with_body(
+            json!({
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": null,
+                        "reasoning": "Add with the calculator.",
+                        "reasoning_details": [{"type": "reasoning.encrypted", "data": "sealed"}],
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {
+                                "name": "calculator",
+                                "arguments": "{\"operation\": \"add\", \"a\": 2, \"b\": 2}"
+                            }
+                        }]
+                    }
+                }]
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create_async()
+        .await;
+
+    let second = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex("sealed".to_string()))
+        .with_body(
+            json!({
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_2",
+                            "type": "function",
+                            "function": {
+                                "name": "final_answer",
+                                "arguments": "{\"answer\": \"4\"}"
+                            }
+                        }]
+                    }
+                }]
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create_async()
+        .await;
+
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(CalculatorTool::new()).unwrap();
+
+    let agent = Agent::new("test-key".to_string(), factory)
+        .with_base_url(server.url())
+        .with_reasoning_replay(true);
+
+    let result = agent.run_with_steps("What is 2 + 2?").await.unwrap();
+    first.assert_async().await;
+    second.assert_async().await;
+
+    assert!(result.steps.iter().any(|step| matches!(
+        step,
+        AgentStep::Reasoning { content, details } if content == "Add with the calculator." && details.len() == 1
+    )));
+    assert!(result
+        .explain()
+        .contains("Reasoning: Add with the calculator."));
+    assert_eq!(result.output, "4");
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::tool_call_body;
+use mockito::Matcher;
+use serde_json::{json, Value};
+use std::{future::Future, pin::Pin};
+use tiny_agent_rs::{Agent, AgentError, AgentStep, FunctionFactory, Redactor, Tool};
+use tokio::sync::mpsc::unbounded_channel;
+
+const TOKEN: &str = "tok-4f9a2c7d1e";
+const API_KEY: &str = "sk-live0123456789abcdefghij";
+
+/// Exchanges a token for an API key
+#[derive(Debug)]
+struct LoginTool;
+
+impl Tool for LoginTool {
+    fn name(&self) -> &str {
+        "login"
+    }
+
+    fn description(&self) -> &str {
+        "Exchange a token for an API key"
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "token": { "type": "string" } },
+            "required": ["token"]
+        })
+    }
+
+    fn execute(
+        &self,
+        _parameters: Value,
+    ) -> Pin<Box<dyn Future<Output = Result<Value, AgentError>> + Send + '_>> {
+        Box::pin(async { Ok(json!({ "api_key": API_KEY, "user": "jane" })) })
+    }
+}
+
+#[tokio::test]
+async fn test_traces_are_masked_while_the_model_sees_real_values() {
+    let mut server = mockito::Server::new_async().await;
+    let login = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body("call_1", "login", json!({ "token": TOKEN })))
+        .expect(1)
+        .create_async()
+        .await;
+    let answer = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex(API_KEY.to_string()))
+        .with_body(tool_call_body(
+            "call_2",
+            "final_answer",
+            json!({ "answer": "Logged in as jane" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(LoginTool).unwrap();
+    let agent = Agent::new("test-key".to_string(), factory)
+        .with_base_url(server.url())
+        .with_redactor(Redactor::new().with_tool_fields("login", ["token"]));
+
+    let (sender, mut receiver) = unbounded_channel();
+    let result = agent
+        .run_with_step_sender("Log me in", sender)
+        .await
+        .unwrap();
+    login.assert_async().await;
+    answer.assert_async().await;
+
+    // The fields keep the real values
+    assert!(matches!(
+        &result.steps[1],
+        AgentStep::Action { arguments, .. } if arguments["token"] == TOKEN
+    ));
+
+    let mut streamed = Vec::new();
+    while let Ok(step) = receiver.try_recv() {
+        streamed.push(serde_json::to_string(&step).unwrap());
+    }
+    let serialized = serde_json::to_string(&result).unwrap();
+    for shown in [
+        serialized,
+        result.replay(),
+        result.explain(),
+        streamed.join("\n"),
+    ] {
+        assert!(!shown.contains(TOKEN), "{}", shown);
+        assert!(!shown.contains(API_KEY), "{}", shown);
+        assert!(shown.contains("[REDACTED]"));
+        assert!(shown.contains("jane"));
+    }
+}
@@ -0,0 +1,202 @@
+#![cfg(feature = "server")]
+
+use mockito::Matcher;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use tiny_agent_rs::{server::AgentServer, Agent, AgentStep, FunctionFactory, RunResult};
+
+fn final_answer_body(answer: &str) -> String {
+    json!({
+        "choices": [{
+            "message": {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {
+                        "name": "final_answer",
+                        "arguments": json!({ "answer": answer }).to_string()
+                    }
+                }]
+            }
+        }],
+        "usage": { "prompt_tokens": 4, "completion_tokens": 3, "total_tokens": 7 }
+    })
+    .to_string()
+}
+
+fn test_agent(llm_url: String) -> Agent {
+    Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(llm_url)
+        .with_model("test/model")
+}
+
+async fn spawn_server(llm_url: String) -> String {
+    spawn(AgentServer::new(test_agent(llm_url))).await
+}
+
+async fn spawn(server: AgentServer) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, server.router()).await.unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn test_chat_completions_endpoint_is_openai_compatible() {
+    let mut llm = mockito::Server::new_async().await;
+    let mock = llm
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex("Answer in French".to_string()),
+            Matcher::Regex("My name is Ana".to_string()),
+            Matcher::Regex("What is my name".to_string()),
+        ]))
+        .with_body(final_answer_body("Vous vous appelez Ana."))
+        .create_async()
+        .await;
+
+    let base = spawn_server(llm.url()).await;
+    let response: Value = reqwest::Client::new()
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&json!({
+            "model": "tiny-agent",
+            "messages": [
+                { "role": "system", "content": "Answer in French." },
+                { "role": "user", "content": "My name is Ana." },
+                { "role": "assistant", "content": "Nice to meet you." },
+                { "role": "user", "content": "What is my name?" }
+            ]
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(response["object"], "chat.completion");
+    assert_eq!(response["model"], "tiny-agent");
+    assert_eq!(
+        response["choices"][0]["message"]["content"],
+        "Vous vous appelez Ana."
+    );
+    assert_eq!(response["usage"]["total_tokens"], 7);
+}
+
+#[tokio::test]
+async fn test_runs_endpoint_returns_and_streams_run_result() {
+    let mut llm = mockito::Server::new_async().await;
+    let _mock = llm
+        .mock("POST", "/chat/completions")
+        .with_body(final_answer_body("42"))
+        .create_async()
+        .await;
+
+    let base = spawn_server(llm.url()).await;
+    let client = reqwest::Client::new();
+
+    let result: RunResult = client
+        .post(format!("{}/runs", base))
+        .json(&json!({ "prompt": "What is the answer?" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(result.output, "42");
+    assert!(result.is_success());
+
+    let stream = client
+        .post(format!("{}/runs", base))
+        .json(&json!({ "prompt": "What is the answer?", "stream": true }))
+        .send()
+        .await
+        .unwrap();
+    assert!(stream.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/event-stream"));
+
+    let body = stream.text().await.unwrap();
+    let events: Vec<&str> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("event: "))
+        .collect();
+    assert_eq!(events.first(), Some(&"step"));
+    assert_eq!(events.last(), Some(&"result"));
+    assert!(body.contains(r#""type":"final_answer""#));
+}
+
+#[tokio::test]
+async fn test_chat_completions_rejects_conversation_without_user_turn() {
+    let llm = mockito::Server::new_async().await;
+    let base = spawn_server(llm.url()).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&json!({ "messages": [{ "role": "system", "content": "hi" }] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "VALIDATION_ERROR");
+}
+
+#[tokio::test]
+async fn test_observers_see_the_steps_and_result_of_every_run() {
+    let mut llm = mockito::Server::new_async().await;
+    let _mock = llm
+        .mock("POST", "/chat/completions")
+        .with_body(final_answer_body("42"))
+        .expect(2)
+        .create_async()
+        .await;
+
+    let seen: Arc<Mutex<Vec<String>>> = Arc::default();
+    let finished: Arc<Mutex<Vec<(String, usize)>>> = Arc::default();
+    let server = AgentServer::new(test_agent(llm.url()))
+        .with_step_observer({
+            let seen = seen.clone();
+            move |step: &AgentStep| seen.lock().unwrap().push(step.describe())
+        })
+        .with_run_observer({
+            let finished = finished.clone();
+            move |outcome, steps| {
+                let output = outcome.map(|run| run.output.clone()).unwrap_or_default();
+                finished.lock().unwrap().push((output, steps.len()));
+            }
+        });
+    let base = spawn(server).await;
+    let client = reqwest::Client::new();
+
+    client
+        .post(format!("{}/runs", base))
+        .json(&json!({ "prompt": "What is the answer?" }))
+        .send()
+        .await
+        .unwrap();
+    client
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&json!({ "messages": [{ "role": "user", "content": "And again?" }] }))
+        .send()
+        .await
+        .unwrap();
+
+    let seen = seen.lock().unwrap();
+    assert!(seen[0].contains("What is the answer?"));
+    assert!(seen.iter().any(|step| step.contains("And again?")));
+    assert_eq!(
+        *finished.lock().unwrap(),
+        [
+            ("42".to_string(), seen.len() / 2),
+            ("42".to_string(), seen.len() / 2)
+        ]
+    );
+}
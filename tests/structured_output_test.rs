@@ -0,0 +1,405 @@
+mod common;
+
+use common::tool_call_body;
+use mockito::Matcher;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tiny_agent_rs::{
+    completion_schema, Agent, FunctionFactory, SchemaHandle, StructuredOutputStrategy,
+};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[completion_schema]
+struct Verdict {
+    /// Whether the request is approved
+    approved: bool,
+    /// Short justification
+    reason: String,
+}
+
+fn completion_body(content: &str) -> String {
+    json!({
+        "choices": [{
+            "message": {
+                "role": "assistant",
+                "content": content
+            }
+        }],
+        "usage": {
+            "prompt_tokens": 10,
+            "completion_tokens": 5,
+            "total_tokens": 15
+        }
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn test_native_strategy_sends_response_format() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": { "name": "Verdict", "strict": true }
+            }
+        })))
+        .with_body(completion_body(
+            r#"{"approved": true, "reason": "within budget"}"#,
+        ))
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_completion_schema::<Verdict>()
+        .with_structured_output(StructuredOutputStrategy::Native);
+
+    let result = agent.run_with_steps("Approve the request?").await.unwrap();
+    mock.assert_async().await;
+
+    let verdict = result.deserialize_structured::<Verdict>().unwrap();
+    assert!(verdict.approved);
+    assert_eq!(verdict.reason, "within budget");
+    assert!(result.is_success());
+}
+
+#[tokio::test]
+async fn test_prompt_strategy_retries_invalid_payload() {
+    let mut server = mockito::Server::new_async().await;
+    let invalid = server
+        .mock("POST", "/chat/completions")
+        .with_body(completion_body(r#"{"approved": "maybe"}"#))
+        .expect(1)
+        .create_async()
+        .await;
+
+    // The rejected reply is kept, followed by the correction as a system message
+    let valid = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex("does not match".to_string()),
+            Matcher::PartialJson(json!({
+                "messages": [
+                    { "role": "system" },
+                    { "role": "user", "content": "Approve the request?" },
+                    { "role": "assistant", "content": r#"{"approved": "maybe"}"# },
+                    { "role": "system" }
+                ]
+            })),
+        ]))
+        .with_body(completion_body(
+            "```json\n{\"approved\": false, \"reason\": \"missing receipts\"}\n```",
+        ))
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_completion_schema::<Verdict>()
+        .with_structured_output(StructuredOutputStrategy::Prompt)
+        .with_max_iterations(2);
+
+    let result = agent.run_with_steps("Approve the request?").await.unwrap();
+    invalid.assert_async().await;
+    valid.assert_async().await;
+
+    let verdict = result.deserialize_structured::<Verdict>().unwrap();
+    assert!(!verdict.approved);
+    assert_eq!(result.iterations, 2);
+    assert_eq!(result.errors().len(), 1);
+}
+
+#[test]
+fn test_custom_system_prompt_composes_schema_instructions() {
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_system_prompt("You review {{kind}} requests.")
+        .with_prompt_variable("kind", "expense")
+        .with_completion_schema::<Verdict>()
+        .with_structured_output(StructuredOutputStrategy::Native);
+
+    let prompt = agent.system_prompt();
+    assert!(prompt.starts_with("You review expense requests."));
+    assert_eq!(
+        prompt.matches("Structured response requirement:").count(),
+        1
+    );
+    assert!(!prompt.contains("final_answer"));
+}
+
+#[tokio::test]
+async fn test_runtime_json_schema_returns_validated_value() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "response_format": { "json_schema": { "name": "Ticket" } }
+        })))
+        .with_body(completion_body(
+            r#"{"priority": "high", "team": "billing"}"#,
+        ))
+        .create_async()
+        .await;
+
+    let schema = SchemaHandle::from_json_schema(
+        "Ticket",
+        json!({
+            "type": "object",
+            "properties": {
+                "priority": { "type": "string", "enum": ["low", "high"] },
+                "team": { "type": "string" }
+            },
+            "required": ["priority", "team"],
+            "additionalProperties": false
+        }),
+    )
+    .unwrap();
+
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_schema_handle(schema)
+        .with_structured_output(StructuredOutputStrategy::Native);
+
+    let result = agent
+        .run_with_steps("Triage: I was charged twice")
+        .await
+        .unwrap();
+    mock.assert_async().await;
+
+    assert_eq!(result.structured().unwrap()["team"], "billing");
+    assert!(result.schema().unwrap().is_runtime());
+    assert!(result.deserialize_structured::<Verdict>().is_err());
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct Reviewer {
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[completion_schema]
+struct Review {
+    score: u8,
+    reviewer: Reviewer,
+    note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[completion_schema]
+struct Release {
+    version: String,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[completion_schema]
+struct Labels {
+    labels: std::collections::HashMap<String, String>,
+}
+
+#[tokio::test]
+async fn test_native_strategy_sends_strict_schema() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "response_format": { "json_schema": { "schema": {
+                "additionalProperties": false,
+                "required": ["note", "reviewer", "score"],
+                "properties": {
+                    "note": { "type": ["string", "null"] },
+                    "score": { "type": "integer" },
+                    "reviewer": { "additionalProperties": false, "required": ["name"] }
+                }
+            } } }
+        })))
+        .with_body(completion_body(
+            r#"{"score": 4, "reviewer": {"name": "Ana"}, "note": null}"#,
+        ))
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_completion_schema::<Review>()
+        .with_structured_output(StructuredOutputStrategy::Native);
+
+    let result = agent.run_with_steps("Review the PR").await.unwrap();
+    mock.assert_async().await;
+    assert_eq!(result.deserialize_structured::<Review>().unwrap().score, 4);
+}
+
+#[tokio::test]
+async fn test_strict_incompatible_schema_fails_before_any_request() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/chat/completions")
+        .expect(0)
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_completion_schema::<Labels>()
+        .with_structured_output(StructuredOutputStrategy::Native);
+
+    let error = agent.check_structured_output().unwrap_err();
+    assert!(error.to_string().contains("#/properties/labels"));
+    assert_eq!(
+        agent
+            .run_with_steps("Label it")
+            .await
+            .unwrap_err()
+            .error_code(),
+        "CONFIG_ERROR"
+    );
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_native_strategy_accepts_nulls_for_optional_fields() {
+    let mut server = mockito::Server::new_async().await;
+    let typed = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "response_format": { "json_schema": { "name": "Release" } }
+        })))
+        .with_body(completion_body(
+            r#"{"version": "1.2.0", "notes": null, "tags": null}"#,
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_completion_schema::<Release>()
+        .with_structured_output(StructuredOutputStrategy::Native);
+
+    let result = agent.run_with_steps("Describe the release").await.unwrap();
+    typed.assert_async().await;
+    assert_eq!(result.structured().unwrap(), &json!({ "version": "1.2.0" }));
+    let release = result.deserialize_structured::<Release>().unwrap();
+    assert_eq!(release.version, "1.2.0");
+    assert!(release.notes.is_empty() && release.tags.is_empty());
+
+    let runtime = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "response_format": { "json_schema": { "name": "Contact" } }
+        })))
+        .with_body(completion_body(r#"{"name": "Ana", "phone": null}"#))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let schema = SchemaHandle::from_json_schema(
+        "Contact",
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "phone": { "type": "string" }
+            },
+            "required": ["name"]
+        }),
+    )
+    .unwrap();
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_schema_handle(schema)
+        .with_structured_output(StructuredOutputStrategy::Native);
+
+    let result = agent.run_with_steps("Who called?").await.unwrap();
+    runtime.assert_async().await;
+    assert_eq!(result.structured().unwrap(), &json!({ "name": "Ana" }));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+#[completion_schema]
+enum Priority {
+    Low,
+    High,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[completion_schema]
+struct Range(u32, u32);
+
+#[tokio::test]
+async fn test_enum_and_tuple_payloads_are_accepted() {
+    let mut server = mockito::Server::new_async().await;
+    let enum_reply = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex("Rate the ticket".to_string()))
+        .with_body(completion_body(r#""high""#))
+        .expect(1)
+        .create_async()
+        .await;
+    let tuple_reply = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex("Pick a range".to_string()))
+        .with_body(completion_body("[1, 5]"))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_completion_schema::<Priority>()
+        .with_structured_output(StructuredOutputStrategy::Prompt);
+    let result = agent.run_with_steps("Rate the ticket").await.unwrap();
+    assert_eq!(
+        result.deserialize_structured::<Priority>().unwrap(),
+        Priority::High
+    );
+
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_completion_schema::<Range>()
+        .with_structured_output(StructuredOutputStrategy::Prompt);
+    let result = agent.run_with_steps("Pick a range").await.unwrap();
+    assert_eq!(
+        result.deserialize_structured::<Range>().unwrap(),
+        Range(1, 5)
+    );
+
+    // The structured_response tool takes the same shapes
+    let tool_reply = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex("Pick another range".to_string()))
+        .with_body(tool_call_body(
+            "call_1",
+            "structured_response",
+            json!({ "structured": [2, 8] }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let agent = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_completion_schema::<Range>();
+    let result = agent.run_with_steps("Pick another range").await.unwrap();
+    assert_eq!(
+        result.deserialize_structured::<Range>().unwrap(),
+        Range(2, 8)
+    );
+
+    enum_reply.assert_async().await;
+    tuple_reply.assert_async().await;
+    tool_reply.assert_async().await;
+
+    // Strict outputs need an object at the root, so the native strategy refuses these up front
+    let native = Agent::new("test-key".to_string(), FunctionFactory::new())
+        .with_base_url(server.url())
+        .with_completion_schema::<Priority>()
+        .with_structured_output(StructuredOutputStrategy::Native);
+    let error = native.run_with_steps("Rate the ticket").await.unwrap_err();
+    assert!(error.to_string().contains("must be an object"));
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::{tool_call_body, tool_call_body_with_usage};
+use mockito::Matcher;
+use serde_json::json;
+use tiny_agent_rs::{Agent, AgentError, AgentStep, FunctionFactory, Team};
+
+#[tokio::test]
+async fn test_triage_hands_conversation_to_specialist() {
+    let mut triage_server = mockito::Server::new_async().await;
+    let mut billing_server = mockito::Server::new_async().await;
+
+    let triage = triage_server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex("You route customers".to_string()),
+            Matcher::Regex(r#""name":"transfer_to_billing""#.to_string()),
+        ]))
+        .with_body(tool_call_body_with_usage(
+            "call_1",
+            "transfer_to_billing",
+            json!({"reason": "refund request"}),
+            5,
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let billing = billing_server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex("You handle refunds".to_string()),
+            Matcher::Regex("I was charged twice".to_string()),
+            Matcher::Regex(r#""tool_call_id":"call_1""#.to_string()),
+            Matcher::Regex(r#""name":"transfer_to_triage""#.to_string()),
+        ]))
+        .with_body(tool_call_body_with_usage(
+            "call_2",
+            "final_answer",
+            json!({"answer": "Your duplicate charge has been refunded."}),
+            5,
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let team = Team::new()
+        .with_agent(
+            "triage",
+            "Routes customers to the right specialist",
+            Agent::new("key".to_string(), FunctionFactory::new())
+                .with_base_url(triage_server.url())
+                .with_system_prompt("You route customers."),
+        )
+        .with_agent(
+            "billing",
+            "Handles refunds and invoices",
+            Agent::new("key".to_string(), FunctionFactory::new())
+                .with_base_url(billing_server.url())
+                .with_system_prompt("You handle refunds."),
+        );
+
+    let result = team.run("I was charged twice").await.unwrap();
+    triage.assert_async().await;
+    billing.assert_async().await;
+
+    assert_eq!(result.output, "Your duplicate charge has been refunded.");
+    assert_eq!(result.agents(), vec!["triage", "billing"]);
+    assert!(result.steps.iter().any(|step| matches!(
+        step,
+        AgentStep::Handoff { reason: Some(reason), .. } if reason == "refund request"
+    )));
+    assert_eq!(result.iterations, 2);
+    assert_eq!(result.tokens.as_ref().unwrap().total_tokens, 20);
+}
+
+#[tokio::test]
+async fn test_handoff_limit_stops_ping_pong() {
+    let mut server = mockito::Server::new_async().await;
+    let _to_b = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex("You are A".to_string()))
+        .with_body(tool_call_body("call_a", "transfer_to_b", json!({})))
+        .create_async()
+        .await;
+    let _to_a = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex("You are B".to_string()))
+        .with_body(tool_call_body("call_b", "transfer_to_a", json!({})))
+        .create_async()
+        .await;
+
+    let agent = |prompt: &str| {
+        Agent::new("key".to_string(), FunctionFactory::new())
+            .with_base_url(server.url())
+            .with_system_prompt(prompt.to_string())
+    };
+    let team = Team::new()
+        .with_agent("a", "First", agent("You are A."))
+        .with_agent("b", "Second", agent("You are B."))
+        .with_max_handoffs(2);
+
+    let error = team.run("loop forever").await.unwrap_err();
+    assert!(matches!(error, AgentError::MaxHandoffs(2)));
+    assert_eq!(error.error_code(), "MAX_HANDOFFS_EXCEEDED");
+}
+
+#[tokio::test]
+async fn test_unknown_entry_is_a_config_error() {
+    let team = Team::new()
+        .with_agent(
+            "triage",
+            "Routes customers",
+            Agent::new("key".to_string(), FunctionFactory::new())
+                .with_base_url("http://127.0.0.1:9"),
+        )
+        .with_entry("billing");
+
+    let error = team.run("I was charged twice").await.unwrap_err();
+    assert!(matches!(error, AgentError::Config(ref message) if message.contains("billing")));
+}
+
+#[tokio::test]
+async fn test_members_with_the_same_handoff_tool_are_a_config_error() {
+    let agent = || {
+        Agent::new("key".to_string(), FunctionFactory::new()).with_base_url("http://127.0.0.1:9")
+    };
+    let team = Team::new()
+        .with_agent("triage", "Routes customers", agent())
+        .with_agent("Research Bot", "Looks things up", agent())
+        .with_agent("research-bot", "Also looks things up", agent());
+
+    let error = team.run("Find my order").await.unwrap_err();
+    assert!(
+        matches!(error, AgentError::Config(ref message) if message.contains("transfer_to_research_bot"))
+    );
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use common::tool_call_body;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tiny_agent_rs::{
+    tools::tool, Agent, AgentError, AgentStep, CancellationToken, FunctionFactory, Tool,
+    ToolContext,
+};
+
+struct Tenant(&'static str);
+
+/// What the `inspect` tool saw of its context
+#[derive(Debug, Default)]
+struct Seen {
+    run_id: String,
+    tool_call_id: String,
+    iteration: usize,
+    prior_steps: usize,
+    tenant: Option<&'static str>,
+}
+
+/// Record the context of the call and report progress
+#[tool]
+async fn inspect(
+    #[state] seen: &Arc<Mutex<Seen>>,
+    context: &ToolContext,
+) -> Result<&'static str, String> {
+    context.progress("halfway", Some(json!({ "percent": 50 })));
+    *seen.lock().unwrap() = Seen {
+        run_id: context.run_id().to_string(),
+        tool_call_id: context.tool_call_id().to_string(),
+        iteration: context.iteration(),
+        prior_steps: context.steps().len(),
+        tenant: context.get::<Tenant>().map(|tenant| tenant.0),
+    };
+    Ok("done")
+}
+
+/// Cancel the run it is part of
+#[tool]
+async fn stop(#[state] token: &CancellationToken) -> Result<&'static str, String> {
+    token.cancel();
+    Ok("stopping")
+}
+
+#[tokio::test]
+async fn test_tools_receive_run_context_and_report_progress() {
+    let mut server = mockito::Server::new_async().await;
+    let first = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body("call_1", "inspect", json!({})))
+        .expect(1)
+        .create_async()
+        .await;
+    let second = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_2",
+            "final_answer",
+            json!({ "answer": "ok" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let seen = Arc::new(Mutex::new(Seen::default()));
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(Inspect::new(seen.clone())).unwrap();
+    let agent = Agent::new("test-key".to_string(), factory)
+        .with_base_url(server.url())
+        .with_user_data(Tenant("acme"));
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let result = agent
+        .run_with_step_sender("Inspect the context", sender)
+        .await
+        .unwrap();
+    first.assert_async().await;
+    second.assert_async().await;
+
+    let seen = seen.lock().unwrap();
+    assert!(!result.run_id.is_empty());
+    assert_eq!(seen.run_id, result.run_id);
+    assert_eq!(seen.tool_call_id, "call_1");
+    assert_eq!(seen.iteration, 1);
+    // The task and the tool's own action
+    assert_eq!(seen.prior_steps, 2);
+    assert_eq!(seen.tenant, Some("acme"));
+
+    // Progress sits between the action and its observation, in the trace and the live stream
+    assert!(
+        matches!(result.steps[2], AgentStep::Progress { ref message, .. } if message == "halfway")
+    );
+    assert!(matches!(result.steps[3], AgentStep::Observation { .. }));
+
+    let mut streamed = Vec::new();
+    while let Ok(step) = receiver.try_recv() {
+        streamed.push(step);
+    }
+    let progress = streamed
+        .iter()
+        .filter(|step| matches!(step, AgentStep::Progress { .. }))
+        .count();
+    assert_eq!(progress, 1);
+
+    // Progress is trace-only
+    assert!(result.steps[2].to_messages().is_empty());
+}
+
+#[tokio::test]
+async fn test_cancelled_run_stops_before_the_next_request() {
+    let mut server = mockito::Server::new_async().await;
+    let first = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body("call_1", "stop", json!({})))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let token = CancellationToken::new();
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(Stop::new(token.clone())).unwrap();
+    let agent = Agent::new("test-key".to_string(), factory).with_base_url(server.url());
+
+    let error = agent
+        .run_with_cancellation("Stop", token.clone())
+        .await
+        .unwrap_err();
+    first.assert_async().await;
+    assert!(matches!(error, AgentError::Cancelled(_)));
+    assert_eq!(error.error_code(), "CANCELLED");
+    assert!(token.is_cancelled());
+
+    // The cancelled token belonged to that run only; the next run starts fresh
+    let answer = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_2",
+            "final_answer",
+            json!({ "answer": "Still here" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let result = agent.run_with_steps("Hello again").await.unwrap();
+    answer.assert_async().await;
+    assert_eq!(result.output, "Still here");
+}
+
+#[tokio::test]
+async fn test_context_tools_still_run_without_an_agent() {
+    let seen = Arc::new(Mutex::new(Seen::default()));
+    let tool = Inspect::new(seen.clone());
+
+    assert_eq!(tool.execute(json!({})).await.unwrap(), "done");
+    assert_eq!(seen.lock().unwrap().run_id, "");
+
+    tool.execute_with_context(json!({}), ToolContext::new("run_test", "call_test"))
+        .await
+        .unwrap();
+    assert_eq!(seen.lock().unwrap().run_id, "run_test");
+    assert_eq!(seen.lock().unwrap().tool_call_id, "call_test");
+}
@@ -0,0 +1,343 @@
+mod common;
+
+use common::tool_call_body;
+use mockito::Matcher;
+use serde_json::{json, Value};
+use tiny_agent_rs::{
+    tools::{CalculatorTool, NamespacedTool, WeatherTool},
+    Agent, AgentError, AgentMemory, AgentStep, FunctionFactory, Tool, ToolChoice, ToolSelection,
+};
+
+fn namespaced_factory() -> FunctionFactory {
+    let mut factory = FunctionFactory::new();
+    factory
+        .register_tool(NamespacedTool::new("math", CalculatorTool::new()))
+        .unwrap();
+    factory
+        .register_tool(NamespacedTool::new("web", WeatherTool::new()))
+        .unwrap();
+    factory
+}
+
+fn function_names(tools: &[Value]) -> Vec<&str> {
+    tools
+        .iter()
+        .filter_map(|tool| tool["function"]["name"].as_str())
+        .collect()
+}
+
+/// Whether each observation in the run (including the final answer) was an error, in order
+fn observation_errors(steps: &[AgentStep]) -> Vec<bool> {
+    steps
+        .iter()
+        .filter_map(|step| match step {
+            AgentStep::Observation { is_error, .. } => Some(*is_error),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_register_replace_and_unregister_report_collisions() {
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(CalculatorTool::new()).unwrap();
+
+    let error = factory.register_tool(CalculatorTool::new()).unwrap_err();
+    assert!(matches!(error, AgentError::Config(ref message) if message.contains("calculator")));
+
+    let error = factory.replace_tool(WeatherTool::new()).unwrap_err();
+    assert!(matches!(error, AgentError::ToolNotFound(ref name) if name == "weather"));
+    let replaced = factory.replace_tool(CalculatorTool::new()).unwrap();
+    assert_eq!(replaced.name(), "calculator");
+
+    let removed = factory.unregister_tool("calculator").unwrap();
+    assert_eq!(removed.name(), "calculator");
+    assert!(!factory.has_function("calculator"));
+    assert!(factory.unregister_tool("calculator").is_none());
+    assert!(factory.tool_names().is_empty());
+}
+
+#[tokio::test]
+async fn test_tools_keep_registration_order() {
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(WeatherTool::new()).unwrap();
+    factory
+        .register_tool(NamespacedTool::new("math", CalculatorTool::new()))
+        .unwrap();
+    factory.register_tool(CalculatorTool::new()).unwrap();
+    assert_eq!(
+        function_names(&factory.get_openai_tools()),
+        ["weather", "math__calculator", "calculator"]
+    );
+
+    // Replacing a tool keeps its place; a removed tool's name goes to the end when re-added
+    factory.replace_tool(WeatherTool::new()).unwrap();
+    assert!(factory
+        .register_tool(NamespacedTool::new("math", CalculatorTool::new()))
+        .is_err());
+    assert_eq!(
+        factory.tool_names(),
+        ["weather", "math.calculator", "calculator"]
+    );
+    factory.unregister_tool("weather");
+    factory.register_tool(WeatherTool::new()).unwrap();
+    assert_eq!(
+        factory.tool_names(),
+        ["math.calculator", "calculator", "weather"]
+    );
+}
+
+#[tokio::test]
+async fn test_namespaced_tools_use_wire_safe_function_names() {
+    let mut factory = namespaced_factory();
+
+    assert_eq!(factory.tool_names(), ["math.calculator", "web.weather"]);
+    assert_eq!(
+        function_names(&factory.get_openai_tools()),
+        ["math__calculator", "web__weather"]
+    );
+
+    // The model calls the function name; callers may use either form
+    let result = factory
+        .execute_function(
+            "math__calculator",
+            json!({ "operation": "add", "a": 2.0, "b": 3.0 }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["result"], 5.0);
+    assert!(factory.has_function("math.calculator"));
+
+    // A plain tool whose function name matches a namespaced one is a collision
+    #[derive(Debug)]
+    struct Clash;
+    impl Tool for Clash {
+        fn name(&self) -> &str {
+            "web__weather"
+        }
+        fn description(&self) -> &str {
+            "Clashes with web.weather"
+        }
+        fn parameters_schema(&self) -> Value {
+            json!({ "type": "object" })
+        }
+        fn execute(
+            &self,
+            _parameters: Value,
+        ) -> std::pin::Pin<
+            Box<dyn std::future::Future<Output = Result<Value, AgentError>> + Send + '_>,
+        > {
+            Box::pin(async { Ok(json!({})) })
+        }
+    }
+    assert!(factory.register_tool(Clash).is_err());
+
+    // The refused tool does not replace the namespaced one, and cannot be swapped in either
+    assert_eq!(
+        factory.tool("web__weather").unwrap().description(),
+        WeatherTool::new().description()
+    );
+    assert!(factory.replace_tool(Clash).is_err());
+
+    let selection = ToolSelection::only(["web.*"]);
+    assert_eq!(
+        function_names(&factory.get_selected_openai_tools(&selection)),
+        ["web__weather"]
+    );
+}
+
+#[tokio::test]
+async fn test_selector_limits_tools_per_iteration() {
+    let mut server = mockito::Server::new_async().await;
+    let calls = [
+        tool_call_body("call_1", "web__weather", json!({ "location": "Oslo" })),
+        tool_call_body("call_2", "web__weather", json!({ "location": "Oslo" })),
+        tool_call_body("call_3", "final_answer", json!({ "answer": "Cold" })),
+    ];
+    let mut mocks = Vec::new();
+    for body in calls {
+        mocks.push(
+            server
+                .mock("POST", "/chat/completions")
+                .with_body(body)
+                .expect(1)
+                .create_async()
+                .await,
+        );
+    }
+
+    // Only the math tools on the first iteration, everything afterwards
+    let agent = Agent::new("test-key".to_string(), namespaced_factory())
+        .with_base_url(server.url())
+        .with_tool_selector(|iteration| match iteration {
+            1 => ToolSelection::only(["math.*"]),
+            _ => ToolSelection::all(),
+        });
+
+    let result = agent.run_with_steps("Weather in Oslo?").await.unwrap();
+    for mock in &mocks {
+        mock.assert_async().await;
+    }
+
+    assert_eq!(observation_errors(&result.steps), [true, false, false]);
+    assert!(result.errors()[0].contains("web__weather"));
+}
+
+#[tokio::test]
+async fn test_memory_selection_narrows_a_single_run() {
+    let mut server = mockito::Server::new_async().await;
+    let first = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_1",
+            "math__calculator",
+            json!({ "operation": "add", "a": 1.0, "b": 1.0 }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let second = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_2",
+            "final_answer",
+            json!({ "answer": "No math today" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), namespaced_factory())
+        .with_base_url(server.url())
+        .with_tool_selection(ToolSelection::only(["math.*", "web.*"]));
+
+    let mut memory = AgentMemory::with_default_system();
+    memory.add_step(AgentStep::Task {
+        content: "What is 1 + 1?".to_string(),
+        attachments: Vec::new(),
+    });
+    memory.set_tool_selection(ToolSelection::only(["web.*"]));
+
+    let result = agent.run_with_memory(&mut memory).await.unwrap();
+    first.assert_async().await;
+    second.assert_async().await;
+
+    assert_eq!(observation_errors(&result.steps), [true, false]);
+}
+
+#[tokio::test]
+async fn test_tool_choice_can_be_forced_per_iteration() {
+    let mut server = mockito::Server::new_async().await;
+    let forced = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "tool_choice": { "type": "function", "function": { "name": "web__weather" } }
+        })))
+        .with_body(tool_call_body(
+            "call_1",
+            "web__weather",
+            json!({ "location": "Oslo" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let required = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({ "tool_choice": "required" })))
+        .with_body(tool_call_body(
+            "call_2",
+            "final_answer",
+            json!({ "answer": "Cold" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), namespaced_factory())
+        .with_base_url(server.url())
+        .with_tool_choice(ToolChoice::Required)
+        .with_iteration_tool_choice(1, ToolChoice::tool("web.weather"));
+
+    agent.run_with_steps("Weather in Oslo?").await.unwrap();
+    forced.assert_async().await;
+    required.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_forcing_a_tool_that_is_not_offered_fails() {
+    let agent = Agent::new("test-key".to_string(), namespaced_factory())
+        .with_base_url("http://127.0.0.1:9")
+        .with_tool_selection(ToolSelection::only(["math.*"]))
+        .with_tool_choice(ToolChoice::tool("web.weather"));
+
+    let error = agent.run_with_steps("Weather in Oslo?").await.unwrap_err();
+    assert!(matches!(error, AgentError::Config(ref message) if message.contains("web__weather")));
+}
+
+#[tokio::test]
+async fn test_plain_reply_is_the_answer_when_tools_are_turned_off() {
+    let mut server = mockito::Server::new_async().await;
+    let reply = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({ "tool_choice": "none" })))
+        .with_body(
+            json!({
+                "choices": [{ "message": { "role": "assistant", "content": "It is cold" } }]
+            })
+            .to_string(),
+        )
+        .expect(2)
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), namespaced_factory())
+        .with_base_url(server.url())
+        .with_tool_choice(ToolChoice::None);
+
+    let result = agent.run_with_steps("Weather in Oslo?").await.unwrap();
+    assert_eq!(result.output, "It is cold");
+    assert!(matches!(
+        result.steps.last(),
+        Some(AgentStep::FinalAnswer { answer, .. }) if answer == "It is cold"
+    ));
+    assert_eq!(agent.run("Weather in Oslo?").await.unwrap(), "It is cold");
+    reply.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_agent_wide_forced_tool_reverts_to_auto_after_its_call() {
+    let mut server = mockito::Server::new_async().await;
+    let forced = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({
+            "tool_choice": { "type": "function", "function": { "name": "web__weather" } }
+        })))
+        .with_body(tool_call_body(
+            "call_1",
+            "web__weather",
+            json!({ "location": "Oslo" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let auto = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::PartialJson(json!({ "tool_choice": "auto" })))
+        .with_body(tool_call_body(
+            "call_2",
+            "final_answer",
+            json!({ "answer": "Cold" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), namespaced_factory())
+        .with_base_url(server.url())
+        .with_tool_choice(ToolChoice::tool("web.weather"));
+
+    let result = agent.run_with_steps("Weather in Oslo?").await.unwrap();
+    forced.assert_async().await;
+    auto.assert_async().await;
+    assert_eq!(result.output, "Cold");
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::tool_call_body;
+use mockito::Matcher;
+use serde_json::json;
+use tiny_agent_rs::{
+    tools::{tool, Artifact},
+    Agent, AgentError, AgentStep, FunctionFactory, ToolError, ToolResult,
+};
+
+/// Export the rows of a table
+#[tool]
+async fn export_rows(table: String) -> Result<ToolResult, ToolError> {
+    match table.as_str() {
+        "orders" => Ok(ToolResult::text("Exported 2 rows")
+            .with_structured(json!({ "rows": [{ "id": 1 }, { "id": 2 }] }))
+            .with_artifact(Artifact::from_bytes(
+                "orders.csv",
+                "text/csv",
+                b"id\n1\n2\n",
+            ))),
+        "locked" => Err(ToolError::fatal("the database is read-only")),
+        other => Err(ToolError::user_fixable(format!("unknown table {other}"))),
+    }
+}
+
+fn agent(server: &mockito::Server) -> Agent {
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(ExportRows::new()).unwrap();
+    Agent::new("test-key".to_string(), factory).with_base_url(server.url())
+}
+
+#[tokio::test]
+async fn test_model_sees_text_while_caller_keeps_data_and_artifacts() {
+    let mut server = mockito::Server::new_async().await;
+    let export = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_1",
+            "export_rows",
+            json!({ "table": "orders" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    // The follow-up request carries the text and artifact note, not the data
+    let answer = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex("Exported 2 rows".to_string()),
+            Matcher::Regex(r"orders\.csv \(text/csv, 7 bytes\)".to_string()),
+        ]))
+        .with_body(tool_call_body(
+            "call_2",
+            "final_answer",
+            json!({ "answer": "Done" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let result = agent(&server)
+        .run_with_steps("Export orders")
+        .await
+        .unwrap();
+    export.assert_async().await;
+    answer.assert_async().await;
+
+    let observation = result
+        .steps
+        .iter()
+        .find_map(|step| match step {
+            AgentStep::Observation {
+                result, structured, ..
+            } => Some((result, structured)),
+            _ => None,
+        })
+        .unwrap();
+    assert!(observation.0.starts_with("Exported 2 rows"));
+    assert!(!observation.0.contains("\"id\""));
+    assert_eq!(observation.1.as_ref().unwrap()["rows"][1]["id"], 2);
+
+    let artifacts = result.artifacts();
+    assert_eq!(artifacts.len(), 1);
+    assert_eq!(artifacts[0].bytes().unwrap(), b"id\n1\n2\n");
+}
+
+#[tokio::test]
+async fn test_user_fixable_error_goes_back_to_the_model_with_its_kind() {
+    let mut server = mockito::Server::new_async().await;
+    let export = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_1",
+            "export_rows",
+            json!({ "table": "users" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let answer = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex(
+            r#"\\"kind\\":\\"user_fixable\\""#.to_string(),
+        ))
+        .with_body(tool_call_body(
+            "call_2",
+            "final_answer",
+            json!({ "answer": "No such table" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let result = agent(&server).run_with_steps("Export users").await.unwrap();
+    export.assert_async().await;
+    answer.assert_async().await;
+    assert_eq!(result.errors().len(), 1);
+}
+
+#[tokio::test]
+async fn test_fatal_error_aborts_the_run() {
+    let mut server = mockito::Server::new_async().await;
+    let export = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_1",
+            "export_rows",
+            json!({ "table": "locked" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let error = agent(&server)
+        .run_with_steps("Export locked")
+        .await
+        .unwrap_err();
+    export.assert_async().await;
+
+    assert!(error.is_fatal());
+    assert_eq!(error.error_code(), "TOOL_FATAL_ERROR");
+    assert!(matches!(
+        error,
+        AgentError::ToolFailed(ToolError { ref message, .. }) if message == "the database is read-only"
+    ));
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use common::tool_call_body;
+use mockito::Matcher;
+use serde_json::json;
+use std::{future::Future, pin::Pin};
+use tiny_agent_rs::{
+    tools::{
+        CalculatorTool, NamespacedTool, ToolDocument, ToolRetrieval, ToolRetriever, WeatherTool,
+    },
+    Agent, AgentStep, FunctionFactory, ToolSelection,
+};
+
+fn catalog() -> FunctionFactory {
+    let mut factory = FunctionFactory::new();
+    factory.register_tool(CalculatorTool::new()).unwrap();
+    factory.register_tool(WeatherTool::new()).unwrap();
+    factory
+        .register_tool(NamespacedTool::new("travel", WeatherTool::new()))
+        .unwrap();
+    factory
+}
+
+/// Prefers tools with the longest names, to show any ranking can be plugged in
+#[derive(Debug)]
+struct LongestName;
+
+impl ToolRetriever for LongestName {
+    fn score<'a>(
+        &'a self,
+        _query: &'a str,
+        documents: &'a [ToolDocument],
+    ) -> Pin<Box<dyn Future<Output = tiny_agent_rs::Result<Vec<f64>>> + Send + 'a>> {
+        Box::pin(async move {
+            Ok(documents
+                .iter()
+                .map(|document| document.name.len() as f64)
+                .collect())
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_retrieval_ranks_the_catalog_against_the_task() {
+    let factory = catalog();
+    let retrieval = ToolRetrieval::new(2);
+
+    let found = retrieval
+        .retrieve(
+            &factory,
+            "Will it rain in Oslo? Check the weather",
+            &ToolSelection::all(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(found, ["weather", "travel.weather"]);
+
+    let found = retrieval
+        .retrieve(&factory, "multiply two numbers", &ToolSelection::all())
+        .await
+        .unwrap();
+    assert_eq!(found, ["calculator"]);
+
+    // Only candidates are ranked
+    let found = retrieval
+        .retrieve(&factory, "weather", &ToolSelection::only(["travel.*"]))
+        .await
+        .unwrap();
+    assert_eq!(found, ["travel.weather"]);
+
+    let found = ToolRetrieval::new(1)
+        .with_retriever(LongestName)
+        .retrieve(&factory, "anything", &ToolSelection::all())
+        .await
+        .unwrap();
+    assert_eq!(found, ["travel.weather"]);
+}
+
+#[tokio::test]
+async fn test_model_searches_for_tools_that_were_not_offered() {
+    let mut server = mockito::Server::new_async().await;
+    // Only the weather tools match the task, so the calculator is not callable yet
+    let first = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex(r#""name":"search_tools""#.to_string()))
+        .with_body(tool_call_body(
+            "call_1",
+            "calculator",
+            json!({ "operation": "add", "a": 20.0, "b": 2.0 }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let search = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_2",
+            "search_tools",
+            json!({ "query": "arithmetic calculator" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let calculate = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex(r#"\\"name\\":\\"calculator\\""#.to_string()))
+        .with_body(tool_call_body(
+            "call_3",
+            "calculator",
+            json!({ "operation": "add", "a": 20.0, "b": 2.0 }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+    let answer = server
+        .mock("POST", "/chat/completions")
+        .with_body(tool_call_body(
+            "call_4",
+            "final_answer",
+            json!({ "answer": "22 degrees" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), catalog())
+        .with_base_url(server.url())
+        .with_tool_retrieval(ToolRetrieval::new(2));
+
+    let result = agent
+        .run_with_steps("What is the weather in Oslo plus 2?")
+        .await
+        .unwrap();
+    for mock in [&first, &search, &calculate, &answer] {
+        mock.assert_async().await;
+    }
+
+    let observations: Vec<(&str, bool)> = result
+        .steps
+        .iter()
+        .filter_map(|step| match step {
+            AgentStep::Observation {
+                result, is_error, ..
+            } => Some((result.as_str(), *is_error)),
+            _ => None,
+        })
+        .collect();
+    assert!(observations[0].1, "the calculator was not offered yet");
+    assert!(observations[1].0.contains("calculator"));
+    assert!(!observations[2].1);
+    assert!(observations[2].0.contains("22"));
+}
+
+#[tokio::test]
+async fn test_message_runs_retrieve_tools_for_the_latest_turn() {
+    let mut server = mockito::Server::new_async().await;
+    // Only one tool is offered, so the request shows which turn it was ranked against
+    let answer = server
+        .mock("POST", "/chat/completions")
+        .match_body(Matcher::Regex(r#""name":"calculator""#.to_string()))
+        .with_body(tool_call_body(
+            "call_1",
+            "final_answer",
+            json!({ "answer": "42" }),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let agent = Agent::new("test-key".to_string(), catalog())
+        .with_base_url(server.url())
+        .with_tool_retrieval(ToolRetrieval::new(1));
+
+    let answer_text = agent
+        .run_with_messages(vec![
+            json!({ "role": "user", "content": "Will it rain in Oslo? Check the weather" }),
+            json!({ "role": "assistant", "content": "Light rain is expected." }),
+            json!({ "role": "user", "content": "Now multiply two numbers: 6 and 7" }),
+        ])
+        .await
+        .unwrap();
+    answer.assert_async().await;
+    assert_eq!(answer_text, "42");
+}
@@ -1,36 +1,68 @@
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::quote;
-use syn::{parse_macro_input, spanned::Spanned, ItemStruct, LitStr};
+use syn::{parse_macro_input, Attribute, Generics, Ident, Item, LitStr};
 
 use crate::schema_extraction::{
-    collect_doc_comments, collect_field_docs, ensure_named_struct, infer_description,
-    infer_schema_name, parse_completion_schema_args,
+    collect_doc_comments, collect_field_docs, collect_variant_docs, completion_schema_generics,
+    ensure_supported_struct, infer_description, infer_schema_name, parse_completion_schema_args,
 };
 
+/// Parts of the annotated struct or enum the generated impl needs
+struct SchemaItem<'a> {
+    ident: &'a Ident,
+    attrs: &'a [Attribute],
+    generics: &'a Generics,
+    field_docs: Vec<(String, String)>,
+    variant_docs: Vec<(String, String)>,
+}
+
 pub fn completion_schema(attr: TokenStream, item: TokenStream) -> TokenStream {
     let args = match parse_completion_schema_args(attr) {
         Ok(args) => args,
         Err(err) => return err.to_compile_error().into(),
     };
 
-    let item_struct = parse_macro_input!(item as ItemStruct);
+    let item = parse_macro_input!(item as Item);
 
-    if let Err(err) = ensure_named_struct(&item_struct) {
-        return err.to_compile_error().into();
-    }
+    let schema_item = match &item {
+        Item::Struct(item_struct) => {
+            if let Err(err) = ensure_supported_struct(item_struct) {
+                return err.to_compile_error().into();
+            }
+            SchemaItem {
+                ident: &item_struct.ident,
+                attrs: &item_struct.attrs,
+                generics: &item_struct.generics,
+                field_docs: collect_field_docs(item_struct),
+                variant_docs: Vec::new(),
+            }
+        }
+        Item::Enum(item_enum) => SchemaItem {
+            ident: &item_enum.ident,
+            attrs: &item_enum.attrs,
+            generics: &item_enum.generics,
+            field_docs: Vec::new(),
+            variant_docs: collect_variant_docs(item_enum),
+        },
+        other => {
+            return syn::Error::new_spanned(
+                other,
+                "`#[completion_schema]` only supports structs and enums",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
 
-    if !item_struct.generics.params.is_empty() {
-        return syn::Error::new(
-            item_struct.generics.span(),
-            "`#[completion_schema]` does not currently support generic structs",
-        )
-        .to_compile_error()
-        .into();
-    }
+    let generics = match completion_schema_generics(schema_item.generics) {
+        Ok(generics) => generics,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let is_generic = !generics.params.is_empty();
 
-    let schema_name = infer_schema_name(&item_struct, args.name.as_ref());
-    let struct_docs = collect_doc_comments(&item_struct.attrs);
+    let ident = schema_item.ident;
+    let struct_docs = collect_doc_comments(schema_item.attrs);
     let description = infer_description(args.description.as_ref(), struct_docs);
 
     let description_tokens = description
@@ -38,42 +70,76 @@ pub fn completion_schema(attr: TokenStream, item: TokenStream) -> TokenStream {
         .map(|lit| quote! { Some(#lit) })
         .unwrap_or_else(|| quote! { None });
 
-    let field_docs = collect_field_docs(&item_struct);
-    let field_doc_tokens: Vec<_> = field_docs
-        .iter()
-        .map(|(field, doc)| {
-            let field_lit = LitStr::new(field, Span::call_site());
-            let doc_lit = LitStr::new(doc, Span::call_site());
-            quote! { (#field_lit, #doc_lit) }
-        })
-        .collect();
+    let field_doc_tokens = doc_pairs(&schema_item.field_docs);
+    let variant_doc_tokens = doc_pairs(&schema_item.variant_docs);
+
+    // Each instantiation of a generic type gets its own name, e.g. `Page_for_Invoice`
+    let (schema_name, type_name) = match (&args.name, is_generic) {
+        (None, true) => (
+            quote! { <Self as schemars::JsonSchema>::schema_name() },
+            quote! { std::any::type_name::<Self>() },
+        ),
+        (explicit, _) => {
+            let schema_name = infer_schema_name(ident, explicit.as_ref());
+            let type_name = LitStr::new(&ident.to_string(), Span::call_site());
+            (quote! { #schema_name }, quote! { #type_name })
+        }
+    };
 
-    let type_name = LitStr::new(&item_struct.ident.to_string(), Span::call_site());
-    let ident = &item_struct.ident;
+    let build_handle = quote! {
+        let schema_name = #schema_name;
+        let mut root = schemars::schema_for!(Self);
+        tiny_agent_rs::schema::apply_doc_comments(
+            &mut root,
+            &schema_name,
+            #description_tokens,
+            &[#(#field_doc_tokens),*],
+        );
+        tiny_agent_rs::schema::apply_variant_doc_comments(
+            &mut root,
+            &[#(#variant_doc_tokens),*],
+        );
+        tiny_agent_rs::schema::SchemaHandle::from_root_schema::<Self>(
+            schema_name,
+            #type_name,
+            root,
+        )
+    };
+
+    // A `static` inside a generic impl is shared by every instantiation, so generic types are
+    // cached per concrete type instead
+    let schema_body = if is_generic {
+        quote! {
+            tiny_agent_rs::schema::cached_schema_handle::<Self>(|| { #build_handle })
+        }
+    } else {
+        quote! {
+            static HANDLE: std::sync::OnceLock<tiny_agent_rs::schema::SchemaHandle> = std::sync::OnceLock::new();
+            HANDLE.get_or_init(|| { #build_handle })
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let expanded = quote! {
-        #item_struct
+        #item
 
-        impl tiny_agent_rs::schema::CompletionSchema for #ident {
+        impl #impl_generics tiny_agent_rs::schema::CompletionSchema for #ident #ty_generics #where_clause {
             fn schema() -> &'static tiny_agent_rs::schema::SchemaHandle {
-                static HANDLE: std::sync::OnceLock<tiny_agent_rs::schema::SchemaHandle> = std::sync::OnceLock::new();
-                HANDLE.get_or_init(|| {
-                    let mut root = schemars::schema_for!(Self);
-                    tiny_agent_rs::schema::apply_doc_comments(
-                        &mut root,
-                        #schema_name,
-                        #description_tokens,
-                        &[#(#field_doc_tokens),*],
-                    );
-                    tiny_agent_rs::schema::SchemaHandle::from_root_schema::<Self>(
-                        #schema_name,
-                        #type_name,
-                        root,
-                    )
-                })
+                #schema_body
             }
         }
     };
 
     expanded.into()
 }
+
+fn doc_pairs(docs: &[(String, String)]) -> Vec<proc_macro2::TokenStream> {
+    docs.iter()
+        .map(|(name, doc)| {
+            let name_lit = LitStr::new(name, Span::call_site());
+            let doc_lit = LitStr::new(doc, Span::call_site());
+            quote! { (#name_lit, #doc_lit) }
+        })
+        .collect()
+}
@@ -1,5 +1,6 @@
 mod completion_schema;
 mod schema_extraction;
+mod tool_attribute;
 
 use proc_macro::TokenStream;
 use quote::quote;
@@ -8,6 +9,9 @@ use syn::parse_macro_input;
 /// Defines the `tool!` macro for declaring tools.
 /// Generates a `Tool` impl with JSON Schema from `params`
 /// and wires an async closure as the executor.
+///
+/// With `state = Type,` the generated struct holds that state, gets a `new(state)` constructor,
+/// and the closure takes `|state: &Type, params: Params|`.
 #[proc_macro]
 pub fn tool(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as ToolDefinition);
@@ -19,24 +23,71 @@ pub fn tool(input: TokenStream) -> TokenStream {
 
     // Convert snake_case to PascalCase for struct name
     // tbd if needed long term idk
-    let struct_name = name
-        .value()
-        .split('_')
-        .filter(|part| !part.is_empty())
-        .map(|part| {
-            let mut chars = part.chars();
-            match chars.next() {
-                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
-                None => String::new(),
-            }
-        })
-        .collect::<String>();
+    let struct_name = pascal_case(&name.value());
 
     let tool_struct = quote::format_ident!("{}", struct_name);
 
+    let (struct_def, run_handler) = match input.state_type {
+        Some(state_type) => {
+            let mut inputs = execute_body.inputs.iter();
+            let (Some(state_pat), Some(params_pat), None) =
+                (inputs.next(), inputs.next(), inputs.next())
+            else {
+                return syn::Error::new_spanned(
+                    &execute_body.inputs,
+                    "a tool with `state` takes a closure `|state: &State, params: Params|`",
+                )
+                .to_compile_error()
+                .into();
+            };
+            let body = &execute_body.body;
+
+            (
+                quote! {
+                    pub struct #tool_struct {
+                        state: #state_type,
+                    }
+
+                    impl #tool_struct {
+                        /// Create the tool around the state passed to every call
+                        pub fn new(state: #state_type) -> Self {
+                            Self { state }
+                        }
+                    }
+
+                    impl std::fmt::Debug for #tool_struct {
+                        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                            f.debug_struct(stringify!(#tool_struct)).finish_non_exhaustive()
+                        }
+                    }
+                },
+                // The closure is inlined rather than called: a closure returning a future that
+                // borrows its `&State` argument cannot be typed
+                quote! {
+                    let #state_pat = &self.state;
+                    let #params_pat = params;
+                    (#body)
+                        .await
+                        .map_err(|e| tiny_agent_rs::AgentError::ToolExecution(e))
+                },
+            )
+        }
+        None => (
+            quote! {
+                #[derive(Debug)]
+                pub struct #tool_struct;
+            },
+            quote! {
+                let handler = #execute_body;
+                handler(params)
+                    .await
+                    .map_err(|e| tiny_agent_rs::AgentError::ToolExecution(e))
+            },
+        ),
+    };
+
     let expanded = quote! {
-        #[derive(Debug)]
-        pub struct #tool_struct;
+        #struct_def
 
         impl tiny_agent_rs::tools::Tool for #tool_struct {
             fn name(&self) -> &'static str {
@@ -74,10 +125,7 @@ pub fn tool(input: TokenStream) -> TokenStream {
                             format!("Invalid parameters for {}: {}", #name, e)
                         ))?;
 
-                    let handler = #execute_body;
-                    handler(params)
-                        .await
-                        .map_err(|e| tiny_agent_rs::AgentError::ToolExecution(e))
+                    #run_handler
                 })
             }
         }
@@ -90,6 +138,7 @@ struct ToolDefinition {
     name: syn::LitStr,
     description: syn::LitStr,
     params_type: syn::Type,
+    state_type: Option<syn::Type>,
     execute_body: syn::ExprClosure,
 }
 
@@ -115,12 +164,18 @@ impl syn::parse::Parse for ToolDefinition {
         let name = parse_named_assignment::<syn::LitStr>(input, "name")?;
         let description = parse_named_assignment::<syn::LitStr>(input, "description")?;
         let params_type = parse_named_assignment::<syn::Type>(input, "params")?;
+        let state_type = if input.peek(syn::Ident) && input.peek2(syn::Token![=]) {
+            Some(parse_named_assignment::<syn::Type>(input, "state")?)
+        } else {
+            None
+        };
         let execute_body: syn::ExprClosure = input.parse()?;
 
         Ok(ToolDefinition {
             name,
             description,
             params_type,
+            state_type,
             execute_body,
         })
     }
@@ -130,3 +185,30 @@ impl syn::parse::Parse for ToolDefinition {
 pub fn completion_schema(attr: TokenStream, item: TokenStream) -> TokenStream {
     completion_schema::completion_schema(attr, item)
 }
+
+/// Declares a tool from an `async fn`; re-exported as `tiny_agent_rs::tools::tool`.
+///
+/// The function's doc comment describes the tool and its arguments become the parameter
+/// schema, documented by their own doc comments. The function returns
+/// `Result<T: Serialize, E: Display>`. An argument marked `#[state]`, `#[state] store: &Store`,
+/// is not shown to the model; the generated struct holds the state and passes it to every call.
+/// A `&ToolContext` argument receives the context of the run that made the call. Any other
+/// argument must be owned.
+#[proc_macro_attribute]
+pub fn tool_attribute(attr: TokenStream, item: TokenStream) -> TokenStream {
+    tool_attribute::tool(attr, item)
+}
+
+/// `snake_case` tool name to the `PascalCase` name of the generated struct
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
@@ -1,7 +1,8 @@
 use proc_macro2::Span;
 use syn::{
-    parse::Parser, punctuated::Punctuated, spanned::Spanned, Attribute, Expr, ExprLit, Fields,
-    ItemStruct, Lit, LitStr, MetaNameValue, Token,
+    parse::Parser, parse_quote, punctuated::Punctuated, spanned::Spanned, Attribute, Expr, ExprLit,
+    Fields, GenericParam, Generics, Ident, ItemEnum, ItemStruct, Lit, LitStr, Meta, MetaNameValue,
+    Token,
 };
 
 #[derive(Default)]
@@ -68,24 +69,63 @@ pub fn parse_completion_schema_args(
     Ok(result)
 }
 
-pub fn ensure_named_struct(item: &ItemStruct) -> syn::Result<()> {
+pub fn ensure_supported_struct(item: &ItemStruct) -> syn::Result<()> {
     match &item.fields {
-        Fields::Named(_) => Ok(()),
-        _ => Err(syn::Error::new(
+        Fields::Named(_) | Fields::Unnamed(_) => Ok(()),
+        Fields::Unit => Err(syn::Error::new(
             item.struct_token.span(),
-            "`#[completion_schema]` only supports structs with named fields",
+            "`#[completion_schema]` does not support unit structs",
         )),
     }
 }
 
+/// Add the bounds `CompletionSchema` needs to every type parameter.
+///
+/// Lifetimes are rejected because completion schemas are deserialized into owned values.
+pub fn completion_schema_generics(generics: &Generics) -> syn::Result<Generics> {
+    let mut bounded = generics.clone();
+
+    for param in &mut bounded.params {
+        match param {
+            GenericParam::Lifetime(lifetime) => {
+                return Err(syn::Error::new(
+                    lifetime.span(),
+                    "`#[completion_schema]` does not support lifetime parameters",
+                ));
+            }
+            GenericParam::Type(type_param) => {
+                type_param.bounds.push(parse_quote!(schemars::JsonSchema));
+                type_param
+                    .bounds
+                    .push(parse_quote!(serde::de::DeserializeOwned));
+                type_param.bounds.push(parse_quote!(Send));
+                type_param.bounds.push(parse_quote!(Sync));
+                type_param.bounds.push(parse_quote!('static));
+            }
+            GenericParam::Const(_) => {}
+        }
+    }
+
+    Ok(bounded)
+}
+
 pub fn collect_doc_comments(attrs: &[Attribute]) -> Option<String> {
     let mut docs = Vec::new();
 
     for attr in attrs {
-        if attr.path().is_ident("doc") {
-            if let Ok(lit) = attr.parse_args::<LitStr>() {
-                docs.push(lit.value().trim().to_string());
-            }
+        if !attr.path().is_ident("doc") {
+            continue;
+        }
+
+        // `/// text` arrives as `#[doc = "text"]`
+        if let Meta::NameValue(MetaNameValue {
+            value: Expr::Lit(ExprLit {
+                lit: Lit::Str(lit), ..
+            }),
+            ..
+        }) = &attr.meta
+        {
+            docs.push(lit.value().trim().to_string());
         }
     }
 
@@ -112,12 +152,22 @@ pub fn collect_field_docs(item: &ItemStruct) -> Vec<(String, String)> {
     results
 }
 
-pub fn infer_schema_name(item: &ItemStruct, explicit: Option<&LitStr>) -> LitStr {
+/// Doc comments on enum variants, keyed by variant name
+pub fn collect_variant_docs(item: &ItemEnum) -> Vec<(String, String)> {
+    item.variants
+        .iter()
+        .filter_map(|variant| {
+            collect_doc_comments(&variant.attrs).map(|doc| (variant.ident.to_string(), doc))
+        })
+        .collect()
+}
+
+pub fn infer_schema_name(ident: &Ident, explicit: Option<&LitStr>) -> LitStr {
     if let Some(explicit) = explicit {
         return explicit.clone();
     }
 
-    LitStr::new(&item.ident.to_string(), Span::call_site())
+    LitStr::new(&ident.to_string(), Span::call_site())
 }
 
 pub fn infer_description(explicit: Option<&LitStr>, doc: Option<String>) -> Option<LitStr> {
@@ -0,0 +1,332 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, FnArg, ItemFn, LitStr, Pat, PatType, Type,
+};
+
+use crate::pascal_case;
+use crate::schema_extraction::{collect_doc_comments, parse_completion_schema_args};
+
+/// A model-supplied argument of the annotated function
+struct ToolArgument {
+    ident: syn::Ident,
+    ty: Box<Type>,
+    attrs: Vec<Attribute>,
+}
+
+/// What the generated tool passes for each argument of the annotated function
+enum CallArgument {
+    State,
+    Context,
+    Param(syn::Ident),
+}
+
+/// Arguments of the annotated function, sorted by where their values come from
+struct SplitArguments {
+    state: Option<Type>,
+    params: Vec<ToolArgument>,
+    call: Vec<CallArgument>,
+}
+
+pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    // Accepts the same `name = "...", description = "..."` overrides as `#[completion_schema]`
+    let args = match parse_completion_schema_args(attr) {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let mut function = parse_macro_input!(item as ItemFn);
+
+    match expand(&mut function, args.name, args.description) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(
+    function: &mut ItemFn,
+    name: Option<LitStr>,
+    description: Option<LitStr>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let signature = &function.sig;
+    if signature.asyncness.is_none() {
+        return Err(syn::Error::new(
+            signature.fn_token.span(),
+            "`#[tool]` requires an `async fn`",
+        ));
+    }
+    if !signature.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            signature.generics.span(),
+            "`#[tool]` does not support generic functions",
+        ));
+    }
+
+    let fn_ident = signature.ident.clone();
+    let name = name.unwrap_or_else(|| LitStr::new(&fn_ident.to_string(), fn_ident.span()));
+    let description = match description.or_else(|| {
+        collect_doc_comments(&function.attrs).map(|doc| LitStr::new(&doc, Span::call_site()))
+    }) {
+        Some(description) => description,
+        None => {
+            return Err(syn::Error::new(
+                fn_ident.span(),
+                "`#[tool]` needs a doc comment or `description = \"...\"` to describe the tool",
+            ))
+        }
+    };
+
+    let SplitArguments {
+        state,
+        params: arguments,
+        call: call_arguments,
+    } = split_arguments(function)?;
+    let uses_context = call_arguments
+        .iter()
+        .any(|argument| matches!(argument, CallArgument::Context));
+
+    let vis = &function.vis;
+    let tool_struct = format_ident!("{}", pascal_case(&name.value()));
+    let params_struct = format_ident!("{}Params", tool_struct);
+
+    let param_fields = arguments.iter().map(|argument| {
+        let ToolArgument { ident, ty, attrs } = argument;
+        quote! { #(#attrs)* #ident: #ty }
+    });
+    let call_arguments = call_arguments.iter().map(|argument| match argument {
+        CallArgument::State => quote! { &self.state },
+        CallArgument::Context => quote! { &context },
+        CallArgument::Param(ident) => quote! { params.#ident },
+    });
+    let call = quote! { #fn_ident(#(#call_arguments),*) };
+
+    let (struct_def, constructor) = match &state {
+        Some(state_ty) => (
+            quote! {
+                #vis struct #tool_struct {
+                    state: #state_ty,
+                }
+
+                impl std::fmt::Debug for #tool_struct {
+                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        f.debug_struct(stringify!(#tool_struct)).finish_non_exhaustive()
+                    }
+                }
+            },
+            quote! {
+                /// Create the tool around the state passed to every call
+                #[allow(dead_code)]
+                #vis fn new(state: #state_ty) -> Self {
+                    Self { state }
+                }
+            },
+        ),
+        None => (
+            quote! {
+                #[derive(Debug, Default, Clone, Copy)]
+                #vis struct #tool_struct;
+            },
+            quote! {
+                #[allow(dead_code)]
+                #vis fn new() -> Self {
+                    Self
+                }
+            },
+        ),
+    };
+
+    let future_type = |output: proc_macro2::TokenStream| {
+        quote! {
+            std::pin::Pin<
+                Box<
+                    dyn std::future::Future<Output = Result<#output, tiny_agent_rs::AgentError>>
+                        + Send
+                        + '_,
+                >,
+            >
+        }
+    };
+    let value_future = future_type(quote! { serde_json::Value });
+    let response_future = future_type(quote! { tiny_agent_rs::tools::ToolResponse });
+    let parse_params = quote! {
+        let params: #params_struct = serde_json::from_value(parameters)
+            .map_err(|e| tiny_agent_rs::AgentError::ToolExecution(
+                format!("Invalid parameters for {}: {}", #name, e)
+            ))?;
+    };
+
+    // Functions taking a `&ToolContext` get the run's context; called directly they get an
+    // empty one. The agent loop calls `execute_with_context`, so a returned `ToolResult` reaches
+    // it intact.
+    let bind_context = (!uses_context).then(|| quote! { let _ = context; });
+    let execute = quote! {
+        fn execute(&self, parameters: serde_json::Value) -> #value_future {
+            Box::pin(async move {
+                self.execute_with_context(parameters, tiny_agent_rs::tools::ToolContext::default())
+                    .await
+                    .map(tiny_agent_rs::tools::ToolResponse::into_value)
+            })
+        }
+
+        fn execute_with_context(
+            &self,
+            parameters: serde_json::Value,
+            context: tiny_agent_rs::tools::ToolContext,
+        ) -> #response_future {
+            Box::pin(async move {
+                #bind_context
+                #parse_params
+                tiny_agent_rs::tools::tool_output(#call.await)
+            })
+        }
+    };
+
+    Ok(quote! {
+        #function
+
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        #[doc = concat!("Parameters of the `", #name, "` tool")]
+        #vis struct #params_struct {
+            #(#param_fields),*
+        }
+
+        #struct_def
+
+        impl #tool_struct {
+            #constructor
+        }
+
+        impl tiny_agent_rs::tools::Tool for #tool_struct {
+            fn name(&self) -> &'static str {
+                #name
+            }
+
+            fn description(&self) -> &'static str {
+                #description
+            }
+
+            fn parameters_schema(&self) -> serde_json::Value {
+                let schema = schemars::schema_for!(#params_struct);
+                let mut parameters = serde_json::to_value(&schema.schema).unwrap_or_else(|_| {
+                    serde_json::json!({
+                        "type": "object",
+                        "properties": {},
+                        "required": []
+                    })
+                });
+                // `$ref`s point at `#/definitions/...`, so the definitions move to the root
+                if !schema.definitions.is_empty() {
+                    if let Ok(definitions) = serde_json::to_value(&schema.definitions) {
+                        parameters["definitions"] = definitions;
+                    }
+                }
+                parameters
+            }
+
+            #execute
+        }
+    })
+}
+
+/// Separate the optional `#[state] &State` argument and any `&ToolContext` argument from the
+/// arguments the model fills in.
+///
+/// Attributes on model arguments (doc comments, `#[serde(...)]`) move to the parameters struct;
+/// the `#[state]` marker is removed from the function.
+fn split_arguments(function: &mut ItemFn) -> syn::Result<SplitArguments> {
+    let mut split = SplitArguments {
+        state: None,
+        params: Vec::new(),
+        call: Vec::new(),
+    };
+
+    for input in function.sig.inputs.iter_mut() {
+        let PatType { attrs, pat, ty, .. } = match input {
+            FnArg::Typed(typed) => typed,
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new(
+                    receiver.span(),
+                    "`#[tool]` works on free functions; take the state as a `#[state] &State` argument instead of `self`",
+                ))
+            }
+        };
+
+        let marked_state = take_state_marker(attrs);
+        if let Type::Reference(reference) = ty.as_ref() {
+            if !marked_state && reference.mutability.is_none() && is_tool_context(&reference.elem) {
+                if split
+                    .call
+                    .iter()
+                    .any(|argument| matches!(argument, CallArgument::Context))
+                {
+                    return Err(syn::Error::new(
+                        ty.span(),
+                        "`#[tool]` functions take at most one `&ToolContext` argument",
+                    ));
+                }
+                split.call.push(CallArgument::Context);
+                continue;
+            }
+            if !marked_state {
+                return Err(syn::Error::new(
+                    ty.span(),
+                    "model arguments must be owned (`String`, not `&str`); mark the tool's state argument with `#[state]`",
+                ));
+            }
+            if reference.mutability.is_some() || split.state.is_some() {
+                return Err(syn::Error::new(
+                    ty.span(),
+                    "`#[tool]` functions take at most one `#[state]` argument, as a shared reference",
+                ));
+            }
+            split.state = Some(reference.elem.as_ref().clone());
+            split.call.push(CallArgument::State);
+            continue;
+        }
+        if marked_state {
+            return Err(syn::Error::new(
+                ty.span(),
+                "the `#[state]` argument must be a shared reference, `&State`",
+            ));
+        }
+
+        let ident = match pat.as_ref() {
+            Pat::Ident(pat_ident) => pat_ident.ident.clone(),
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    "`#[tool]` arguments must be plain identifiers",
+                ))
+            }
+        };
+
+        split.call.push(CallArgument::Param(ident.clone()));
+        split.params.push(ToolArgument {
+            ident,
+            ty: ty.clone(),
+            attrs: std::mem::take(attrs),
+        });
+    }
+
+    Ok(split)
+}
+
+/// Remove a `#[state]` marker from an argument's attributes, returning whether it had one
+fn take_state_marker(attrs: &mut Vec<Attribute>) -> bool {
+    let before = attrs.len();
+    attrs.retain(|attr| !attr.path().is_ident("state"));
+    attrs.len() != before
+}
+
+/// Whether `ty` names `ToolContext`, with or without a path
+fn is_tool_context(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "ToolContext"),
+        _ => false,
+    }
+}